@@ -1,3 +1,8 @@
+use std::fmt;
+use std::ops::Add;
+
+use crate::span::Span;
+
 #[derive(Debug)]
 pub struct Stylesheet {
     pub rules: Vec<Rule>,
@@ -7,31 +12,500 @@ pub struct Stylesheet {
 pub struct Rule {
     pub selectors: Vec<Selector>,
     pub declarations: Vec<Declaration>,
+    /// From the first selector to the closing '}'
+    pub span: Span,
 }
 
-#[derive(Debug)]
+/// A complex selector, e.g `nav > ul li + li`
+#[derive(Debug, Clone)]
 pub struct Selector {
+    /// From left to right, never empty. The last one is the subject
+    pub compounds: Vec<CompoundSelector>,
+    /// `combinators[i]` is between `compounds[i]` and `compounds[i + 1]`
+    pub combinators: Vec<Combinator>,
+    /// e.g `li::marker`, the selector then applies to that part of the subject
+    pub pseudo_element: Option<PseudoElement>,
+    pub span: Span,
+}
+
+impl Selector {
+    /// https://drafts.csswg.org/selectors/#specificity-rules
+    pub fn specificity(&self) -> Specificity {
+        // A pseudo-element counts as a type
+        let pseudo_element = Specificity(0, 0, self.pseudo_element.iter().count() as u32);
+
+        self.compounds
+            .iter()
+            .map(CompoundSelector::specificity)
+            .fold(pseudo_element, Add::add)
+    }
+}
+
+/// The simple selectors of one element, e.g `a#home.nav[href]`
+#[derive(Debug, Clone, Default)]
+pub struct CompoundSelector {
     /// Can be '*' for universal
     pub tag_name: Option<String>,
     /// #...
     pub identifier: Option<String>,
     /// .x.y.z
-    pub class_names: Option<Vec<String>>,
-    /// div h1
-    pub descendant: Option<Box<Selector>>,
-    /// div > h1
-    pub child: Option<Box<Selector>>,
+    pub class_names: Vec<String>,
+    /// [href^="https"][lang|=en]
+    pub attributes: Vec<AttributeSelector>,
+    /// :first-child:not(.x)
+    pub pseudo_classes: Vec<PseudoClass>,
 }
 
-impl Selector {
-    pub fn new() -> Selector {
-        return Selector {
-            tag_name: None,
-            identifier: None,
-            class_names: None,
-            descendant: None,
-            child: None,
+impl CompoundSelector {
+    pub fn is_empty(&self) -> bool {
+        self.tag_name.is_none()
+            && self.identifier.is_none()
+            && self.class_names.is_empty()
+            && self.attributes.is_empty()
+            && self.pseudo_classes.is_empty()
+    }
+
+    pub fn specificity(&self) -> Specificity {
+        let ids = self.identifier.iter().count();
+        // Attribute selectors count as classes
+        let classes = self.class_names.len() + self.attributes.len();
+        // The universal selector doesn't count
+        let types = self.tag_name.iter().filter(|name| *name != "*").count();
+
+        self.pseudo_classes.iter().fold(
+            Specificity(ids as u32, classes as u32, types as u32),
+            |specificity, pseudo_class| specificity + pseudo_class.specificity(),
+        )
+    }
+}
+
+/// https://drafts.csswg.org/selectors/#pseudo-classes
+#[derive(Debug, Clone)]
+pub enum PseudoClass {
+    Root,
+    Empty,
+    FirstChild,
+    LastChild,
+    OnlyChild,
+    FirstOfType,
+    LastOfType,
+    OnlyOfType,
+    NthChild(Nth),
+    NthLastChild(Nth),
+    NthOfType(Nth),
+    NthLastOfType(Nth),
+    /// Matches none of the selectors
+    Not(Vec<Selector>),
+    /// Matches any of the selectors, the invalid ones are dropped
+    Is(Vec<Selector>),
+    /// Like :is() but without specificity
+    Where(Vec<Selector>),
+    /// Has an element matching one of the selectors, relative to this one
+    Has(Vec<RelativeSelector>),
+    /// <a> and <area> with a href
+    AnyLink,
+    /// An :any-link which isn't :visited
+    Link,
+    /// A form control which isn't :disabled
+    Enabled,
+    /// Decided by the caller, e.g :hover
+    State(ElementState),
+}
+
+impl PseudoClass {
+    /// https://drafts.csswg.org/selectors/#specificity-rules
+    pub fn specificity(&self) -> Specificity {
+        match self {
+            PseudoClass::Not(selectors) | PseudoClass::Is(selectors) => selectors
+                .iter()
+                .map(Selector::specificity)
+                .max()
+                .unwrap_or_default(),
+            PseudoClass::Has(selectors) => selectors
+                .iter()
+                .map(|relative| relative.selector.specificity())
+                .max()
+                .unwrap_or_default(),
+            PseudoClass::Where(_) => Specificity::default(),
+            _ => Specificity(0, 1, 0),
+        }
+    }
+}
+
+/// The pseudo-classes depending on the user or on the state of form controls
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ElementState {
+    Hover,
+    Active,
+    Focus,
+    FocusWithin,
+    FocusVisible,
+    Visited,
+    Target,
+    Checked,
+    Disabled,
+}
+
+/// https://drafts.csswg.org/css-syntax/#anb-microsyntax, the elements whose
+/// 1-based index is `a * n + b` for some n >= 0
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Nth {
+    pub a: i32,
+    pub b: i32,
+}
+
+impl Nth {
+    pub fn matches(&self, index: i32) -> bool {
+        if self.a == 0 {
+            return index == self.b;
+        }
+
+        let n = index - self.b;
+
+        n % self.a == 0 && n / self.a >= 0
+    }
+}
+
+/// The argument of :has(), e.g `> img`
+#[derive(Debug, Clone)]
+pub struct RelativeSelector {
+    /// Relation to the element :has() applies to, Descendant when omitted
+    pub combinator: Combinator,
+    pub selector: Selector,
+}
+
+/// https://drafts.csswg.org/css-pseudo/#treelike
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PseudoElement {
+    Before,
+    After,
+    Marker,
+    FirstLine,
+    FirstLetter,
+}
+
+impl PseudoElement {
+    /// The name following '::', also accepted after a single ':' when `legacy` is set
+    pub fn from_name(name: &str, legacy: bool) -> Option<PseudoElement> {
+        let pseudo_element = match name.to_ascii_lowercase().as_str() {
+            "before" => PseudoElement::Before,
+            "after" => PseudoElement::After,
+            "first-line" => PseudoElement::FirstLine,
+            "first-letter" => PseudoElement::FirstLetter,
+            "marker" if !legacy => PseudoElement::Marker,
+            _ => return None,
         };
+
+        Some(pseudo_element)
+    }
+}
+
+/// https://drafts.csswg.org/selectors/#combinators
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Combinator {
+    /// div p
+    Descendant,
+    /// div > p
+    Child,
+    /// h1 + p
+    NextSibling,
+    /// h1 ~ p
+    SubsequentSibling,
+}
+
+/// https://drafts.csswg.org/selectors/#attribute-selectors
+#[derive(Debug, Clone)]
+pub struct AttributeSelector {
+    /// The part before '|' in [ns|name], None without one
+    pub namespace: Option<NamespacePrefix>,
+    pub name: String,
+    /// None when only the presence of the attribute is tested
+    pub matcher: Option<AttributeMatcher>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NamespacePrefix {
+    /// [*|name]
+    Any,
+    /// [|name]
+    None,
+    /// [xlink|href]
+    Named(String),
+}
+
+/// The `=value i` part of an attribute selector
+#[derive(Debug, Clone)]
+pub struct AttributeMatcher {
+    pub operator: AttributeOperator,
+    pub value: String,
+    /// Set by the 'i' and 's' flags, None lets the document language decide
+    pub case_sensitivity: Option<CaseSensitivity>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AttributeOperator {
+    /// =
+    Equals,
+    /// ~=, one of the whitespace separated words
+    Includes,
+    /// |=, the value alone or followed by '-'
+    DashMatch,
+    /// ^=
+    Prefix,
+    /// $=
+    Suffix,
+    /// *=
+    Substring,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CaseSensitivity {
+    CaseSensitive,
+    AsciiCaseInsensitive,
+}
+
+/// https://drafts.csswg.org/cssom/#serialize-a-selector
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, compound) in self.compounds.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", self.combinators[i - 1])?;
+            }
+
+            write!(f, "{}", compound)?;
+        }
+
+        if let Some(pseudo_element) = self.pseudo_element {
+            write!(f, "{}", pseudo_element)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for PseudoElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PseudoElement::Before => "::before",
+            PseudoElement::After => "::after",
+            PseudoElement::Marker => "::marker",
+            PseudoElement::FirstLine => "::first-line",
+            PseudoElement::FirstLetter => "::first-letter",
+        })
+    }
+}
+
+impl fmt::Display for CompoundSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tag_name.as_deref() {
+            Some("*") => f.write_str("*")?,
+            Some(tag_name) => write_identifier(f, tag_name)?,
+            None => (),
+        }
+
+        if let Some(identifier) = &self.identifier {
+            f.write_str("#")?;
+            write_identifier(f, identifier)?;
+        }
+
+        for class_name in &self.class_names {
+            f.write_str(".")?;
+            write_identifier(f, class_name)?;
+        }
+
+        for attribute in &self.attributes {
+            write!(f, "{}", attribute)?;
+        }
+
+        for pseudo_class in &self.pseudo_classes {
+            write!(f, "{}", pseudo_class)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for PseudoClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let write_list = |f: &mut fmt::Formatter<'_>, name: &str, selectors: &[Selector]| {
+            write!(f, ":{}(", name)?;
+
+            for (i, selector) in selectors.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+
+                write!(f, "{}", selector)?;
+            }
+
+            f.write_str(")")
+        };
+
+        match self {
+            PseudoClass::Root => f.write_str(":root"),
+            PseudoClass::Empty => f.write_str(":empty"),
+            PseudoClass::FirstChild => f.write_str(":first-child"),
+            PseudoClass::LastChild => f.write_str(":last-child"),
+            PseudoClass::OnlyChild => f.write_str(":only-child"),
+            PseudoClass::FirstOfType => f.write_str(":first-of-type"),
+            PseudoClass::LastOfType => f.write_str(":last-of-type"),
+            PseudoClass::OnlyOfType => f.write_str(":only-of-type"),
+            PseudoClass::NthChild(nth) => write!(f, ":nth-child({})", nth),
+            PseudoClass::NthLastChild(nth) => write!(f, ":nth-last-child({})", nth),
+            PseudoClass::NthOfType(nth) => write!(f, ":nth-of-type({})", nth),
+            PseudoClass::NthLastOfType(nth) => write!(f, ":nth-last-of-type({})", nth),
+            PseudoClass::Not(selectors) => write_list(f, "not", selectors),
+            PseudoClass::Is(selectors) => write_list(f, "is", selectors),
+            PseudoClass::Where(selectors) => write_list(f, "where", selectors),
+            PseudoClass::Has(selectors) => {
+                f.write_str(":has(")?;
+
+                for (i, relative) in selectors.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+
+                    match relative.combinator {
+                        Combinator::Descendant => (),
+                        combinator => write!(f, "{}", combinator.to_string().trim_start())?,
+                    }
+
+                    write!(f, "{}", relative.selector)?;
+                }
+
+                f.write_str(")")
+            }
+            PseudoClass::AnyLink => f.write_str(":any-link"),
+            PseudoClass::Link => f.write_str(":link"),
+            PseudoClass::Enabled => f.write_str(":enabled"),
+            PseudoClass::State(state) => f.write_str(match state {
+                ElementState::Hover => ":hover",
+                ElementState::Active => ":active",
+                ElementState::Focus => ":focus",
+                ElementState::FocusWithin => ":focus-within",
+                ElementState::FocusVisible => ":focus-visible",
+                ElementState::Visited => ":visited",
+                ElementState::Target => ":target",
+                ElementState::Checked => ":checked",
+                ElementState::Disabled => ":disabled",
+            }),
+        }
+    }
+}
+
+/// https://drafts.csswg.org/css-syntax/#serializing-anb
+impl fmt::Display for Nth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.a {
+            0 => return write!(f, "{}", self.b),
+            1 => f.write_str("n")?,
+            -1 => f.write_str("-n")?,
+            a => write!(f, "{}n", a)?,
+        }
+
+        match self.b {
+            0 => Ok(()),
+            b if b > 0 => write!(f, "+{}", b),
+            b => write!(f, "{}", b),
+        }
+    }
+}
+
+impl fmt::Display for Combinator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Combinator::Descendant => " ",
+            Combinator::Child => " > ",
+            Combinator::NextSibling => " + ",
+            Combinator::SubsequentSibling => " ~ ",
+        })
+    }
+}
+
+impl fmt::Display for AttributeSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[")?;
+
+        match &self.namespace {
+            Some(NamespacePrefix::Any) => f.write_str("*|")?,
+            Some(NamespacePrefix::None) => f.write_str("|")?,
+            Some(NamespacePrefix::Named(prefix)) => {
+                write_identifier(f, prefix)?;
+                f.write_str("|")?;
+            }
+            None => (),
+        }
+
+        write_identifier(f, &self.name)?;
+
+        if let Some(matcher) = &self.matcher {
+            f.write_str(match matcher.operator {
+                AttributeOperator::Equals => "=",
+                AttributeOperator::Includes => "~=",
+                AttributeOperator::DashMatch => "|=",
+                AttributeOperator::Prefix => "^=",
+                AttributeOperator::Suffix => "$=",
+                AttributeOperator::Substring => "*=",
+            })?;
+
+            write_string(f, &matcher.value)?;
+
+            match matcher.case_sensitivity {
+                Some(CaseSensitivity::AsciiCaseInsensitive) => f.write_str(" i")?,
+                Some(CaseSensitivity::CaseSensitive) => f.write_str(" s")?,
+                None => (),
+            }
+        }
+
+        f.write_str("]")
+    }
+}
+
+/// https://drafts.csswg.org/cssom/#serialize-an-identifier
+fn write_identifier(f: &mut fmt::Formatter<'_>, identifier: &str) -> fmt::Result {
+    let starts_with_dash = identifier.starts_with('-');
+
+    for (i, c) in identifier.chars().enumerate() {
+        match c {
+            '\0' => f.write_str("\u{FFFD}")?,
+            '\u{1}'..='\u{1F}' | '\u{7F}' => write!(f, "\\{:x} ", c as u32)?,
+            '0'..='9' if i == 0 || (i == 1 && starts_with_dash) => write!(f, "\\{:x} ", c as u32)?,
+            '-' if identifier.len() == 1 => f.write_str("\\-")?,
+            c if c >= '\u{80}' || c == '-' || c == '_' || c.is_ascii_alphanumeric() => {
+                write!(f, "{}", c)?
+            }
+            c => write!(f, "\\{}", c)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// https://drafts.csswg.org/cssom/#serialize-a-string
+fn write_string(f: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+    f.write_str("\"")?;
+
+    for c in string.chars() {
+        match c {
+            '\0' => f.write_str("\u{FFFD}")?,
+            '\u{1}'..='\u{1F}' | '\u{7F}' => write!(f, "\\{:x} ", c as u32)?,
+            '"' | '\\' => write!(f, "\\{}", c)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+
+    f.write_str("\"")
+}
+
+/// The (ids, classes, types) counts of a selector, compared in that order
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Specificity(pub u32, pub u32, pub u32);
+
+impl Add for Specificity {
+    type Output = Specificity;
+
+    fn add(self, other: Specificity) -> Specificity {
+        Specificity(self.0 + other.0, self.1 + other.1, self.2 + other.2)
     }
 }
 
@@ -39,29 +513,53 @@ impl Selector {
 pub struct Declaration {
     pub name: String,
     pub value: CSSValue,
+    /// color: red !important
+    pub important: bool,
+    /// From the name to the end of the value, without the ';'
+    pub span: Span,
+}
+
+impl Default for Declaration {
+    fn default() -> Self {
+        Declaration::new()
+    }
 }
 
 impl Declaration {
     pub fn new() -> Declaration {
-        return Declaration {
+        Declaration {
             name: String::new(),
             value: CSSValue::Keyword(String::new()),
-        };
+            important: false,
+            span: Span::default(),
+        }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum CSSValue {
     Keyword(String),
+    /// e.g rgb(0, 0, 0), an anonymous `( ... )` block has an empty name
     Function(String, Vec<CSSValue>),
     StringLiteral(String),
     Number(Number),
     NumberWithUnit(Number, String),
     Percentage(Number),
+    /// e.g #ffffff, stored without the leading '#'
     Color(String),
+    /// e.g 1px solid black
     List(Vec<CSSValue>),
+    /// e.g "SF Pro Display", sans-serif
     CommaSeparatedList(Vec<CSSValue>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Number(pub String);
+
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    /// e.g invalid-selector, invalid-declaration
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+}
@@ -1,42 +1,95 @@
-use std::vec;
+use std::mem;
 
-use self::interface::{CSSValue, Declaration, Number, Rule, Selector, Stylesheet};
+use self::interface::{
+    AttributeMatcher, AttributeOperator, AttributeSelector, CSSValue, CaseSensitivity, Combinator,
+    CompoundSelector, Declaration, ElementState, NamespacePrefix, Nth, Number, ParseError,
+    PseudoClass, PseudoElement, RelativeSelector, Rule, Selector, Stylesheet,
+};
 use self::tokenizer::{Token, Tokenizer};
+use crate::span::Span;
 
-mod interface;
+pub mod interface;
 mod tokenizer;
 
-pub fn parse(input: &str) -> Stylesheet {
+/// Parses a stylesheet, dropping the rules and declarations it can't understand
+pub fn parse(input: &str) -> (Stylesheet, Vec<ParseError>) {
     let tokenizer = Tokenizer::new(input);
     let mut parser = Parser::new(tokenizer);
 
-    parser.parse_stylesheet()
+    let stylesheet = parser.parse_stylesheet();
+
+    (stylesheet, parser.errors)
+}
+
+/// Parses a list of declarations without the braces around, e.g the value of
+/// a `style` attribute
+pub fn parse_declarations(input: &str) -> (Vec<Declaration>, Vec<ParseError>) {
+    let tokenizer = Tokenizer::new(input);
+    let mut parser = Parser::new(tokenizer);
+
+    let declarations = parser.parse_declaration_list(false);
+
+    (declarations, parser.errors)
+}
+
+/// Parses a comma separated list of selectors, e.g the argument of `query_selector`
+pub fn parse_selectors(input: &str) -> Result<Vec<Selector>, ParseError> {
+    let tokenizer = Tokenizer::new(input);
+    let mut parser = Parser::new(tokenizer);
+
+    let selectors = parser.parse_selectors()?;
+
+    match parser.tokenizer.next() {
+        Token::EOS => Ok(selectors),
+        t => Err(parser.unexpected_token_error("invalid-selector", "a selector", &t)),
+    }
 }
 
 struct Parser<'a> {
     tokenizer: Tokenizer<'a>,
+    errors: Vec<ParseError>,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(tokenizer: Tokenizer) -> Parser {
-        Parser { tokenizer }
+        Parser {
+            tokenizer,
+            errors: vec![],
+        }
     }
 
     fn parse_stylesheet(&mut self) -> Stylesheet {
         let mut rules: Vec<Rule> = vec![];
 
         loop {
+            self.skip_whitespace();
+
             match self.tokenizer.peek() {
                 Token::EOS => break,
 
-                _ => {
-                    let selectors = self.parse_selectors();
-                    let declarations = self.parse_declarations();
+                // Only there to hide stylesheets from legacy browsers
+                Token::CDO | Token::CDC => {
+                    self.tokenizer.next();
+                }
 
-                    rules.push(Rule {
-                        selectors,
-                        declarations,
-                    })
+                // At-rules aren't supported yet, so they are skipped as a whole
+                Token::AtKeyword(_) => {
+                    self.tokenizer.next();
+                    self.skip_until(|t| matches!(t, Token::SemiColon | Token::OpenCurly));
+                    self.skip_block();
+                }
+
+                Token::CloseCurly => {
+                    let t = self.tokenizer.next();
+                    let error = self.unexpected_token_error("unexpected-token", "a rule", &t);
+
+                    self.errors.push(error);
+                }
+
+                _ => {
+                    if let Some(rule) = self.parse_rule() {
+                        rules.push(rule);
+                    }
                 }
             }
         }
@@ -44,105 +97,921 @@ impl<'a> Parser<'a> {
         Stylesheet { rules }
     }
 
-    fn parse_selectors(&mut self) -> Vec<Selector> {
+    fn parse_rule(&mut self) -> Option<Rule> {
+        let selectors = match self.parse_selectors() {
+            Ok(selectors) => selectors,
+            Err(error) => {
+                self.errors.push(error);
+                self.skip_until(|t| matches!(t, Token::OpenCurly));
+                self.skip_block();
+
+                return None;
+            }
+        };
+
+        if let Token::EOS = self.tokenizer.peek() {
+            let t = self.tokenizer.next();
+            let error = self.unexpected_token_error("eof-in-rule", "'{'", &t);
+
+            self.errors.push(error);
+            return None;
+        }
+
+        let declarations = self.parse_declarations();
+        let span = selectors[0].span.to(self.tokenizer.span());
+
+        Some(Rule {
+            selectors,
+            declarations,
+            span,
+        })
+    }
+
+    /// https://drafts.csswg.org/selectors/#selector-list, an empty item makes
+    /// the whole list invalid
+    fn parse_selectors(&mut self) -> Result<Vec<Selector>, ParseError> {
         let mut selectors: Vec<Selector> = vec![];
 
         loop {
+            selectors.push(self.parse_selector()?);
+
             match self.tokenizer.peek() {
-                Token::EOS => break,
+                Token::EOS | Token::OpenCurly => break,
 
-                Token::Comma | Token::OpenCurly => {
+                Token::Comma => {
                     self.tokenizer.next();
-                    selectors.push(self.parse_selector());
                 }
 
-                _ => {
-                    selectors.push(self.parse_selector());
-                }
+                _ => return Err(self.unexpected_next_token_error("invalid-selector", "',' or '{'")),
             }
         }
 
-        selectors
+        Ok(selectors)
     }
 
-    fn parse_selector(&mut self) -> Selector {
-        let mut selector = Selector::new();
+    fn parse_selector(&mut self) -> Result<Selector, ParseError> {
+        self.skip_whitespace();
 
-        for i in 0.. {
-            match self.tokenizer.peek() {
-                Token::Comma | Token::OpenCurly | Token::EOS => break,
+        let start = self.tokenizer.peek_span().start;
+        let (compound, mut pseudo_element) = self.parse_compound_selector()?;
+        let mut compounds = vec![compound];
+        let mut combinators = vec![];
 
-                _ => (),
+        loop {
+            let whitespace = matches!(self.tokenizer.peek(), Token::WhiteSpace(_));
+
+            self.skip_whitespace();
+
+            let combinator = match self.tokenizer.peek() {
+                Token::Comma | Token::OpenCurly | Token::CloseBracket | Token::EOS => break,
+
+                // Nothing can follow a pseudo-element
+                _ if pseudo_element.is_some() => {
+                    return Err(self.unexpected_next_token_error(
+                        "invalid-selector",
+                        "the end of the selector",
+                    ))
+                }
+
+                Token::CloseAngle | Token::Plus | Token::Tilde => {
+                    let combinator = match self.tokenizer.next() {
+                        Token::CloseAngle => Combinator::Child,
+                        Token::Plus => Combinator::NextSibling,
+                        _ => Combinator::SubsequentSibling,
+                    };
+
+                    self.skip_whitespace();
+                    combinator
+                }
+
+                _ if whitespace => Combinator::Descendant,
+
+                _ => return Err(self.unexpected_next_token_error("invalid-selector", "a selector")),
+            };
+
+            let (compound, compound_pseudo_element) = self.parse_compound_selector()?;
+
+            combinators.push(combinator);
+            compounds.push(compound);
+            pseudo_element = compound_pseudo_element;
+        }
+
+        Ok(Selector {
+            compounds,
+            combinators,
+            pseudo_element,
+            span: Span::new(start, self.tokenizer.end()),
+        })
+    }
+
+    /// A type selector followed by ids, classes, attribute selectors and
+    /// pseudo-classes, then an optional pseudo-element
+    fn parse_compound_selector(
+        &mut self,
+    ) -> Result<(CompoundSelector, Option<PseudoElement>), ParseError> {
+        let mut compound = CompoundSelector::default();
+        let mut pseudo_element = None;
+
+        match self.tokenizer.peek() {
+            Token::Ident(_) => compound.tag_name = Some(self.expect_ident("a tag name")?),
+
+            Token::Asterisk => {
+                self.tokenizer.next();
+                compound.tag_name = Some(String::from("*"));
             }
 
-            match self.tokenizer.next() {
-                Token::Hash(identifier) => {
-                    selector.identifier = Some(identifier);
+            _ => (),
+        }
+
+        loop {
+            match self.tokenizer.peek() {
+                Token::Hash(_) => {
+                    if let Token::Hash(identifier) = self.tokenizer.next() {
+                        compound.identifier = Some(identifier);
+                    }
                 }
 
                 Token::Dot => {
-                    match self.tokenizer.next() {
-                        Token::Ident(class_name) => {
-                            selector
-                                .class_names
-                                .get_or_insert_with(|| vec![])
-                                .push(class_name);
-                        }
+                    self.tokenizer.next();
+
+                    let class_name = self.expect_ident("a class name")?;
+                    compound.class_names.push(class_name);
+                }
 
-                        t => panic!("Expected token: {:?}", t),
+                Token::OpenSquare => {
+                    self.tokenizer.next();
+
+                    let attribute = self.parse_attribute_selector()?;
+                    compound.attributes.push(attribute);
+                }
+
+                Token::Colon => {
+                    self.tokenizer.next();
+
+                    if let Token::Colon = self.tokenizer.peek() {
+                        self.tokenizer.next();
+                        pseudo_element = Some(self.parse_pseudo_element()?);
+                        break;
+                    }
+
+                    // :before, :after, :first-line and :first-letter predate the '::' syntax
+                    let legacy = match self.tokenizer.peek() {
+                        Token::Ident(name) => PseudoElement::from_name(name, true),
+                        _ => None,
                     };
+
+                    if legacy.is_some() {
+                        self.tokenizer.next();
+                        pseudo_element = legacy;
+                        break;
+                    }
+
+                    let pseudo_class = self.parse_pseudo_class()?;
+                    compound.pseudo_classes.push(pseudo_class);
                 }
 
-                Token::Ident(tag_name) => {
-                    selector.tag_name = Some(tag_name);
+                _ => break,
+            }
+        }
+
+        if compound.is_empty() && pseudo_element.is_none() {
+            return Err(self.unexpected_next_token_error("invalid-selector", "a selector"));
+        }
+
+        Ok((compound, pseudo_element))
+    }
+
+    /// Parses what follows the '::' of a pseudo-element
+    fn parse_pseudo_element(&mut self) -> Result<PseudoElement, ParseError> {
+        let name = match self.tokenizer.next() {
+            Token::Ident(name) => name,
+            t => {
+                return Err(self.unexpected_token_error("invalid-selector", "a pseudo-element", &t))
+            }
+        };
+
+        match PseudoElement::from_name(&name, false) {
+            Some(pseudo_element) => Ok(pseudo_element),
+            None => Err(self.error(
+                "invalid-selector",
+                format!("Unknown pseudo-element ::{}", name),
+            )),
+        }
+    }
+
+    /// Parses what follows the ':' of a pseudo-class. An invalid functional
+    /// pseudo-class is consumed up to its closing ')', so that the forgiving
+    /// selector lists can recover after it
+    fn parse_pseudo_class(&mut self) -> Result<PseudoClass, ParseError> {
+        let (name, is_function) = match self.tokenizer.next() {
+            Token::Ident(name) => (name, false),
+            Token::Function(name) => (name, true),
+            t => return Err(self.unexpected_token_error("invalid-selector", "a pseudo-class", &t)),
+        };
+
+        let pseudo_class = self.parse_pseudo_class_arguments(&name, is_function);
+
+        if is_function && pseudo_class.is_err() {
+            self.skip_until(|t| matches!(t, Token::CloseBracket));
+            self.tokenizer.next();
+        }
+
+        pseudo_class
+    }
+
+    fn parse_pseudo_class_arguments(
+        &mut self,
+        name: &str,
+        is_function: bool,
+    ) -> Result<PseudoClass, ParseError> {
+        let pseudo_class = match (name.to_ascii_lowercase().as_str(), is_function) {
+            ("root", false) => PseudoClass::Root,
+            ("empty", false) => PseudoClass::Empty,
+            ("first-child", false) => PseudoClass::FirstChild,
+            ("last-child", false) => PseudoClass::LastChild,
+            ("only-child", false) => PseudoClass::OnlyChild,
+            ("first-of-type", false) => PseudoClass::FirstOfType,
+            ("last-of-type", false) => PseudoClass::LastOfType,
+            ("only-of-type", false) => PseudoClass::OnlyOfType,
+            ("any-link", false) => PseudoClass::AnyLink,
+            ("link", false) => PseudoClass::Link,
+            ("enabled", false) => PseudoClass::Enabled,
+            ("hover", false) => PseudoClass::State(ElementState::Hover),
+            ("active", false) => PseudoClass::State(ElementState::Active),
+            ("focus", false) => PseudoClass::State(ElementState::Focus),
+            ("focus-within", false) => PseudoClass::State(ElementState::FocusWithin),
+            ("focus-visible", false) => PseudoClass::State(ElementState::FocusVisible),
+            ("visited", false) => PseudoClass::State(ElementState::Visited),
+            ("target", false) => PseudoClass::State(ElementState::Target),
+            ("checked", false) => PseudoClass::State(ElementState::Checked),
+            ("disabled", false) => PseudoClass::State(ElementState::Disabled),
+
+            ("nth-child", true) => PseudoClass::NthChild(self.parse_nth()?),
+            ("nth-last-child", true) => PseudoClass::NthLastChild(self.parse_nth()?),
+            ("nth-of-type", true) => PseudoClass::NthOfType(self.parse_nth()?),
+            ("nth-last-of-type", true) => PseudoClass::NthLastOfType(self.parse_nth()?),
+            ("not", true) => PseudoClass::Not(self.parse_arguments(Parser::parse_nested_selector)?),
+            ("is", true) => PseudoClass::Is(self.parse_forgiving_selectors()?),
+            ("where", true) => PseudoClass::Where(self.parse_forgiving_selectors()?),
+            ("has", true) => {
+                PseudoClass::Has(self.parse_arguments(Parser::parse_relative_selector)?)
+            }
+
+            _ => {
+                let message = format!(
+                    "Unknown pseudo-class :{}{}",
+                    name,
+                    if is_function { "()" } else { "" }
+                );
+
+                return Err(self.error("invalid-selector", message));
+            }
+        };
+
+        Ok(pseudo_class)
+    }
+
+    /// Parses the An+B argument and the closing ')'
+    fn parse_nth(&mut self) -> Result<Nth, ParseError> {
+        let mut argument = String::new();
+
+        loop {
+            match self.tokenizer.peek() {
+                Token::CloseBracket => break,
+                Token::EOS => {
+                    return Err(self.error(
+                        "invalid-selector",
+                        String::from("Expected ')', but found the end of file"),
+                    ))
                 }
+                Token::WhiteSpace(_) => argument.push(' '),
+                t => argument.push_str(&t.to_string()),
+            }
+
+            self.tokenizer.next();
+        }
 
-                Token::CloseAngle => {
-                    selector.child = Some(Box::new(self.parse_selector()));
+        match parse_nth(&argument) {
+            Some(nth) => {
+                self.tokenizer.next();
+                Ok(nth)
+            }
+            None => Err(self.error(
+                "invalid-selector",
+                format!("Expected An+B, but found \"{}\"", argument.trim()),
+            )),
+        }
+    }
+
+    /// Comma separated items up to the closing ')'
+    fn parse_arguments<T>(
+        &mut self,
+        parse: fn(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut arguments = vec![];
+
+        loop {
+            arguments.push(parse(self)?);
+
+            match self.tokenizer.peek() {
+                Token::Comma => self.tokenizer.next(),
+                Token::CloseBracket => {
+                    self.tokenizer.next();
+                    return Ok(arguments);
                 }
+                _ => return Err(self.unexpected_next_token_error("invalid-selector", "',' or ')'")),
+            };
+        }
+    }
+
+    /// https://drafts.csswg.org/selectors/#forgiving-selector, the invalid selectors are dropped
+    fn parse_forgiving_selectors(&mut self) -> Result<Vec<Selector>, ParseError> {
+        let selectors = self.parse_arguments(|parser| match parser.parse_nested_selector() {
+            Ok(selector) => Ok(Some(selector)),
+            Err(_) => {
+                parser.skip_until(|t| matches!(t, Token::Comma | Token::CloseBracket));
+                Ok(None)
+            }
+        })?;
+
+        Ok(selectors.into_iter().flatten().collect())
+    }
+
+    /// A selector starting with an optional combinator, e.g `> img`
+    fn parse_relative_selector(&mut self) -> Result<RelativeSelector, ParseError> {
+        self.skip_whitespace();
+
+        let combinator = match self.tokenizer.peek() {
+            Token::CloseAngle => Combinator::Child,
+            Token::Plus => Combinator::NextSibling,
+            Token::Tilde => Combinator::SubsequentSibling,
+            _ => Combinator::Descendant,
+        };
+
+        if combinator != Combinator::Descendant {
+            self.tokenizer.next();
+        }
+
+        Ok(RelativeSelector {
+            combinator,
+            selector: self.parse_nested_selector()?,
+        })
+    }
+
+    /// A selector in a pseudo-class argument, which can't have a pseudo-element
+    fn parse_nested_selector(&mut self) -> Result<Selector, ParseError> {
+        let selector = self.parse_selector()?;
+
+        match selector.pseudo_element {
+            Some(pseudo_element) => Err(self.error(
+                "invalid-selector",
+                format!("Unexpected {} in a pseudo-class argument", pseudo_element),
+            )),
+            None => Ok(selector),
+        }
+    }
+
+    /// Parses the inside of `[ ... ]`, whose '[' has been consumed
+    fn parse_attribute_selector(&mut self) -> Result<AttributeSelector, ParseError> {
+        let mut namespace = None;
+        let mut operator = None;
+
+        self.skip_whitespace();
+
+        let mut name = match self.tokenizer.next() {
+            Token::Ident(name) => name,
+
+            Token::Asterisk => {
+                match self.tokenizer.next() {
+                    Token::Pipe => (),
+                    t => return Err(self.unexpected_token_error("invalid-selector", "'|'", &t)),
+                }
+
+                namespace = Some(NamespacePrefix::Any);
+                self.expect_ident("an attribute name")?
+            }
+
+            Token::Pipe => {
+                namespace = Some(NamespacePrefix::None);
+                self.expect_ident("an attribute name")?
+            }
+
+            t => {
+                return Err(self.unexpected_token_error(
+                    "invalid-selector",
+                    "an attribute name",
+                    &t,
+                ))
+            }
+        };
+
+        // Either [ns|name] or the start of [name|=value]
+        if namespace.is_none() && matches!(self.tokenizer.peek(), Token::Pipe) {
+            self.tokenizer.next();
+
+            match self.tokenizer.next() {
+                Token::Ident(local_name) => {
+                    let prefix = mem::replace(&mut name, local_name);
+                    namespace = Some(NamespacePrefix::Named(prefix));
+                }
+
+                Token::Equals => operator = Some(AttributeOperator::DashMatch),
+
+                t => {
+                    return Err(self.unexpected_token_error(
+                        "invalid-selector",
+                        "an attribute name or '='",
+                        &t,
+                    ))
+                }
+            }
+        }
+
+        self.skip_whitespace();
+
+        let operator = match operator {
+            Some(operator) => operator,
+            None => match self.tokenizer.next() {
+                Token::CloseSquare => {
+                    return Ok(AttributeSelector {
+                        namespace,
+                        name,
+                        matcher: None,
+                    })
+                }
+
+                Token::Equals => AttributeOperator::Equals,
+
+                t @ (Token::Tilde
+                | Token::Pipe
+                | Token::Caret
+                | Token::Dollar
+                | Token::Asterisk) => {
+                    match self.tokenizer.next() {
+                        Token::Equals => (),
+                        t => {
+                            return Err(self.unexpected_token_error("invalid-selector", "'='", &t))
+                        }
+                    }
 
-                Token::WhiteSpace(_) => {
-                    if i != 0 && self.is_selector_start() {
-                        selector.descendant = Some(Box::new(self.parse_selector()));
+                    match t {
+                        Token::Tilde => AttributeOperator::Includes,
+                        Token::Pipe => AttributeOperator::DashMatch,
+                        Token::Caret => AttributeOperator::Prefix,
+                        Token::Dollar => AttributeOperator::Suffix,
+                        _ => AttributeOperator::Substring,
                     }
                 }
 
-                t => panic!("Expected token: {:?}", t),
+                t => {
+                    return Err(self.unexpected_token_error(
+                        "invalid-selector",
+                        "an attribute operator or ']'",
+                        &t,
+                    ))
+                }
+            },
+        };
+
+        self.skip_whitespace();
+
+        let value = match self.tokenizer.next() {
+            Token::Ident(value) | Token::String(value) => value,
+            t => {
+                return Err(self.unexpected_token_error(
+                    "invalid-selector",
+                    "an attribute value",
+                    &t,
+                ))
             }
+        };
+
+        self.skip_whitespace();
+
+        let mut case_sensitivity = None;
+
+        if let Token::Ident(flag) = self.tokenizer.peek() {
+            case_sensitivity = match flag.to_ascii_lowercase().as_str() {
+                "i" => Some(CaseSensitivity::AsciiCaseInsensitive),
+                "s" => Some(CaseSensitivity::CaseSensitive),
+                _ => None,
+            };
+
+            let t = self.tokenizer.next();
+
+            if case_sensitivity.is_none() {
+                return Err(self.unexpected_token_error("invalid-selector", "'i' or 's'", &t));
+            }
+
+            self.skip_whitespace();
         }
 
-        selector
+        match self.tokenizer.next() {
+            Token::CloseSquare => Ok(AttributeSelector {
+                namespace,
+                name,
+                matcher: Some(AttributeMatcher {
+                    operator,
+                    value,
+                    case_sensitivity,
+                }),
+            }),
+            t => Err(self.unexpected_token_error("invalid-selector", "']'", &t)),
+        }
     }
 
     fn parse_declarations(&mut self) -> Vec<Declaration> {
+        match self.tokenizer.next() {
+            Token::OpenCurly => self.parse_declaration_list(true),
+            _ => vec![],
+        }
+    }
+
+    /// Declarations up to the '}' closing the block, or up to the end of file
+    /// outside of one
+    fn parse_declaration_list(&mut self, in_block: bool) -> Vec<Declaration> {
         let mut declarations: Vec<Declaration> = vec![];
 
+        loop {
+            self.skip_whitespace();
+
+            match self.tokenizer.peek() {
+                Token::EOS => {
+                    let t = self.tokenizer.next();
+
+                    if in_block {
+                        let error = self.unexpected_token_error("eof-in-block", "'}'", &t);
+                        self.errors.push(error);
+                    }
+
+                    break;
+                }
+
+                Token::CloseCurly if in_block => {
+                    self.tokenizer.next();
+                    break;
+                }
+
+                Token::SemiColon => {
+                    self.tokenizer.next();
+                }
+
+                _ => match self.parse_declaration() {
+                    Ok(declaration) => declarations.push(declaration),
+                    Err(error) => {
+                        self.errors.push(error);
+                        self.skip_until(|t| matches!(t, Token::SemiColon | Token::CloseCurly));
+                    }
+                },
+            }
+        }
+
         declarations
     }
 
-    fn skip_whitespace(&mut self) {
+    fn parse_declaration(&mut self) -> Result<Declaration, ParseError> {
+        let mut declaration = Declaration::new();
+
+        match self.tokenizer.next() {
+            // Custom property names are case-sensitive
+            Token::Ident(name) if name.starts_with("--") => {
+                declaration.name = name;
+                declaration.span = self.tokenizer.span();
+            }
+            Token::Ident(name) => {
+                declaration.name = name.to_ascii_lowercase();
+                declaration.span = self.tokenizer.span();
+            }
+            t => {
+                return Err(self.unexpected_token_error(
+                    "invalid-declaration",
+                    "a property name",
+                    &t,
+                ))
+            }
+        }
+
+        self.skip_whitespace();
+
+        // The offending token isn't consumed, it may be the '}' closing the block
+        match self.tokenizer.peek() {
+            Token::Colon => self.tokenizer.next(),
+            _ => return Err(self.unexpected_next_token_error("invalid-declaration", "':'")),
+        };
+
+        self.tokenizer.allow_unicode_ranges(true);
+        let value = self.parse_value();
+        self.tokenizer.allow_unicode_ranges(false);
+
+        declaration.value = value?;
+
+        if let CSSValue::List(values) = &declaration.value {
+            // Custom properties are the only ones allowed to be empty
+            if values.is_empty() && !declaration.name.starts_with("--") {
+                return Err(self.unexpected_next_token_error("invalid-declaration", "a value"));
+            }
+        }
+
+        if let Token::Exclamation = self.tokenizer.peek() {
+            self.tokenizer.next();
+            self.skip_whitespace();
+
+            match self.tokenizer.peek() {
+                Token::Ident(ident) if ident.eq_ignore_ascii_case("important") => {
+                    self.tokenizer.next();
+                    declaration.important = true;
+                }
+
+                _ => {
+                    return Err(
+                        self.unexpected_next_token_error("invalid-declaration", "'important'")
+                    )
+                }
+            }
+
+            self.skip_whitespace();
+        }
+
+        declaration.span.end = self.tokenizer.end();
+
+        match self.tokenizer.peek() {
+            Token::SemiColon | Token::CloseCurly | Token::EOS => Ok(declaration),
+            _ => {
+                let t = self.tokenizer.next();
+                Err(self.unexpected_token_error("invalid-declaration", "';'", &t))
+            }
+        }
+    }
+
+    /// Parses a value up to the end of the declaration, e.g `"SF Pro Display", sans-serif`
+    fn parse_value(&mut self) -> Result<CSSValue, ParseError> {
+        let mut values: Vec<CSSValue> = vec![];
+
         loop {
+            values.push(self.parse_space_separated_value()?);
+
             match self.tokenizer.peek() {
-                Token::WhiteSpace(_) => {
+                Token::Comma => {
                     self.tokenizer.next();
                 }
 
                 _ => break,
             }
         }
+
+        Ok(flatten(values, CSSValue::CommaSeparatedList))
     }
 
-    fn is_selector_start(&mut self) -> bool {
-        match self.tokenizer.peek() {
-            Token::Ident(_) | Token::Dot | Token::Hash(_) => true,
-            _ => false,
+    /// Parses a value up to the next comma, e.g `1px solid black`
+    fn parse_space_separated_value(&mut self) -> Result<CSSValue, ParseError> {
+        let mut values: Vec<CSSValue> = vec![];
+
+        loop {
+            self.skip_whitespace();
+
+            match self.tokenizer.peek() {
+                Token::Comma
+                | Token::SemiColon
+                | Token::CloseCurly
+                | Token::CloseBracket
+                | Token::Exclamation
+                | Token::EOS => break,
+
+                _ => values.push(self.parse_component_value()?),
+            }
         }
+
+        Ok(flatten(values, CSSValue::List))
     }
 
-    fn unexpected_token_error(&self, expected: &Token, unexpected: &Token) {
-        panic!(
-            "Expected token: \"{:?}\", but found \"{:?}\"",
-            expected, unexpected
-        );
+    fn parse_component_value(&mut self) -> Result<CSSValue, ParseError> {
+        let value = match self.tokenizer.next() {
+            Token::Ident(name) => CSSValue::Keyword(name),
+
+            Token::Function(name) => self.parse_function(name)?,
+
+            Token::Url(url) => {
+                CSSValue::Function(String::from("url"), vec![CSSValue::StringLiteral(url)])
+            }
+
+            Token::Number(number) => CSSValue::Number(Number(number)),
+
+            Token::Dimension(number, unit) => {
+                CSSValue::NumberWithUnit(Number(number), unit.to_ascii_lowercase())
+            }
+
+            Token::Percentage(number) => CSSValue::Percentage(Number(number)),
+
+            Token::String(string) => CSSValue::StringLiteral(string),
+
+            Token::Hash(color) => CSSValue::Color(color),
+
+            Token::OpenBracket => self.parse_function(String::new())?,
+
+            Token::BadString => {
+                return Err(self.error("bad-string", String::from("Unclosed string")));
+            }
+
+            Token::BadUrl => {
+                return Err(self.error("bad-url", String::from("Invalid character in url()")));
+            }
+
+            Token::BadEscape => {
+                return Err(self.error("bad-escape", String::from("Escaped newline")));
+            }
+
+            t => CSSValue::Keyword(t.to_string()),
+        };
+
+        Ok(value)
+    }
+
+    /// Parses the arguments of a function whose opening bracket has been consumed
+    fn parse_function(&mut self, name: String) -> Result<CSSValue, ParseError> {
+        let mut arguments: Vec<CSSValue> = vec![];
+
+        loop {
+            self.skip_whitespace();
+
+            if let Token::CloseBracket = self.tokenizer.peek() {
+                self.tokenizer.next();
+                break;
+            }
+
+            arguments.push(self.parse_space_separated_value()?);
+
+            match self.tokenizer.peek() {
+                Token::Comma => {
+                    self.tokenizer.next();
+                }
+
+                Token::CloseBracket => {
+                    self.tokenizer.next();
+                    break;
+                }
+
+                // An unclosed function ends with the declaration
+                _ => break,
+            }
+        }
+
+        Ok(CSSValue::Function(name, arguments))
+    }
+
+    /// Skips tokens up to the first one matching at the top level, nested blocks are skipped whole
+    fn skip_until(&mut self, stop: fn(&Token) -> bool) {
+        let mut depth: usize = 0;
+
+        loop {
+            let t = self.tokenizer.peek();
+
+            if let Token::EOS = t {
+                break;
+            }
+
+            if depth == 0 && stop(t) {
+                break;
+            }
+
+            match t {
+                Token::OpenCurly | Token::OpenBracket | Token::OpenSquare | Token::Function(_) => {
+                    depth += 1
+                }
+
+                Token::CloseCurly if depth == 0 => break,
+
+                Token::CloseCurly | Token::CloseBracket | Token::CloseSquare => {
+                    depth = depth.saturating_sub(1)
+                }
+
+                _ => (),
+            }
+
+            self.tokenizer.next();
+        }
+    }
+
+    /// Skips a `{ ... }` block, or the ';' ending a statement
+    fn skip_block(&mut self) {
+        if let Token::OpenCurly = self.tokenizer.next() {
+            self.skip_until(|t| matches!(t, Token::CloseCurly));
+            self.tokenizer.next();
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Token::WhiteSpace(_) = self.tokenizer.peek() {
+            self.tokenizer.next();
+        }
+    }
+
+    fn expect_ident(&mut self, expected: &str) -> Result<String, ParseError> {
+        match self.tokenizer.next() {
+            Token::Ident(ident) => Ok(ident),
+            t => Err(self.unexpected_token_error("invalid-selector", expected, &t)),
+        }
+    }
+
+    fn unexpected_token_error(
+        &self,
+        code: &'static str,
+        expected: &str,
+        unexpected: &Token,
+    ) -> ParseError {
+        let message = match unexpected {
+            Token::EOS => format!("Expected {}, but found the end of file", expected),
+            t => format!("Expected {}, but found \"{}\"", expected, t),
+        };
+
+        self.error(code, message)
+    }
+
+    /// An error at the next token, which isn't consumed so that the caller can
+    /// recover from it
+    fn unexpected_next_token_error(&mut self, code: &'static str, expected: &str) -> ParseError {
+        let span = self.tokenizer.peek_span();
+        let message = match self.tokenizer.peek() {
+            Token::EOS => format!("Expected {}, but found the end of file", expected),
+            t => format!("Expected {}, but found \"{}\"", expected, t),
+        };
+
+        ParseError {
+            code,
+            message,
+            span,
+        }
+    }
+
+    /// An error at the last token returned by the tokenizer
+    fn error(&self, code: &'static str, message: String) -> ParseError {
+        ParseError {
+            code,
+            message,
+            span: self.tokenizer.span(),
+        }
+    }
+}
+
+/// https://drafts.csswg.org/css-syntax/#anb-microsyntax, e.g odd, 2n+1, -n + 3
+fn parse_nth(argument: &str) -> Option<Nth> {
+    let argument = argument.trim().to_ascii_lowercase();
+
+    match argument.as_str() {
+        "odd" => return Some(Nth { a: 2, b: 1 }),
+        "even" => return Some(Nth { a: 2, b: 0 }),
+        _ => (),
+    }
+
+    // No whitespace is allowed in the integers, or between A and 'n'
+    let integer = |s: &str| -> Option<i32> {
+        let digits = s.strip_prefix(['+', '-']).unwrap_or(s);
+
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        s.parse().ok()
+    };
+
+    let (a, b) = match argument.split_once('n') {
+        Some((a, b)) => (a, b.trim_start()),
+        None => return integer(&argument).map(|b| Nth { a: 0, b }),
+    };
+
+    let a = match a {
+        "" | "+" => 1,
+        "-" => -1,
+        a => integer(a)?,
+    };
+
+    // The sign of B can be separated from its digits
+    let b = match b.chars().next() {
+        None => 0,
+        Some(sign @ ('+' | '-')) => {
+            let digits = b[1..].trim_start();
+
+            if digits.starts_with(['+', '-']) {
+                return None;
+            }
+
+            let b = integer(digits)?;
+
+            if sign == '-' {
+                -b
+            } else {
+                b
+            }
+        }
+        Some(_) => return None,
+    };
+
+    Some(Nth { a, b })
+}
+
+fn flatten(mut values: Vec<CSSValue>, list: fn(Vec<CSSValue>) -> CSSValue) -> CSSValue {
+    if values.len() == 1 {
+        values.remove(0)
+    } else {
+        list(values)
     }
 }
@@ -1,116 +1,632 @@
-use std::{iter::Peekable, str::Chars};
+use std::fmt;
 
+use crate::span::{Position, Span};
+
+/// Tokens of https://www.w3.org/TR/css-syntax-3/#tokenization
 #[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Token {
     WhiteSpace(String),
     Ident(String),
+    /// e.g rgb(
+    Function(String),
+    /// e.g @media
+    AtKeyword(String),
+    /// e.g url(image.png), the unquoted form only
+    Url(String),
     Comment(String),
     /// e.g #my-idx, #ffffff
     Hash(String),
-    /// e.g 42
+    /// e.g 42, +1.5, -2e3
     Number(String),
+    /// e.g 10px
+    Dimension(String, String),
+    /// e.g 50%
+    Percentage(String),
     /// e.g "SF Pro Display"
     String(String),
+    /// e.g U+0025-00FF, u+4??
+    UnicodeRange(u32, u32),
     OpenCurly,
     CloseCurly,
     OpenBracket,
     CloseBracket,
+    OpenSquare,
+    CloseSquare,
     Colon,
     SemiColon,
     Dot,
     CloseAngle,
     Comma,
     Asterisk,
-    Percentage,
+    Plus,
+    Tilde,
+    Equals,
+    Pipe,
+    Caret,
+    Dollar,
+    Exclamation,
+    /// Any other code point, e.g '/'
+    Delim(char),
+    /// <!--
+    CDO,
+    /// -->
+    CDC,
+    /// A string interrupted by a newline
+    BadString,
+    /// An url(...) containing a quote, bracket or bad escape
+    BadUrl,
+    /// A '\' which doesn't start a valid escape
+    BadEscape,
     /// END of source
     EOS,
 }
 
 pub struct Tokenizer<'a> {
-    source: Peekable<Chars<'a>>,
-    current: Option<Token>,
+    input: &'a str,
+    position: usize,
+    /// Byte offsets of the start of each line
+    line_starts: Vec<usize>,
+    /// Span of the last token returned by `next`
+    span: Span,
+    /// End of the last token returned by `next` which isn't whitespace
+    end: Position,
+    current: Option<(Span, Token)>,
+    /// Whether `U+...` is a unicode-range token rather than an ident and a '+',
+    /// which only matters in declaration values, e.g `u+a` is a selector
+    unicode_ranges: bool,
 }
 
 impl<'a> Tokenizer<'a> {
-    pub fn new(input: &str) -> Tokenizer {
+    pub fn new(input: &str) -> Tokenizer<'_> {
+        let line_starts = std::iter::once(0)
+            .chain(input.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
         Tokenizer {
-            source: input.chars().peekable(),
+            input,
+            position: 0,
+            line_starts,
+            span: Span::default(),
+            end: Position::default(),
             current: None,
+            unicode_ranges: false,
         }
     }
 
     pub fn peek(&mut self) -> &Token {
         if self.current.is_none() {
-            self.current = Some(self.consume_token());
+            self.current = Some(self.consume_significant_token());
         }
 
-        self.current.as_ref().unwrap()
+        &self.current.as_ref().unwrap().1
     }
 
     pub fn next(&mut self) -> Token {
-        match self.current.take() {
-            Some(t) => t,
-            _ => self.consume_token(),
+        let (span, token) = match self.current.take() {
+            Some(current) => current,
+            None => self.consume_significant_token(),
+        };
+
+        self.span = span;
+
+        if !matches!(token, Token::WhiteSpace(_)) {
+            self.end = span.end;
+        }
+
+        token
+    }
+
+    /// Span of the token `peek` returns
+    pub fn peek_span(&mut self) -> Span {
+        self.peek();
+
+        self.current.as_ref().unwrap().0
+    }
+
+    /// https://drafts.csswg.org/css-syntax/#consume-token, applies from the
+    /// next token which hasn't been peeked yet
+    pub fn allow_unicode_ranges(&mut self, allowed: bool) {
+        self.unicode_ranges = allowed;
+    }
+
+    /// Span of the last token returned by `next`
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// End of the last token returned by `next`, ignoring whitespace
+    pub fn end(&self) -> Position {
+        self.end
+    }
+
+    /// Comments are dropped here, so the parser never sees them
+    fn consume_significant_token(&mut self) -> (Span, Token) {
+        loop {
+            let start = self.position;
+
+            match self.consume_token() {
+                Token::Comment(_) => (),
+                t => {
+                    let span = Span::new(self.position_at(start), self.position_at(self.position));
+                    return (span, t);
+                }
+            }
+        }
+    }
+
+    fn position_at(&self, offset: usize) -> Position {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1];
+
+        Position {
+            line,
+            column: self.input[line_start..offset].chars().count() + 1,
+            offset,
         }
     }
 
     fn consume_token(&mut self) -> Token {
-        let current_char = match self.source.peek() {
+        let current_char = match self.peek_char(0) {
             None => return Token::EOS,
             Some(c) => c,
         };
 
         match current_char {
-            '\t' | '\n' | '\r' | '\x0C' | ' ' => Token::WhiteSpace(self.consume_whitespace()),
-            c if is_valid_start_ident(*c) => Token::Ident(self.consume_identifier()),
-            _ => match self.source.next() {
+            '/' if self.peek_char(1) == Some('*') => Token::Comment(self.consume_comment()),
+            c if is_whitespace(c) => Token::WhiteSpace(self.consume_whitespace()),
+            c if c.is_ascii_digit() => self.consume_numeric(),
+            '+' | '.' if self.starts_number() => self.consume_numeric(),
+            '-' if self.starts_number() => self.consume_numeric(),
+            '-' if self.input[self.position..].starts_with("-->") => {
+                self.position += 3;
+                Token::CDC
+            }
+            'u' | 'U' if self.unicode_ranges && self.starts_unicode_range() => {
+                self.consume_unicode_range()
+            }
+            '-' | '\\' if self.starts_identifier() => self.consume_ident_like(),
+            c if is_valid_start_ident(c) => self.consume_ident_like(),
+            '<' if self.input[self.position..].starts_with("<!--") => {
+                self.position += 4;
+                Token::CDO
+            }
+            _ => match self.next_char() {
                 Some(c) => match c {
-                    '#' => Token::Hash(self.consume_identifier()),
+                    '#' if self.peek_char(0).is_some_and(is_valid_ident)
+                        || self.starts_escape(0) =>
+                    {
+                        Token::Hash(self.consume_identifier())
+                    }
+                    '@' if self.starts_identifier() => Token::AtKeyword(self.consume_identifier()),
+                    '"' | '\'' => self.consume_string(c),
                     '{' => Token::OpenCurly,
                     '}' => Token::CloseCurly,
                     '(' => Token::OpenBracket,
                     ')' => Token::CloseBracket,
+                    '[' => Token::OpenSquare,
+                    ']' => Token::CloseSquare,
                     ':' => Token::Colon,
                     ';' => Token::SemiColon,
                     ',' => Token::Comma,
                     '>' => Token::CloseAngle,
                     '.' => Token::Dot,
                     '*' => Token::Asterisk,
-                    '%' => Token::Percentage,
-                    _ => Token::EOS,
+                    '+' => Token::Plus,
+                    '~' => Token::Tilde,
+                    '=' => Token::Equals,
+                    '|' => Token::Pipe,
+                    '^' => Token::Caret,
+                    '$' => Token::Dollar,
+                    '!' => Token::Exclamation,
+                    '\\' => Token::BadEscape,
+                    c => Token::Delim(c),
                 },
                 None => Token::EOS,
             },
         }
     }
 
+    fn consume_comment(&mut self) -> String {
+        self.position += 2;
+
+        let rest = &self.input[self.position..];
+        let (comment, length) = match rest.find("*/") {
+            Some(end) => (&rest[..end], end + 2),
+            None => (rest, rest.len()),
+        };
+
+        self.position += length;
+        String::from(comment)
+    }
+
     fn consume_identifier(&mut self) -> String {
-        self.consume_while(is_valid_ident)
+        let mut result = String::new();
+
+        loop {
+            match self.peek_char(0) {
+                Some(c) if is_valid_ident(c) => {
+                    self.next_char();
+                    result.push(c);
+                }
+
+                Some('\\') if self.starts_escape(0) => {
+                    self.next_char();
+                    result.push(self.consume_escape());
+                }
+
+                _ => break,
+            }
+        }
+
+        result
+    }
+
+    fn consume_ident_like(&mut self) -> Token {
+        let name = self.consume_identifier();
+
+        if self.peek_char(0) != Some('(') {
+            return Token::Ident(name);
+        }
+
+        self.next_char();
+
+        if !name.eq_ignore_ascii_case("url") {
+            return Token::Function(name);
+        }
+
+        while self.peek_char(0).is_some_and(is_whitespace)
+            && self.peek_char(1).is_some_and(is_whitespace)
+        {
+            self.next_char();
+        }
+
+        let quoted = |c: Option<char>| matches!(c, Some('"') | Some('\''));
+
+        if quoted(self.peek_char(0))
+            || (self.peek_char(0).is_some_and(is_whitespace) && quoted(self.peek_char(1)))
+        {
+            Token::Function(name)
+        } else {
+            self.consume_url()
+        }
+    }
+
+    fn consume_url(&mut self) -> Token {
+        let mut url = String::new();
+
+        self.consume_whitespace();
+
+        loop {
+            match self.next_char() {
+                Some(')') | None => return Token::Url(url),
+
+                Some(c) if is_whitespace(c) => {
+                    self.consume_whitespace();
+
+                    return match self.next_char() {
+                        Some(')') | None => Token::Url(url),
+                        _ => self.consume_bad_url(),
+                    };
+                }
+
+                Some('"') | Some('\'') | Some('(') => return self.consume_bad_url(),
+
+                Some(c) if is_non_printable(c) => return self.consume_bad_url(),
+
+                Some('\\') => {
+                    if self.starts_escape(-1) {
+                        url.push(self.consume_escape());
+                    } else {
+                        return self.consume_bad_url();
+                    }
+                }
+
+                Some(c) => url.push(c),
+            }
+        }
+    }
+
+    /// Consumes the remnants of a bad url up to the closing bracket
+    fn consume_bad_url(&mut self) -> Token {
+        loop {
+            match self.next_char() {
+                Some(')') | None => return Token::BadUrl,
+                Some('\\') if self.starts_escape(-1) => {
+                    self.consume_escape();
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn consume_numeric(&mut self) -> Token {
+        let number = self.consume_number();
+
+        if self.starts_identifier() {
+            Token::Dimension(number, self.consume_identifier())
+        } else if self.peek_char(0) == Some('%') {
+            self.next_char();
+            Token::Percentage(number)
+        } else {
+            Token::Number(number)
+        }
     }
 
     fn consume_number(&mut self) -> String {
-        self.consume_while(|c| match c {
-            '0'..='9' => true,
-            _ => false,
-        })
+        let mut result = String::new();
+
+        if let Some(c @ '+') | Some(c @ '-') = self.peek_char(0) {
+            self.next_char();
+            result.push(c);
+        }
+
+        result.push_str(&self.consume_digits());
+
+        if self.peek_char(0) == Some('.') && self.peek_char(1).is_some_and(|c| c.is_ascii_digit()) {
+            self.next_char();
+            result.push('.');
+            result.push_str(&self.consume_digits());
+        }
+
+        let exponent = match (self.peek_char(0), self.peek_char(1), self.peek_char(2)) {
+            (Some('e' | 'E'), Some(d), _) if d.is_ascii_digit() => 2,
+            (Some('e' | 'E'), Some('+' | '-'), Some(d)) if d.is_ascii_digit() => 3,
+            _ => 0,
+        };
+
+        if exponent > 0 {
+            for _ in 0..exponent {
+                result.push(self.next_char().unwrap());
+            }
+
+            result.push_str(&self.consume_digits());
+        }
+
+        result
+    }
+
+    fn consume_digits(&mut self) -> String {
+        self.consume_while(|c| c.is_ascii_digit())
+    }
+
+    fn consume_string(&mut self, quote: char) -> Token {
+        let mut result = String::new();
+
+        loop {
+            match self.peek_char(0) {
+                None => return Token::String(result),
+
+                Some(c) if c == quote => {
+                    self.next_char();
+                    return Token::String(result);
+                }
+
+                Some('\n' | '\r' | '\x0C') => return Token::BadString,
+
+                Some('\\') => {
+                    self.next_char();
+
+                    match self.peek_char(0) {
+                        None => (),
+                        Some('\n' | '\x0C') => {
+                            self.next_char();
+                        }
+                        Some('\r') => {
+                            self.next_char();
+                            if self.peek_char(0) == Some('\n') {
+                                self.next_char();
+                            }
+                        }
+                        Some(_) => result.push(self.consume_escape()),
+                    }
+                }
+
+                Some(c) => {
+                    self.next_char();
+                    result.push(c);
+                }
+            }
+        }
+    }
+
+    /// Consumes the code points after a '\', see https://www.w3.org/TR/css-syntax-3/#consume-escaped-code-point
+    fn consume_escape(&mut self) -> char {
+        let c = match self.next_char() {
+            None => return '\u{FFFD}',
+            Some(c) => c,
+        };
+
+        if !c.is_ascii_hexdigit() {
+            return c;
+        }
+
+        let mut hex = String::from(c);
+
+        while hex.len() < 6 && self.peek_char(0).is_some_and(|c| c.is_ascii_hexdigit()) {
+            hex.push(self.next_char().unwrap());
+        }
+
+        if self.peek_char(0).is_some_and(is_whitespace) {
+            if self.peek_char(0) == Some('\r') && self.peek_char(1) == Some('\n') {
+                self.next_char();
+            }
+            self.next_char();
+        }
+
+        match u32::from_str_radix(&hex, 16) {
+            Ok(0) | Err(_) => '\u{FFFD}',
+            Ok(code) => char::from_u32(code).unwrap_or('\u{FFFD}'),
+        }
+    }
+
+    fn consume_unicode_range(&mut self) -> Token {
+        self.position += 2;
+
+        let mut start = self.consume_while_max(|c| c.is_ascii_hexdigit(), 6);
+        let wildcards = self.consume_while_max(|c| c == '?', 6 - start.len());
+
+        if !wildcards.is_empty() {
+            let end = format!("{}{}", start, "F".repeat(wildcards.len()));
+            start.push_str(&"0".repeat(wildcards.len()));
+
+            return Token::UnicodeRange(parse_hex(&start), parse_hex(&end));
+        }
+
+        let start = parse_hex(&start);
+
+        if self.peek_char(0) == Some('-')
+            && self.peek_char(1).is_some_and(|c| c.is_ascii_hexdigit())
+        {
+            self.next_char();
+            let end = self.consume_while_max(|c| c.is_ascii_hexdigit(), 6);
+
+            return Token::UnicodeRange(start, parse_hex(&end));
+        }
+
+        Token::UnicodeRange(start, start)
     }
 
     fn consume_whitespace(&mut self) -> String {
-        self.consume_while(char::is_whitespace)
+        self.consume_while(is_whitespace)
     }
 
     fn consume_while<F>(&mut self, condition: F) -> String
+    where
+        F: Fn(char) -> bool,
+    {
+        self.consume_while_max(condition, usize::MAX)
+    }
+
+    fn consume_while_max<F>(&mut self, condition: F, max: usize) -> String
     where
         F: Fn(char) -> bool,
     {
         let mut result = String::new();
 
-        while self.source.peek().map_or(false, |c| condition(*c)) {
-            result.push(self.source.next().unwrap())
+        while result.len() < max && self.peek_char(0).is_some_and(&condition) {
+            result.push(self.next_char().unwrap())
         }
 
         result
     }
+
+    /// Whether a '\' at the given offset starts a valid escape
+    fn starts_escape(&self, offset: isize) -> bool {
+        let position = (self.position as isize + offset) as usize;
+        let mut chars = self.input[position..].chars();
+
+        chars.next() == Some('\\') && !matches!(chars.next(), None | Some('\n' | '\r' | '\x0C'))
+    }
+
+    /// https://www.w3.org/TR/css-syntax-3/#would-start-an-identifier
+    fn starts_identifier(&self) -> bool {
+        match self.peek_char(0) {
+            Some('-') => match self.peek_char(1) {
+                Some('-') => true,
+                Some(c) if is_valid_start_ident(c) => true,
+                _ => self.starts_escape(1),
+            },
+            Some('\\') => self.starts_escape(0),
+            Some(c) => is_valid_start_ident(c),
+            None => false,
+        }
+    }
+
+    /// https://www.w3.org/TR/css-syntax-3/#starts-with-a-number
+    fn starts_number(&self) -> bool {
+        let is_digit = |c: Option<char>| c.is_some_and(|c| c.is_ascii_digit());
+
+        match self.peek_char(0) {
+            Some('+' | '-') => {
+                is_digit(self.peek_char(1))
+                    || (self.peek_char(1) == Some('.') && is_digit(self.peek_char(2)))
+            }
+            Some('.') => is_digit(self.peek_char(1)),
+            c => is_digit(c),
+        }
+    }
+
+    fn starts_unicode_range(&self) -> bool {
+        self.peek_char(1) == Some('+')
+            && self
+                .peek_char(2)
+                .is_some_and(|c| c.is_ascii_hexdigit() || c == '?')
+    }
+
+    fn peek_char(&self, n: usize) -> Option<char> {
+        self.input[self.position..].chars().nth(n).map(preprocess)
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.input[self.position..].chars().next()?;
+        self.position += c.len_utf8();
+
+        Some(preprocess(c))
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::WhiteSpace(s) => write!(f, "{}", s),
+            Token::Ident(s) => write!(f, "{}", s),
+            Token::Function(s) => write!(f, "{}(", s),
+            Token::AtKeyword(s) => write!(f, "@{}", s),
+            Token::Url(s) => write!(f, "url({})", s),
+            Token::Comment(s) => write!(f, "/*{}*/", s),
+            Token::Hash(s) => write!(f, "#{}", s),
+            Token::Number(s) => write!(f, "{}", s),
+            Token::Dimension(s, unit) => write!(f, "{}{}", s, unit),
+            Token::Percentage(s) => write!(f, "{}%", s),
+            Token::String(s) => write!(f, "\"{}\"", s),
+            Token::UnicodeRange(start, end) if start == end => write!(f, "U+{:X}", start),
+            Token::UnicodeRange(start, end) => write!(f, "U+{:X}-{:X}", start, end),
+            Token::OpenCurly => write!(f, "{{"),
+            Token::CloseCurly => write!(f, "}}"),
+            Token::OpenBracket => write!(f, "("),
+            Token::CloseBracket => write!(f, ")"),
+            Token::OpenSquare => write!(f, "["),
+            Token::CloseSquare => write!(f, "]"),
+            Token::Colon => write!(f, ":"),
+            Token::SemiColon => write!(f, ";"),
+            Token::Dot => write!(f, "."),
+            Token::CloseAngle => write!(f, ">"),
+            Token::Comma => write!(f, ","),
+            Token::Asterisk => write!(f, "*"),
+            Token::Plus => write!(f, "+"),
+            Token::Tilde => write!(f, "~"),
+            Token::Equals => write!(f, "="),
+            Token::Pipe => write!(f, "|"),
+            Token::Caret => write!(f, "^"),
+            Token::Dollar => write!(f, "$"),
+            Token::Exclamation => write!(f, "!"),
+            Token::Delim(c) => write!(f, "{}", c),
+            Token::CDO => write!(f, "<!--"),
+            Token::CDC => write!(f, "-->"),
+            Token::BadString | Token::BadUrl | Token::BadEscape | Token::EOS => Ok(()),
+        }
+    }
+}
+
+/// https://www.w3.org/TR/css-syntax-3/#input-preprocessing
+fn preprocess(c: char) -> char {
+    match c {
+        '\0' => '\u{FFFD}',
+        c => c,
+    }
+}
+
+fn parse_hex(hex: &str) -> u32 {
+    u32::from_str_radix(hex, 16).unwrap_or(0)
+}
+
+fn is_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\x0C' | ' ')
+}
+
+fn is_non_printable(c: char) -> bool {
+    matches!(c, '\0'..='\x08' | '\x0B' | '\x0E'..='\x1F' | '\x7F')
 }
 
 fn is_valid_start_ident(c: char) -> bool {
@@ -118,7 +634,7 @@ fn is_valid_start_ident(c: char) -> bool {
 }
 
 fn is_valid_ident(c: char) -> bool {
-    is_valid_start_ident(c) || c.is_digit(10) || c == '-'
+    is_valid_start_ident(c) || c.is_ascii_digit() || c == '-'
 }
 
 fn is_letter(c: char) -> bool {
@@ -126,11 +642,11 @@ fn is_letter(c: char) -> bool {
 }
 
 fn is_upper_letter(c: char) -> bool {
-    c >= 'A' && c <= 'Z'
+    c.is_ascii_uppercase()
 }
 
 fn is_lower_letter(c: char) -> bool {
-    c >= 'a' && c <= 'z'
+    c.is_ascii_lowercase()
 }
 
 fn is_non_ascii(c: char) -> bool {
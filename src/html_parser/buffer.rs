@@ -1,27 +1,99 @@
 use std::collections::VecDeque;
 
+use crate::span::Position;
+
+/// The input characters not consumed yet, which can arrive in several chunks
 pub struct Buffer {
     vec: VecDeque<char>,
+    position: Position,
+    /// Whether the last chunk has been pushed
+    ended: bool,
 }
 
 impl Buffer {
-    pub fn new(input: &str) -> Buffer {
-        let mut vec: VecDeque<char> = VecDeque::new();
+    pub fn new() -> Buffer {
+        Buffer {
+            vec: VecDeque::new(),
+            position: Position::default(),
+            ended: false,
+        }
+    }
+
+    pub fn push_str(&mut self, chunk: &str) {
+        self.vec.extend(chunk.chars());
+    }
+
+    /// Marks the end of the input, there won't be any more chunks
+    pub fn end(&mut self) {
+        self.ended = true;
+    }
+
+    pub fn is_ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Whether the next `n` characters are known, they are either buffered or past the end of input
+    pub fn has(&self, n: usize) -> bool {
+        self.ended || self.vec.len() >= n
+    }
+
+    /// Consumes a character, CRLF and CR are normalized to LF, see
+    /// https://html.spec.whatwg.org/multipage/parsing.html#preprocessing-the-input-stream.
+    /// A CR ending the buffer waits for the next chunk, which tells whether a
+    /// LF follows it
+    pub fn next(&mut self) -> Option<char> {
+        if self.vec.len() == 1 && self.vec[0] == '\r' && !self.ended {
+            return None;
+        }
 
-        for char in input.chars() {
-            vec.push_back(char);
+        let c = self.vec.pop_front()?;
+
+        if c != '\r' {
+            self.position = self.position.advance(c);
+            return Some(c);
+        }
+
+        self.position = self.position.advance('\n');
+
+        if self.vec.front() == Some(&'\n') {
+            self.vec.pop_front();
+            self.position.offset += 1;
         }
 
-        Buffer { vec }
+        Some('\n')
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.vec.is_empty()
+    /// Position of the next character to be consumed
+    pub fn position(&self) -> Position {
+        self.position
     }
 
-    pub fn next(&mut self) -> char {
-        self.vec
-            .pop_front()
-            .expect("buffers in the queue are empty")
+    pub fn peek(&self, n: usize) -> Option<char> {
+        self.vec.get(n).copied()
+    }
+
+    /// Consumes the pattern if the buffer starts with it
+    pub fn eat(&mut self, pattern: &str, ignore_case: bool) -> bool {
+        let mut length = 0;
+
+        for (i, p) in pattern.chars().enumerate() {
+            let matched = match self.vec.get(i) {
+                Some(c) if ignore_case => c.eq_ignore_ascii_case(&p),
+                Some(c) => *c == p,
+                None => false,
+            };
+
+            if !matched {
+                return false;
+            }
+
+            length += 1;
+        }
+
+        for _ in 0..length {
+            self.next();
+        }
+
+        true
     }
 }
@@ -0,0 +1,597 @@
+//! The document tree, an arena where nodes link to each other by `NodeId`
+
+use std::error::Error;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+use super::interface::{Attribute, Element, Namespace, NodeData, QuirksMode};
+use super::serializer::Serializer;
+use crate::span::Span;
+
+/// Index of a node in its `Document`, it stays valid as long as the document lives
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+#[derive(Debug)]
+pub struct Node {
+    pub data: NodeData,
+    /// Only covers the start tag of elements
+    pub span: Span,
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    previous_sibling: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+}
+
+impl Node {
+    fn new(data: NodeData, span: Span) -> Node {
+        Node {
+            data,
+            span,
+            parent: None,
+            first_child: None,
+            last_child: None,
+            previous_sibling: None,
+            next_sibling: None,
+        }
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn first_child(&self) -> Option<NodeId> {
+        self.first_child
+    }
+
+    pub fn last_child(&self) -> Option<NodeId> {
+        self.last_child
+    }
+
+    pub fn previous_sibling(&self) -> Option<NodeId> {
+        self.previous_sibling
+    }
+
+    pub fn next_sibling(&self) -> Option<NodeId> {
+        self.next_sibling
+    }
+
+    pub fn as_element(&self) -> Option<&Element> {
+        match &self.data {
+            NodeData::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Document {
+    nodes: Vec<Node>,
+    pub quirks_mode: QuirksMode,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Document::new()
+    }
+}
+
+impl Index<NodeId> for Document {
+    type Output = Node;
+
+    fn index(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+}
+
+impl IndexMut<NodeId> for Document {
+    fn index_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id.0]
+    }
+}
+
+impl Document {
+    /// An empty document, holding only its root node
+    pub fn new() -> Document {
+        Document {
+            nodes: vec![Node::new(NodeData::Document, Span::default())],
+            quirks_mode: QuirksMode::NoQuirks,
+        }
+    }
+
+    /// The document node, parent of the doctype and the <html> element
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    /// The <html> element
+    pub fn document_element(&self) -> Option<NodeId> {
+        self.children(self.root())
+            .find(|&id| self[id].as_element().is_some())
+    }
+
+    pub fn children(&self, id: NodeId) -> Children<'_> {
+        Children {
+            document: self,
+            next: self[id].first_child,
+        }
+    }
+
+    /// All the nodes below this one, in document order
+    pub fn descendants(&self, id: NodeId) -> Descendants<'_> {
+        Descendants {
+            document: self,
+            root: id,
+            next: self[id].first_child,
+        }
+    }
+
+    /// The parent, grandparent and so on up to the root
+    pub fn ancestors(&self, id: NodeId) -> Ancestors<'_> {
+        Ancestors {
+            document: self,
+            next: self[id].parent,
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/dynamic-markup-insertion.html#dom-element-outerhtml
+    pub fn outer_html(&self, id: NodeId) -> String {
+        Serializer::new().outer_html(self, id)
+    }
+
+    /// https://html.spec.whatwg.org/multipage/dynamic-markup-insertion.html#dom-element-innerhtml
+    pub fn inner_html(&self, id: NodeId) -> String {
+        Serializer::new().inner_html(self, id)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-document-createelement
+    pub fn create_element(&mut self, name: &str) -> Result<NodeId, DomError> {
+        if !is_valid_element_name(name) {
+            return Err(DomError::InvalidCharacter);
+        }
+
+        let element = Element {
+            name: name.to_ascii_lowercase(),
+            namespace: Namespace::Html,
+            attrs: vec![],
+        };
+
+        Ok(self.create_node(NodeData::Element(element), Span::default()))
+    }
+
+    pub fn create_text_node(&mut self, data: &str) -> NodeId {
+        self.create_node(NodeData::Text(data.to_string()), Span::default())
+    }
+
+    pub fn create_comment(&mut self, data: &str) -> NodeId {
+        self.create_node(NodeData::Comment(data.to_string()), Span::default())
+    }
+
+    pub fn create_document_fragment(&mut self) -> NodeId {
+        self.create_node(NodeData::DocumentFragment, Span::default())
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-node-appendchild
+    pub fn append_child(&mut self, parent: NodeId, node: NodeId) -> Result<NodeId, DomError> {
+        self.insert_before(parent, node, None)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-node-insertbefore, appends when `child` is None
+    pub fn insert_before(
+        &mut self,
+        parent: NodeId,
+        node: NodeId,
+        child: Option<NodeId>,
+    ) -> Result<NodeId, DomError> {
+        self.check_insertion(parent, node, child, false)?;
+
+        let child = if child == Some(node) {
+            self[node].next_sibling
+        } else {
+            child
+        };
+
+        self.insert(parent, node, child);
+
+        Ok(node)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-node-removechild
+    pub fn remove_child(&mut self, parent: NodeId, child: NodeId) -> Result<NodeId, DomError> {
+        if self[child].parent != Some(parent) {
+            return Err(DomError::NotFound);
+        }
+
+        self.detach(child);
+
+        Ok(child)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-node-replacechild, returns the replaced child
+    pub fn replace_child(
+        &mut self,
+        parent: NodeId,
+        node: NodeId,
+        child: NodeId,
+    ) -> Result<NodeId, DomError> {
+        self.check_insertion(parent, node, Some(child), true)?;
+
+        if node == child {
+            return Ok(child);
+        }
+
+        let mut before = self[child].next_sibling;
+
+        if before == Some(node) {
+            before = self[node].next_sibling;
+        }
+
+        self.detach(child);
+        self.insert(parent, node, before);
+
+        Ok(child)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-node-clonenode, the copy has no parent
+    pub fn clone_node(&mut self, id: NodeId, deep: bool) -> NodeId {
+        let copy = self.create_node(self[id].data.clone(), self[id].span);
+
+        if deep {
+            let children: Vec<NodeId> = self.children(id).collect();
+
+            for child in children {
+                let child = self.clone_node(child, true);
+                self.append(copy, child);
+            }
+        }
+
+        copy
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-element-setattribute
+    pub fn set_attribute(&mut self, id: NodeId, name: &str, value: &str) -> Result<(), DomError> {
+        if !is_valid_attribute_name(name) {
+            return Err(DomError::InvalidCharacter);
+        }
+
+        let element = match &mut self[id].data {
+            NodeData::Element(element) => element,
+            _ => return Err(DomError::InvalidNodeType),
+        };
+
+        let name = if element.namespace == Namespace::Html {
+            name.to_ascii_lowercase()
+        } else {
+            name.to_string()
+        };
+
+        match element.attrs.iter_mut().find(|attr| attr.name == name) {
+            Some(attr) => attr.value = value.to_string(),
+            None => element.attrs.push(Attribute {
+                name,
+                value: value.to_string(),
+                span: Span::default(),
+            }),
+        }
+
+        Ok(())
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-element-removeattribute, returns the removed attribute
+    pub fn remove_attribute(&mut self, id: NodeId, name: &str) -> Option<Attribute> {
+        let element = match &mut self[id].data {
+            NodeData::Element(element) => element,
+            _ => return None,
+        };
+
+        let index = element.attrs.iter().position(|attr| {
+            if element.namespace == Namespace::Html {
+                attr.name.eq_ignore_ascii_case(name)
+            } else {
+                attr.name == name
+            }
+        })?;
+
+        Some(element.attrs.remove(index))
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-node-textcontent, elements and fragments
+    /// lose their children for a single text node
+    pub fn set_text_content(&mut self, id: NodeId, text: &str) {
+        match &mut self[id].data {
+            NodeData::Text(data) | NodeData::Comment(data) => *data = text.to_string(),
+
+            NodeData::Element(_) | NodeData::DocumentFragment => {
+                while let Some(child) = self[id].first_child {
+                    self.detach(child);
+                }
+
+                if !text.is_empty() {
+                    let child = self.create_text_node(text);
+                    self.append(id, child);
+                }
+            }
+
+            NodeData::Document | NodeData::Doctype(_) => (),
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-node-ensure-pre-insertion-validity, and the
+    /// checks of https://dom.spec.whatwg.org/#concept-node-replace when `replacing` the child
+    fn check_insertion(
+        &self,
+        parent: NodeId,
+        node: NodeId,
+        child: Option<NodeId>,
+        replacing: bool,
+    ) -> Result<(), DomError> {
+        let is_element = |id: NodeId| matches!(self[id].data, NodeData::Element(_));
+        let is_doctype = |id: NodeId| matches!(self[id].data, NodeData::Doctype(_));
+        let is_text = |id: NodeId| matches!(self[id].data, NodeData::Text(_));
+
+        if !matches!(
+            self[parent].data,
+            NodeData::Document | NodeData::DocumentFragment | NodeData::Element(_)
+        ) {
+            return Err(DomError::HierarchyRequest);
+        }
+
+        // The node would end up inside itself
+        if node == parent || self.ancestors(parent).any(|id| id == node) {
+            return Err(DomError::HierarchyRequest);
+        }
+
+        if child.is_some_and(|child| self[child].parent != Some(parent)) {
+            return Err(DomError::NotFound);
+        }
+
+        let parent_is_document = matches!(self[parent].data, NodeData::Document);
+
+        match self[node].data {
+            NodeData::Document => return Err(DomError::HierarchyRequest),
+            NodeData::Text(_) if parent_is_document => return Err(DomError::HierarchyRequest),
+            NodeData::Doctype(_) if !parent_is_document => return Err(DomError::HierarchyRequest),
+            _ => (),
+        }
+
+        if !parent_is_document {
+            return Ok(());
+        }
+
+        // A document has at most one doctype, followed by at most one element
+        let ignored = if replacing { child } else { None };
+        let others = || self.children(parent).filter(move |&id| Some(id) != ignored);
+
+        let has_element = others().any(is_element);
+        let has_doctype = others().any(is_doctype);
+        let child_is_doctype = !replacing && child.is_some_and(is_doctype);
+        let doctype_after = child.is_some_and(|child| {
+            self.children(parent)
+                .skip_while(|&id| id != child)
+                .skip(1)
+                .any(is_doctype)
+        });
+        let element_before = child.is_some_and(|child| {
+            self.children(parent)
+                .take_while(|&id| id != child)
+                .any(is_element)
+        });
+
+        let invalid = match self[node].data {
+            NodeData::DocumentFragment => {
+                let elements = self.children(node).filter(|&id| is_element(id)).count();
+
+                elements > 1
+                    || self.children(node).any(is_text)
+                    || (elements == 1 && (has_element || child_is_doctype || doctype_after))
+            }
+            NodeData::Element(_) => has_element || child_is_doctype || doctype_after,
+            NodeData::Doctype(_) => {
+                has_doctype || element_before || (child.is_none() && has_element)
+            }
+            _ => false,
+        };
+
+        if invalid {
+            Err(DomError::HierarchyRequest)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Inserts before `child` or at the end, a fragment is replaced by its children
+    fn insert(&mut self, parent: NodeId, node: NodeId, child: Option<NodeId>) {
+        let nodes = match self[node].data {
+            NodeData::DocumentFragment => self.children(node).collect(),
+            _ => vec![node],
+        };
+
+        for node in nodes {
+            match child {
+                Some(child) => self.move_before(child, node),
+                None => self.append(parent, node),
+            }
+        }
+    }
+
+    /// Adds a node which isn't part of the tree yet
+    pub(crate) fn create_node(&mut self, data: NodeData, span: Span) -> NodeId {
+        self.nodes.push(Node::new(data, span));
+
+        NodeId(self.nodes.len() - 1)
+    }
+
+    /// Moves the node to the end of the children of `parent`
+    pub(crate) fn append(&mut self, parent: NodeId, child: NodeId) {
+        self.detach(child);
+
+        let previous = self[parent].last_child;
+
+        self[child].parent = Some(parent);
+        self[child].previous_sibling = previous;
+        self[parent].last_child = Some(child);
+
+        match previous {
+            Some(previous) => self[previous].next_sibling = Some(child),
+            None => self[parent].first_child = Some(child),
+        }
+    }
+
+    /// Moves the node right before `sibling`, which must have a parent
+    pub(crate) fn move_before(&mut self, sibling: NodeId, child: NodeId) {
+        self.detach(child);
+
+        let parent = self[sibling].parent.expect("the sibling has a parent");
+        let previous = self[sibling].previous_sibling;
+
+        self[child].parent = Some(parent);
+        self[child].previous_sibling = previous;
+        self[child].next_sibling = Some(sibling);
+        self[sibling].previous_sibling = Some(child);
+
+        match previous {
+            Some(previous) => self[previous].next_sibling = Some(child),
+            None => self[parent].first_child = Some(child),
+        }
+    }
+
+    /// Removes the node from its parent, it keeps its own children
+    pub(crate) fn detach(&mut self, id: NodeId) {
+        let parent = match self[id].parent.take() {
+            Some(parent) => parent,
+            None => return,
+        };
+
+        let previous = self[id].previous_sibling.take();
+        let next = self[id].next_sibling.take();
+
+        match previous {
+            Some(previous) => self[previous].next_sibling = next,
+            None => self[parent].first_child = next,
+        }
+
+        match next {
+            Some(next) => self[next].previous_sibling = previous,
+            None => self[parent].last_child = previous,
+        }
+    }
+}
+
+/// https://webidl.spec.whatwg.org/#idl-DOMException-error-names
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DomError {
+    /// The node can't go there, e.g inside itself or as a second root element
+    HierarchyRequest,
+    /// The reference node isn't a child of the parent
+    NotFound,
+    /// The name isn't a valid element or attribute name
+    InvalidCharacter,
+    /// The operation only applies to elements
+    InvalidNodeType,
+}
+
+impl fmt::Display for DomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DomError::HierarchyRequest => "the node can't be inserted there",
+            DomError::NotFound => "the node isn't a child of the parent",
+            DomError::InvalidCharacter => "the name contains an invalid character",
+            DomError::InvalidNodeType => "the node isn't an element",
+        })
+    }
+}
+
+impl Error for DomError {}
+
+/// https://dom.spec.whatwg.org/#valid-element-local-name
+fn is_valid_element_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => {
+            chars.all(|c| !matches!(c, '\t' | '\n' | '\x0C' | '\r' | ' ' | '\0' | '/' | '>'))
+        }
+        Some(c) if matches!(c, ':' | '_') || c >= '\u{80}' => chars.all(|c| {
+            c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | ':' | '_') || c >= '\u{80}'
+        }),
+        _ => false,
+    }
+}
+
+/// https://dom.spec.whatwg.org/#valid-attribute-local-name
+fn is_valid_attribute_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.chars().any(|c| {
+            matches!(
+                c,
+                '\t' | '\n' | '\x0C' | '\r' | ' ' | '\0' | '/' | '=' | '>'
+            )
+        })
+}
+
+pub struct Children<'a> {
+    document: &'a Document,
+    next: Option<NodeId>,
+}
+
+impl Iterator for Children<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        self.next = self.document[id].next_sibling;
+
+        Some(id)
+    }
+}
+
+pub struct Descendants<'a> {
+    document: &'a Document,
+    root: NodeId,
+    next: Option<NodeId>,
+}
+
+impl Iterator for Descendants<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        let node = &self.document[id];
+
+        // The first child, else the next sibling of the node or of its closest ancestor having one
+        self.next = node.first_child.or_else(|| {
+            let mut current = id;
+
+            loop {
+                if current == self.root {
+                    return None;
+                }
+
+                if let Some(next) = self.document[current].next_sibling {
+                    return Some(next);
+                }
+
+                current = self.document[current].parent?;
+            }
+        });
+
+        Some(id)
+    }
+}
+
+pub struct Ancestors<'a> {
+    document: &'a Document,
+    next: Option<NodeId>,
+}
+
+impl Iterator for Ancestors<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        self.next = self.document[id].parent;
+
+        Some(id)
+    }
+}
@@ -0,0 +1,400 @@
+//! https://html.spec.whatwg.org/multipage/parsing.html#determining-the-character-encoding
+
+use encoding_rs::{Encoding, WINDOWS_1252, X_USER_DEFINED};
+
+use super::interface::{Attribute, Charset, Confidence};
+
+/// How many bytes the <meta> prescan looks at
+const PRESCAN_LENGTH: usize = 1024;
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#encoding-sniffing-algorithm
+pub fn sniff(input: &[u8], transport_charset: Option<&str>) -> Charset {
+    if let Some((encoding, _)) = Encoding::for_bom(input) {
+        return Charset::new(encoding, Confidence::Certain);
+    }
+
+    if let Some(encoding) =
+        transport_charset.and_then(|label| Encoding::for_label(label.as_bytes()))
+    {
+        return Charset::new(encoding, Confidence::Certain);
+    }
+
+    let end = input.len().min(PRESCAN_LENGTH);
+    let encoding = prescan(&input[..end]).unwrap_or(WINDOWS_1252);
+
+    Charset::new(encoding, Confidence::Tentative)
+}
+
+/// The encoding declared by a <meta charset> or <meta http-equiv="content-type"> element
+pub fn from_meta(attrs: &[Attribute]) -> Option<&'static Encoding> {
+    let get = |name: &str| attrs.iter().find(|attr| attr.name == name);
+
+    // An unknown charset label falls through to the http-equiv pragma
+    let charset = get("charset").and_then(|charset| Encoding::for_label(charset.value.as_bytes()));
+
+    if let Some(encoding) = charset {
+        return Some(adjust(encoding));
+    }
+
+    let http_equiv = get("http-equiv")?;
+
+    if !http_equiv.value.eq_ignore_ascii_case("content-type") {
+        return None;
+    }
+
+    let label = extract_from_content(get("content")?.value.as_bytes())?;
+
+    Encoding::for_label(label).map(adjust)
+}
+
+/// A <meta> can't declare an encoding which isn't ASCII compatible, the
+/// document would be unreadable up to it
+fn adjust(encoding: &'static Encoding) -> &'static Encoding {
+    if encoding == X_USER_DEFINED {
+        WINDOWS_1252
+    } else {
+        encoding.output_encoding()
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#prescan-a-byte-stream-to-determine-its-encoding
+fn prescan(input: &[u8]) -> Option<&'static Encoding> {
+    let mut position = 0;
+
+    while position < input.len() {
+        let rest = &input[position..];
+
+        if rest.starts_with(b"<!--") {
+            position += find(&rest[2..], b"-->").map_or(rest.len(), |end| end + 5);
+        } else if starts_with_ignore_case(rest, b"<meta")
+            && rest.get(5).is_some_and(|&b| is_whitespace(b) || b == b'/')
+        {
+            position += 6;
+
+            if let Some(encoding) = prescan_meta(input, &mut position) {
+                return Some(encoding);
+            }
+        } else if is_tag_start(rest) {
+            position += rest
+                .iter()
+                .position(|&b| is_whitespace(b) || b == b'>')
+                .unwrap_or(rest.len());
+
+            while get_attribute(input, &mut position).is_some() {}
+        } else if rest.starts_with(b"<!") || rest.starts_with(b"</") || rest.starts_with(b"<?") {
+            position += find(rest, b">").map_or(rest.len(), |end| end + 1);
+        } else {
+            position += 1;
+        }
+    }
+
+    None
+}
+
+/// Reads the attributes of a <meta> whose name has been consumed
+fn prescan_meta(input: &[u8], position: &mut usize) -> Option<&'static Encoding> {
+    let mut names: Vec<Vec<u8>> = vec![];
+    let mut got_pragma = false;
+    let mut need_pragma: Option<bool> = None;
+    // None until an attribute gives it, Some(None) for the failure of an unknown
+    // charset label, which a later content attribute doesn't replace
+    let mut charset: Option<Option<&'static Encoding>> = None;
+
+    while let Some((name, value)) = get_attribute(input, position) {
+        if names.contains(&name) {
+            continue;
+        }
+
+        match name.as_slice() {
+            b"http-equiv" => got_pragma |= value.eq_ignore_ascii_case(b"content-type"),
+
+            b"content" if charset.is_none() => {
+                if let Some(encoding) = extract_from_content(&value).and_then(Encoding::for_label) {
+                    charset = Some(Some(encoding));
+                    need_pragma = Some(true);
+                }
+            }
+
+            // Only used when the content attribute didn't give an encoding first
+            b"charset" if charset.is_none() => {
+                charset = Some(Encoding::for_label(&value));
+                need_pragma = Some(false);
+            }
+
+            _ => (),
+        }
+
+        names.push(name);
+    }
+
+    match need_pragma {
+        Some(true) if !got_pragma => None,
+        Some(_) => charset.flatten().map(adjust),
+        None => None,
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#concept-get-attributes-when-sniffing
+fn get_attribute(input: &[u8], position: &mut usize) -> Option<(Vec<u8>, Vec<u8>)> {
+    let byte = |position: usize| input.get(position).copied();
+
+    while byte(*position).is_some_and(|b| is_whitespace(b) || b == b'/') {
+        *position += 1;
+    }
+
+    let mut name: Vec<u8> = vec![];
+    let mut value: Vec<u8> = vec![];
+
+    match byte(*position)? {
+        b'>' => return None,
+        b => {
+            name.push(b.to_ascii_lowercase());
+            *position += 1;
+        }
+    }
+
+    loop {
+        match byte(*position)? {
+            b'=' => break,
+            b if is_whitespace(b) => {
+                while byte(*position).is_some_and(is_whitespace) {
+                    *position += 1;
+                }
+
+                if byte(*position)? != b'=' {
+                    return Some((name, value));
+                }
+
+                break;
+            }
+            b'/' | b'>' => return Some((name, value)),
+            b => name.push(b.to_ascii_lowercase()),
+        }
+
+        *position += 1;
+    }
+
+    // Skips the '=' and the whitespace around the value
+    *position += 1;
+
+    while byte(*position).is_some_and(is_whitespace) {
+        *position += 1;
+    }
+
+    match byte(*position)? {
+        quote @ (b'"' | b'\'') => {
+            *position += 1;
+
+            loop {
+                match byte(*position)? {
+                    b if b == quote => {
+                        *position += 1;
+                        return Some((name, value));
+                    }
+                    b => value.push(b.to_ascii_lowercase()),
+                }
+
+                *position += 1;
+            }
+        }
+
+        b'>' => Some((name, value)),
+
+        _ => {
+            while let Some(b) = byte(*position) {
+                if is_whitespace(b) || b == b'>' {
+                    break;
+                }
+
+                value.push(b.to_ascii_lowercase());
+                *position += 1;
+            }
+
+            Some((name, value))
+        }
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#algorithm-for-extracting-a-character-encoding-from-a-meta-element
+fn extract_from_content(content: &[u8]) -> Option<&[u8]> {
+    let mut position = 0;
+
+    loop {
+        let start = position + find_ignore_case(&content[position..], b"charset")? + 7;
+        position = start;
+
+        while content.get(position).copied().is_some_and(is_whitespace) {
+            position += 1;
+        }
+
+        if content.get(position) != Some(&b'=') {
+            position = start;
+            continue;
+        }
+
+        position += 1;
+
+        while content.get(position).copied().is_some_and(is_whitespace) {
+            position += 1;
+        }
+
+        let rest = &content[position..];
+
+        return match rest.first()? {
+            &quote @ (b'"' | b'\'') => {
+                let end = rest[1..].iter().position(|&b| b == quote)?;
+                Some(&rest[1..end + 1])
+            }
+            _ => {
+                let end = rest
+                    .iter()
+                    .position(|&b| is_whitespace(b) || b == b';')
+                    .unwrap_or(rest.len());
+
+                Some(&rest[..end]).filter(|label| !label.is_empty())
+            }
+        };
+    }
+}
+
+/// A start or end tag, whose attributes have to be skipped
+fn is_tag_start(input: &[u8]) -> bool {
+    let name = if input.get(1) == Some(&b'/') { 2 } else { 1 };
+
+    input[0] == b'<' && input.get(name).is_some_and(u8::is_ascii_alphabetic)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn find_ignore_case(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle))
+}
+
+fn starts_with_ignore_case(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.len() >= needle.len() && haystack[..needle.len()].eq_ignore_ascii_case(needle)
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b'\t' | b'\n' | b'\x0C' | b'\r' | b' ')
+}
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::{Encoding, ISO_8859_2, SHIFT_JIS, UTF_8, WINDOWS_1252};
+
+    use super::sniff;
+
+    fn prescan(input: &str) -> &'static Encoding {
+        sniff(input.as_bytes(), None).encoding
+    }
+
+    #[test]
+    fn charset_attribute() {
+        assert_eq!(prescan("<meta charset=shift_jis>"), SHIFT_JIS);
+        assert_eq!(prescan("<META CHARSET='Shift_JIS'/>"), SHIFT_JIS);
+        assert_eq!(prescan("<meta charset=utf-16le>"), UTF_8);
+        assert_eq!(prescan("<meta charset=x-user-defined>"), WINDOWS_1252);
+    }
+
+    #[test]
+    fn content_attribute_needs_the_pragma() {
+        let content = "content='text/html; charset=iso-8859-2'";
+
+        assert_eq!(prescan(&format!("<meta {}>", content)), WINDOWS_1252);
+        assert_eq!(
+            prescan(&format!("<meta {} http-equiv=Content-Type>", content)),
+            ISO_8859_2
+        );
+        assert_eq!(
+            prescan(&format!("<meta http-equiv=content-type {}>", content)),
+            ISO_8859_2
+        );
+    }
+
+    #[test]
+    fn the_first_of_charset_and_content_wins() {
+        let content = "http-equiv=content-type content='text/html; charset=iso-8859-2'";
+
+        assert_eq!(
+            prescan(&format!("<meta charset=shift_jis {}>", content)),
+            SHIFT_JIS
+        );
+        assert_eq!(
+            prescan(&format!("<meta {} charset=shift_jis>", content)),
+            ISO_8859_2
+        );
+    }
+
+    #[test]
+    fn unknown_labels_skip_the_meta() {
+        let content = "http-equiv=content-type content='text/html; charset=iso-8859-2'";
+
+        assert_eq!(
+            prescan(&format!("<meta charset=bogus {}>", content)),
+            WINDOWS_1252
+        );
+        assert_eq!(
+            prescan(&format!(
+                "<meta charset=bogus {}><meta charset=shift_jis>",
+                content
+            )),
+            SHIFT_JIS
+        );
+        // An unknown label in content is ignored, unlike in charset
+        assert_eq!(
+            prescan("<meta http-equiv=content-type content='charset=bogus' charset=shift_jis>"),
+            SHIFT_JIS
+        );
+    }
+
+    #[test]
+    fn repeated_attributes_are_ignored() {
+        assert_eq!(
+            prescan("<meta charset=shift_jis charset=iso-8859-2>"),
+            SHIFT_JIS
+        );
+    }
+
+    #[test]
+    fn comments_and_other_tags_are_skipped() {
+        assert_eq!(
+            prescan("<!-- <meta charset=shift_jis> --><meta charset=iso-8859-2>"),
+            ISO_8859_2
+        );
+        assert_eq!(prescan("<!--><meta charset=shift_jis>"), SHIFT_JIS);
+        assert_eq!(prescan("<!---><meta charset=shift_jis>"), SHIFT_JIS);
+        assert_eq!(
+            prescan("<p title='<meta charset=shift_jis>'><meta charset=iso-8859-2>"),
+            ISO_8859_2
+        );
+        assert_eq!(prescan("<metal charset=shift_jis>"), WINDOWS_1252);
+    }
+
+    #[test]
+    fn only_the_first_1024_bytes_are_prescanned() {
+        let padding = " ".repeat(1024);
+
+        assert_eq!(
+            prescan(&format!("{}<meta charset=shift_jis>", padding)),
+            WINDOWS_1252
+        );
+    }
+
+    #[test]
+    fn byte_order_mark_and_transport_win() {
+        assert_eq!(
+            sniff(b"\xEF\xBB\xBF<meta charset=shift_jis>", None).encoding,
+            UTF_8
+        );
+        assert_eq!(
+            sniff(b"<meta charset=shift_jis>", Some("iso-8859-2")).encoding,
+            ISO_8859_2
+        );
+    }
+}
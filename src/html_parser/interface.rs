@@ -1,19 +1,74 @@
-use std::cell::RefCell;
+use encoding_rs::Encoding;
+
+use crate::span::Span;
 
 #[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Token {
     Tag(TagToken),
     Characters(String),
-    ParseError(String),
+    Comment(String),
+    Doctype(Doctype),
+    ParseError(ParseError),
+    EOF,
 }
 
-pub trait TokenSink {
-    fn process_token(&mut self, token: Token);
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    /// One of https://html.spec.whatwg.org/multipage/parsing.html#parse-errors
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+}
+
+/// The encoding a byte stream is decoded with, see
+/// https://html.spec.whatwg.org/multipage/parsing.html#encoding-sniffing-algorithm
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Charset {
+    pub encoding: &'static Encoding,
+    pub confidence: Confidence,
+}
+
+impl Charset {
+    pub fn new(encoding: &'static Encoding, confidence: Confidence) -> Charset {
+        Charset {
+            encoding,
+            confidence,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Confidence {
+    /// Found by the <meta> prescan or the default, a <meta> found while parsing can change it
+    Tentative,
+    /// Given by a byte order mark, the transport layer or a <meta> found while parsing
+    Certain,
 }
 
+/// What the tokenizer should do once a token has been processed
 #[derive(Debug)]
+pub enum TokenSinkResult {
+    Continue,
+    /// Switches the tokenizer to another state, e.g. to RAWTEXT after a <style> start tag
+    SwitchTo(State),
+}
+
+pub trait TokenSink {
+    fn process_token(&mut self, token: Token, span: Span) -> TokenSinkResult;
+
+    /// Whether the adjusted current node is an SVG or MathML element, CDATA
+    /// sections are only allowed there
+    fn is_foreign_content(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
 pub enum State {
     Data,
+    RawData(RawKind),
+    PlainText,
     TagOpen,
     EndTagOpen,
     TagName,
@@ -24,6 +79,67 @@ pub enum State {
     AttributeValue(AttrValueKind),
     AfterAttributeValueQuoted,
     SelfClosingStartTag,
+    RawLessThanSign(RawKind),
+    RawEndTagOpen(RawKind),
+    RawEndTagName(RawKind),
+    ScriptDataEscapeStart(ScriptEscapeKind),
+    ScriptDataEscapeStartDash,
+    ScriptDataEscapedDash(ScriptEscapeKind),
+    ScriptDataEscapedDashDash(ScriptEscapeKind),
+    ScriptDataDoubleEscapeEnd,
+    BogusComment,
+    MarkupDeclarationOpen,
+    CommentStart,
+    CommentStartDash,
+    Comment,
+    CommentLessThanSign,
+    CommentLessThanSignBang,
+    CommentLessThanSignBangDash,
+    CommentLessThanSignBangDashDash,
+    CommentEndDash,
+    CommentEnd,
+    CommentEndBang,
+    Doctype,
+    BeforeDoctypeName,
+    DoctypeName,
+    AfterDoctypeName,
+    AfterDoctypeKeyword(DoctypeIdKind),
+    BeforeDoctypeIdentifier(DoctypeIdKind),
+    DoctypeIdentifierDoubleQuoted(DoctypeIdKind),
+    DoctypeIdentifierSingleQuoted(DoctypeIdKind),
+    AfterDoctypePublicIdentifier,
+    BetweenDoctypePublicAndSystemIdentifiers,
+    AfterDoctypeSystemIdentifier,
+    BogusDoctype,
+    CdataSection,
+    CdataSectionBracket,
+    CdataSectionEnd,
+    CharacterReference,
+    NamedCharacterReference,
+    AmbiguousAmpersand,
+    NumericCharacterReference,
+    HexadecimalCharacterReferenceStart,
+    DecimalCharacterReferenceStart,
+    HexadecimalCharacterReference,
+    DecimalCharacterReference,
+}
+
+/// The content of elements which can't contain markup
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RawKind {
+    /// <title> and <textarea>, where character references are decoded
+    RcData,
+    /// <style>, <xmp>, <iframe>, <noembed> and <noframes>
+    RawText,
+    ScriptData,
+    /// Script data inside `<!--`, where `<script>` can nest
+    ScriptDataEscaped(ScriptEscapeKind),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScriptEscapeKind {
+    Escaped,
+    DoubleEscaped,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -44,38 +160,97 @@ pub struct TagToken {
 pub struct Attribute {
     pub name: String,
     pub value: String,
+    /// From the start of the name to the end of the value
+    pub span: Span,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 pub enum AttrValueKind {
     Unquoted,
     SingleQuoted,
     DoubleQuoted,
 }
 
-#[derive(Debug)]
-pub struct Node {
-    pub data: NodeData,
-    pub children: RefCell<Vec<Node>>,
+#[derive(Debug, Copy, Clone)]
+pub enum DoctypeIdKind {
+    Public,
+    System,
 }
 
-impl Node {
-    pub fn new(data: NodeData) -> Node {
-        Node {
-            data,
-            children: RefCell::new(vec![]),
-        }
-    }
+/// <!DOCTYPE html PUBLIC "..." "...">
+#[derive(Debug, Clone, Default)]
+pub struct Doctype {
+    pub name: Option<String>,
+    pub public_id: Option<String>,
+    pub system_id: Option<String>,
+    pub force_quirks: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum NodeData {
+    /// The root of the tree, parent of the <html> element
+    Document,
+    /// A parentless container, inserting it moves its children instead
+    DocumentFragment,
     Element(Element),
     Text(String),
+    Comment(String),
+    Doctype(Doctype),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Element {
     pub name: String,
+    pub namespace: Namespace,
     pub attrs: Vec<Attribute>,
 }
+
+impl Element {
+    pub fn get_attribute(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|attr| attr.name == name)
+            .map(|attr| attr.value.as_str())
+    }
+
+    /// Void elements can't have any content, and have no end tag
+    pub fn is_void(&self) -> bool {
+        self.namespace == Namespace::Html
+            && matches!(
+                self.name.as_str(),
+                "area"
+                    | "base"
+                    | "basefont"
+                    | "bgsound"
+                    | "br"
+                    | "col"
+                    | "embed"
+                    | "frame"
+                    | "hr"
+                    | "img"
+                    | "input"
+                    | "keygen"
+                    | "link"
+                    | "meta"
+                    | "param"
+                    | "source"
+                    | "track"
+                    | "wbr"
+            )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Namespace {
+    Html,
+    Svg,
+    MathMl,
+}
+
+/// https://dom.spec.whatwg.org/#concept-document-quirks
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum QuirksMode {
+    NoQuirks,
+    LimitedQuirks,
+    Quirks,
+}
@@ -1,15 +1,94 @@
-use self::{buffer::Buffer, dom_handler::DOMHandler, interface::Node, tokenizer::Tokenizer};
+use self::{
+    buffer::Buffer,
+    dom::Document,
+    interface::{Charset, Confidence, ParseError},
+    tokenizer::Tokenizer,
+    tree_builder::TreeBuilder,
+};
 
 mod buffer;
-mod dom_handler;
-mod interface;
+pub mod dom;
+mod encoding;
+mod entities;
+pub mod interface;
+pub mod serializer;
 mod tokenizer;
+mod tree_builder;
 
-pub fn parse(input: &str) -> Vec<Node> {
-    let handler = DOMHandler::new();
-    let mut buffer = Buffer::new(input);
-    let mut tokenizer = Tokenizer::new(handler);
+pub fn parse(input: &str) -> (Document, Vec<ParseError>) {
+    let mut parser = Parser::new();
 
-    tokenizer.feed(&mut buffer);
-    tokenizer.sink.nodes
+    parser.feed(input);
+    parser.end()
+}
+
+/// Decodes and parses a document given as bytes, `transport_charset` is the
+/// charset of e.g the Content-Type header. Returns the encoding used as well
+pub fn parse_bytes(
+    input: &[u8],
+    transport_charset: Option<&str>,
+) -> (Document, Vec<ParseError>, Charset) {
+    let mut charset = encoding::sniff(input, transport_charset);
+
+    loop {
+        let (text, _) = charset.encoding.decode_with_bom_removal(input);
+
+        let mut parser = Parser::new();
+        parser.feed(&text);
+        let builder = parser.end_builder();
+
+        // https://html.spec.whatwg.org/multipage/parsing.html#changing-the-encoding-while-parsing
+        if let (Confidence::Tentative, Some(declared)) =
+            (charset.confidence, builder.declared_encoding)
+        {
+            let previous = charset.encoding;
+            charset = Charset::new(declared, Confidence::Certain);
+
+            if declared != previous {
+                continue;
+            }
+        }
+
+        let (document, errors) = builder.finish();
+
+        return (document, errors, charset);
+    }
+}
+
+/// Parses a document arriving in chunks, e.g from the network. Chunks can be
+/// split anywhere, the document doesn't depend on it
+pub struct Parser {
+    tokenizer: Tokenizer<TreeBuilder>,
+    buffer: Buffer,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Parser::new()
+    }
+}
+
+impl Parser {
+    pub fn new() -> Parser {
+        Parser {
+            tokenizer: Tokenizer::new(TreeBuilder::new()),
+            buffer: Buffer::new(),
+        }
+    }
+
+    /// Parses as much of the chunk as possible, the rest waits for the next one
+    pub fn feed(&mut self, chunk: &str) {
+        self.buffer.push_str(chunk);
+        self.tokenizer.feed(&mut self.buffer);
+    }
+
+    /// Parses the remaining input, returning the document and the parse errors
+    pub fn end(self) -> (Document, Vec<ParseError>) {
+        self.end_builder().finish()
+    }
+
+    fn end_builder(mut self) -> TreeBuilder {
+        self.tokenizer.end(&mut self.buffer);
+        self.tokenizer.sink
+    }
 }
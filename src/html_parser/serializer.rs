@@ -0,0 +1,282 @@
+//! https://html.spec.whatwg.org/multipage/parsing.html#serialising-html-fragments
+
+use super::dom::{Document, NodeId};
+use super::interface::{Doctype, Element, Namespace, NodeData};
+
+/// Turns nodes back into markup. Parsing the output of the default serializer
+/// gives back the same tree, except for the trees no markup can produce:
+/// - adjacent text nodes, which are parsed back as one
+/// - content the parser moved out of a table, which can nest elements their
+///   start tags would close, e.g a <p> in a <p>, an <a> in an <a> or a <li> in a <li>
+/// - the same nestings built with the DOM API, e.g nested forms or headings
+/// - anything after a <plaintext>, even its end tag or the one of a <template>
+///   around it, which becomes its text
+#[derive(Debug, Clone, Default)]
+pub struct Serializer {
+    /// Spaces per level of the pretty printed output, None keeps the whitespace as is
+    indent: Option<usize>,
+}
+
+impl Serializer {
+    pub fn new() -> Serializer {
+        Serializer::default()
+    }
+
+    /// Puts block content on its own lines. Whitespace only text is dropped, so
+    /// the output doesn't round-trip
+    pub fn pretty(indent: usize) -> Serializer {
+        Serializer {
+            indent: Some(indent),
+        }
+    }
+
+    /// The node itself and its descendants
+    pub fn outer_html(&self, document: &Document, id: NodeId) -> String {
+        let mut output = String::new();
+        self.write_node(&mut output, document, id, 0, self.indent.is_some());
+
+        output
+    }
+
+    /// Only the descendants of the node
+    pub fn inner_html(&self, document: &Document, id: NodeId) -> String {
+        let mut output = String::new();
+        self.write_children(&mut output, document, id, 0, self.indent.is_some());
+
+        output
+    }
+
+    fn write_node(
+        &self,
+        output: &mut String,
+        document: &Document,
+        id: NodeId,
+        depth: usize,
+        pretty: bool,
+    ) {
+        match &document[id].data {
+            NodeData::Document | NodeData::DocumentFragment => {
+                self.write_children(output, document, id, depth, pretty)
+            }
+
+            NodeData::Element(element) => {
+                output.push('<');
+                output.push_str(&element.name);
+
+                for attr in &element.attrs {
+                    output.push(' ');
+                    output.push_str(&attr.name);
+                    output.push_str("=\"");
+                    escape(output, &attr.value, true);
+                    output.push('"');
+                }
+
+                output.push('>');
+
+                if element.is_void() {
+                    return;
+                }
+
+                // The parser drops a newline right after these start tags
+                if is_html(element, &["pre", "textarea", "listing"]) {
+                    let first_text =
+                        document[id]
+                            .first_child()
+                            .and_then(|child| match &document[child].data {
+                                NodeData::Text(text) => Some(text),
+                                _ => None,
+                            });
+
+                    if first_text.is_some_and(|text| text.starts_with('\n')) {
+                        output.push('\n');
+                    }
+                }
+
+                self.write_children(output, document, id, depth + 1, pretty);
+
+                if ends_in_plaintext(document, id) {
+                    return;
+                }
+
+                output.push_str("</");
+                output.push_str(&element.name);
+                output.push('>');
+            }
+
+            NodeData::Text(text) => {
+                let raw = document[id]
+                    .parent()
+                    .and_then(|parent| document[parent].as_element())
+                    .is_some_and(is_raw_text);
+
+                if raw {
+                    output.push_str(text);
+                } else {
+                    escape(output, text, false);
+                }
+            }
+
+            NodeData::Comment(text) => {
+                output.push_str("<!--");
+                output.push_str(text);
+                output.push_str("-->");
+            }
+
+            NodeData::Doctype(doctype) => write_doctype(output, doctype),
+        }
+    }
+
+    fn write_children(
+        &self,
+        output: &mut String,
+        document: &Document,
+        parent: NodeId,
+        depth: usize,
+        pretty: bool,
+    ) {
+        let pretty = pretty && !keeps_whitespace(document, parent);
+
+        if !pretty {
+            for child in document.children(parent) {
+                self.write_node(output, document, child, depth, false);
+            }
+
+            return;
+        }
+
+        let indent = self.indent.unwrap_or(0);
+        let mut wrote = false;
+
+        for child in document.children(parent) {
+            if is_whitespace_text(document, child) {
+                continue;
+            }
+
+            if wrote || depth > 0 {
+                output.push('\n');
+                output.push_str(&" ".repeat(indent * depth));
+            }
+
+            self.write_node(output, document, child, depth, true);
+            wrote = true;
+        }
+
+        if wrote && depth > 0 {
+            output.push('\n');
+            output.push_str(&" ".repeat(indent * (depth - 1)));
+        }
+    }
+}
+
+/// The spec only writes the name, the identifiers are kept so that the quirks
+/// mode survives a round-trip
+fn write_doctype(output: &mut String, doctype: &Doctype) {
+    output.push_str("<!DOCTYPE");
+
+    if let Some(name) = &doctype.name {
+        output.push(' ');
+        output.push_str(name);
+    }
+
+    match (&doctype.public_id, &doctype.system_id) {
+        (Some(public_id), system_id) => {
+            output.push_str(" PUBLIC ");
+            write_quoted(output, public_id);
+
+            if let Some(system_id) = system_id {
+                output.push(' ');
+                write_quoted(output, system_id);
+            }
+        }
+        (None, Some(system_id)) => {
+            output.push_str(" SYSTEM ");
+            write_quoted(output, system_id);
+        }
+        (None, None) => (),
+    }
+
+    output.push('>');
+}
+
+/// Doctype identifiers can't be escaped, one with a '"' was single quoted
+fn write_quoted(output: &mut String, id: &str) {
+    let quote = if id.contains('"') { '\'' } else { '"' };
+
+    output.push(quote);
+    output.push_str(id);
+    output.push(quote);
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#escapingString, a CR
+/// is escaped too since the parser would turn it into a LF
+fn escape(output: &mut String, text: &str, attribute_mode: bool) {
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '\r' => output.push_str("&#13;"),
+            '\u{A0}' => output.push_str("&nbsp;"),
+            '"' if attribute_mode => output.push_str("&quot;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            c => output.push(c),
+        }
+    }
+}
+
+fn is_html(element: &Element, names: &[&str]) -> bool {
+    element.namespace == Namespace::Html && names.contains(&element.name.as_str())
+}
+
+/// Elements whose text is written without escaping, scripting is disabled so
+/// <noscript> isn't one of them
+fn is_raw_text(element: &Element) -> bool {
+    is_html(
+        element,
+        &[
+            "style",
+            "script",
+            "xmp",
+            "iframe",
+            "noembed",
+            "noframes",
+            "plaintext",
+        ],
+    )
+}
+
+/// <plaintext> consumes the rest of the input, so neither its end tag nor the
+/// ones of the elements it closes can be written
+fn ends_in_plaintext(document: &Document, id: NodeId) -> bool {
+    let element = match document[id].as_element() {
+        Some(element) => element,
+        None => return false,
+    };
+
+    is_html(element, &["plaintext"])
+        || document[id]
+            .last_child()
+            .is_some_and(|child| ends_in_plaintext(document, child))
+}
+
+/// Whether pretty printing would change the content, because whitespace is
+/// significant or the children are inline with text
+fn keeps_whitespace(document: &Document, parent: NodeId) -> bool {
+    let preformatted = document[parent].as_element().is_some_and(|element| {
+        is_raw_text(element) || is_html(element, &["pre", "textarea", "listing"])
+    });
+
+    preformatted
+        || document.children(parent).any(|child| {
+            matches!(document[child].data, NodeData::Text(_))
+                && !is_whitespace_text(document, child)
+        })
+}
+
+fn is_whitespace_text(document: &Document, id: NodeId) -> bool {
+    match &document[id].data {
+        NodeData::Text(text) => text
+            .chars()
+            .all(|c| matches!(c, '\t' | '\n' | '\x0C' | '\r' | ' ')),
+        _ => false,
+    }
+}
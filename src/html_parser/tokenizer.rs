@@ -1,9 +1,17 @@
 use super::{
     buffer::Buffer,
-    interface::{AttrValueKind, Attribute, State, TagKind, TagToken, Token, TokenSink},
+    entities,
+    interface::{
+        AttrValueKind, Attribute, Doctype, DoctypeIdKind, ParseError, RawKind, ScriptEscapeKind,
+        State, TagKind, TagToken, Token, TokenSink, TokenSinkResult,
+    },
 };
+use crate::span::{Position, Span};
 
 use self::AttrValueKind::{DoubleQuoted, SingleQuoted, Unquoted};
+use self::DoctypeIdKind::{Public, System};
+use self::RawKind::{RcData, ScriptData, ScriptDataEscaped};
+use self::ScriptEscapeKind::{DoubleEscaped, Escaped};
 use self::TagKind::{EndTag, StartTag};
 
 macro_rules! go {
@@ -15,6 +23,14 @@ macro_rules! go {
         $me.state = State::$s($k1);
     }};
 
+    ($me:ident $a:tt) => {{
+        $me.$a();
+    }};
+
+    ($me:ident $a:tt $b:tt) => {{
+        $me.$a($b);
+    }};
+
     ($me:ident $a:tt; $($rest:tt)*) => {{
         $me.$a();
         go!($me $($rest)*);
@@ -34,14 +50,30 @@ macro_rules! go {
 pub struct Tokenizer<Sink> {
     pub sink: Sink,
     state: State,
+    reconsume: bool,
     current_char: char,
+    /// Span of the current character
+    char_start: Position,
+    char_end: Position,
+    /// Start of the tag, comment or doctype being tokenized
+    token_start: Position,
+    /// Start of the characters not emitted yet
+    text_start: Position,
     current_chars: String,
     current_tag_kind: TagKind,
     current_tag_name: String,
     current_tag_self_closing: bool,
     current_tag_attrs: Vec<Attribute>,
+    last_start_tag_name: String,
     current_attr_name: String,
     current_attr_value: String,
+    attr_start: Position,
+    attr_end: Position,
+    current_comment: String,
+    current_doctype: Doctype,
+    return_state: State,
+    temporary_buffer: String,
+    char_ref_code: u32,
 }
 
 impl<Sink: TokenSink> Tokenizer<Sink> {
@@ -49,146 +81,941 @@ impl<Sink: TokenSink> Tokenizer<Sink> {
         Tokenizer {
             sink,
             state: State::Data,
+            reconsume: false,
             current_char: '\0',
+            char_start: Position::default(),
+            char_end: Position::default(),
+            token_start: Position::default(),
+            text_start: Position::default(),
             current_chars: String::new(),
             current_tag_kind: TagKind::StartTag,
             current_tag_name: String::new(),
             current_tag_self_closing: false,
             current_tag_attrs: Vec::new(),
+            last_start_tag_name: String::new(),
             current_attr_name: String::new(),
             current_attr_value: String::new(),
+            attr_start: Position::default(),
+            attr_end: Position::default(),
+            current_comment: String::new(),
+            current_doctype: Doctype::default(),
+            return_state: State::Data,
+            temporary_buffer: String::new(),
+            char_ref_code: 0,
         }
     }
 
+    /// Tokenizes the buffered input, stopping where more input is needed
     pub fn feed(&mut self, buffer: &mut Buffer) {
-        while !buffer.is_empty() {
-            let char = buffer.next();
-            self.step(char);
+        while self.step(buffer) {}
+    }
+
+    /// Tokenizes the rest of the input, then emits the end of file
+    pub fn end(&mut self, buffer: &mut Buffer) {
+        buffer.end();
+        self.feed(buffer);
+        self.eof_step();
+    }
+
+    fn get_char(&mut self, buffer: &mut Buffer) -> Option<char> {
+        if self.reconsume {
+            self.reconsume = false;
+            return Some(self.current_char);
+        }
+
+        self.char_start = buffer.position();
+        self.current_char = buffer.next()?;
+        self.char_end = buffer.position();
+
+        let code = self.current_char as u32;
+
+        if is_noncharacter(code) {
+            self.emit_error("noncharacter-in-input-stream");
+        } else if is_control(code) {
+            self.emit_error("control-character-in-input-stream");
         }
+
+        Some(self.current_char)
     }
 
-    pub fn step(&mut self, char: char) {
-        self.current_char = char;
+    /// Runs the current state for one character, returns false once the buffer runs out
+    fn step(&mut self, buffer: &mut Buffer) -> bool {
+        // Wait for the next chunk rather than deciding on a partial lookahead
+        if !buffer.has(self.lookahead()) {
+            return false;
+        }
+
+        if let State::CharacterReference = self.state {
+            match buffer.peek(0) {
+                Some(c) if c.is_ascii_alphanumeric() => go!(self to NamedCharacterReference),
+                Some('#') => {
+                    buffer.next();
+                    go!(self push_temporary '#'; to NumericCharacterReference);
+                }
+                _ => go!(self flush_char_ref; leave_char_ref),
+            }
+
+            return true;
+        }
+
+        if let State::NamedCharacterReference = self.state {
+            return self.consume_named_char_ref(buffer);
+        }
+
+        if let State::MarkupDeclarationOpen = self.state {
+            if buffer.eat("--", false) {
+                go!(self create_comment; to CommentStart);
+            } else if buffer.eat("doctype", true) {
+                go!(self to Doctype);
+            } else if buffer.eat("[CDATA[", false) {
+                if self.sink.is_foreign_content() {
+                    go!(self to CdataSection);
+                } else {
+                    go!(self emit_error "cdata-in-html-content"; create_comment; push_comment "[CDATA["; to BogusComment);
+                }
+            } else {
+                go!(self emit_error "incorrectly-opened-comment"; create_comment; to BogusComment);
+            }
+
+            return true;
+        }
+
+        let char = match self.get_char(buffer) {
+            Some(c) => c,
+            None => return false,
+        };
 
         match self.state {
             State::Data => match char {
-                '<' => go!(self emit_chars; to TagOpen),
+                '&' => self.start_char_ref(),
+                '<' => go!(self start_token; emit_chars; to TagOpen),
+                // Left to the tree builder, which drops it outside of foreign content
+                '\0' => go!(self emit_error "unexpected-null-character"; push_char '\0'),
+                c => self.push_char(c),
+            },
+
+            State::RawData(kind) => match (kind, char) {
+                (RcData, '&') => self.start_char_ref(),
+                (ScriptDataEscaped(escape), '-') => {
+                    go!(self push_char '-'; to ScriptDataEscapedDash escape)
+                }
+                (ScriptDataEscaped(DoubleEscaped), '<') => {
+                    go!(self push_char '<'; to RawLessThanSign kind)
+                }
+                (_, '<') => go!(self start_token; to RawLessThanSign kind),
+                (_, '\0') => self.push_replacement_char(),
+                (_, c) => self.push_char(c),
+            },
+
+            State::PlainText => match char {
+                '\0' => self.push_replacement_char(),
                 c => self.push_char(c),
             },
 
+            State::RawLessThanSign(kind) => match (kind, char) {
+                (ScriptDataEscaped(DoubleEscaped), '/') => {
+                    go!(self clear_temporary; push_char '/'; to ScriptDataDoubleEscapeEnd)
+                }
+                (ScriptDataEscaped(DoubleEscaped), _) => go!(self reconsume; to RawData kind),
+                (_, '/') => go!(self clear_temporary; to RawEndTagOpen kind),
+                (ScriptData, '!') => {
+                    go!(self push_chars "<!"; to ScriptDataEscapeStart Escaped)
+                }
+                (ScriptDataEscaped(Escaped), c) if c.is_ascii_alphabetic() => {
+                    go!(self clear_temporary; push_char '<'; reconsume; to ScriptDataEscapeStart DoubleEscaped)
+                }
+                _ => go!(self push_char '<'; reconsume; to RawData kind),
+            },
+
+            State::RawEndTagOpen(kind) => match lower_ascii_letter(char) {
+                Some(c) => {
+                    go!(self create_tag EndTag c; push_temporary char; to RawEndTagName kind)
+                }
+                None => go!(self push_chars "</"; reconsume; to RawData kind),
+            },
+
+            // Only the end tag matching the last start tag ends the text
+            State::RawEndTagName(kind) => match char {
+                '\t' | '\n' | '\x0C' | ' ' if self.is_appropriate_end_tag() => {
+                    go!(self to BeforeAttributeName)
+                }
+                '/' if self.is_appropriate_end_tag() => go!(self to SelfClosingStartTag),
+                '>' if self.is_appropriate_end_tag() => self.emit_tag(),
+                c => match lower_ascii_letter(c) {
+                    Some(lower) => go!(self push_tag_name lower; push_temporary c),
+                    None => {
+                        go!(self push_chars "</"; flush_temporary; reconsume; to RawData kind)
+                    }
+                },
+            },
+
+            State::ScriptDataEscapeStart(Escaped) => match char {
+                '-' => go!(self push_char '-'; to ScriptDataEscapeStartDash),
+                _ => go!(self reconsume; to RawData ScriptData),
+            },
+
+            // Looks for a nested <script>, whose end tag won't end the outer script
+            State::ScriptDataEscapeStart(DoubleEscaped) => match char {
+                '\t' | '\n' | '\x0C' | ' ' | '/' | '>' => {
+                    let escape = if self.temporary_buffer == "script" {
+                        DoubleEscaped
+                    } else {
+                        Escaped
+                    };
+
+                    go!(self push_char char; to RawData ScriptDataEscaped(escape));
+                }
+                c => match lower_ascii_letter(c) {
+                    Some(lower) => go!(self push_temporary lower; push_char c),
+                    None => go!(self reconsume; to RawData ScriptDataEscaped(Escaped)),
+                },
+            },
+
+            State::ScriptDataEscapeStartDash => match char {
+                '-' => go!(self push_char '-'; to ScriptDataEscapedDashDash Escaped),
+                _ => go!(self reconsume; to RawData ScriptData),
+            },
+
+            State::ScriptDataEscapedDash(escape) => match char {
+                '-' => go!(self push_char '-'; to ScriptDataEscapedDashDash escape),
+                '<' => {
+                    if escape == DoubleEscaped {
+                        self.push_char('<');
+                    }
+
+                    go!(self start_token; to RawLessThanSign ScriptDataEscaped(escape));
+                }
+                '\0' => go!(self push_replacement_char; to RawData ScriptDataEscaped(escape)),
+                c => go!(self push_char c; to RawData ScriptDataEscaped(escape)),
+            },
+
+            State::ScriptDataEscapedDashDash(escape) => match char {
+                '-' => self.push_char('-'),
+                '<' => {
+                    if escape == DoubleEscaped {
+                        self.push_char('<');
+                    }
+
+                    go!(self start_token; to RawLessThanSign ScriptDataEscaped(escape));
+                }
+                '>' => go!(self push_char '>'; to RawData ScriptData),
+                '\0' => go!(self push_replacement_char; to RawData ScriptDataEscaped(escape)),
+                c => go!(self push_char c; to RawData ScriptDataEscaped(escape)),
+            },
+
+            State::ScriptDataDoubleEscapeEnd => match char {
+                '\t' | '\n' | '\x0C' | ' ' | '/' | '>' => {
+                    let escape = if self.temporary_buffer == "script" {
+                        Escaped
+                    } else {
+                        DoubleEscaped
+                    };
+
+                    go!(self push_char char; to RawData ScriptDataEscaped(escape));
+                }
+                c => match lower_ascii_letter(c) {
+                    Some(lower) => go!(self push_temporary lower; push_char c),
+                    None => go!(self reconsume; to RawData ScriptDataEscaped(DoubleEscaped)),
+                },
+            },
+
             State::TagOpen => match char {
+                '!' => go!(self to MarkupDeclarationOpen),
                 '/' => go!(self to EndTagOpen),
+                '?' => {
+                    go!(self emit_error "unexpected-question-mark-instead-of-tag-name"; create_comment; reconsume; to BogusComment)
+                }
                 c => match lower_ascii_letter(c) {
                     Some(c) => go!(self create_tag StartTag c; to TagName),
-                    None => self.emit_error(),
+                    None => {
+                        go!(self emit_error "invalid-first-character-of-tag-name"; push_char '<'; reconsume; to Data)
+                    }
                 },
             },
 
             State::EndTagOpen => match char {
-                '>' => self.emit_error(),
+                '>' => go!(self emit_error "missing-end-tag-name"; to Data),
                 c => match lower_ascii_letter(c) {
                     Some(c) => go!(self create_tag EndTag c; to TagName),
-                    None => self.emit_error(),
+                    None => {
+                        go!(self emit_error "invalid-first-character-of-tag-name"; create_comment; reconsume; to BogusComment)
+                    }
                 },
             },
 
             State::SelfClosingStartTag => match char {
                 '>' => {
                     self.current_tag_self_closing = true;
-                    go!(self emit_tag; to Data);
+                    go!(self emit_tag);
+                }
+                _ => {
+                    go!(self emit_error "unexpected-solidus-in-tag"; reconsume; to BeforeAttributeName)
                 }
-                _ => self.emit_error(),
             },
 
             State::TagName => match char {
                 '\t' | '\n' | '\x0C' | ' ' => go!(self to BeforeAttributeName),
                 '/' => go!(self emit_chars; to SelfClosingStartTag),
-                '>' => go!(self emit_tag; to Data),
+                '>' => go!(self emit_tag),
+                '\0' => go!(self emit_error "unexpected-null-character"; push_tag_name '\u{FFFD}'),
                 c => self.push_tag_name(c.to_ascii_lowercase()),
             },
 
             State::BeforeAttributeName => match char {
                 '\t' | '\n' | '\x0C' | ' ' => (),
-                '/' => go!(self to SelfClosingStartTag),
-                '>' => go!(self emit_tag; to Data),
-                c => match lower_ascii_letter(c) {
-                    Some(c) => go!(self create_attribute c; to AttributeName),
-                    None => self.emit_error(),
-                },
+                '/' | '>' => go!(self reconsume; to AfterAttributeName),
+                '=' => {
+                    go!(self emit_error "unexpected-equals-sign-before-attribute-name"; create_attribute; push_attribute_name '='; to AttributeName)
+                }
+                _ => go!(self create_attribute; reconsume; to AttributeName),
             },
 
             State::AttributeName => match char {
-                '\t' | '\n' | '\x0C' | ' ' => go!(self to AfterAttributeName),
-                '/' => go!(self to SelfClosingStartTag),
+                '\t' | '\n' | '\x0C' | ' ' | '/' | '>' => {
+                    go!(self reconsume; to AfterAttributeName)
+                }
                 '=' => go!(self to BeforeAttributeValue),
-                '>' => go!(self emit_tag; to Data),
-                c => match lower_ascii_letter(c) {
-                    Some(c) => self.push_attribute_name(c),
-                    None => self.emit_error(),
-                },
+                '"' | '\'' | '<' => {
+                    go!(self emit_error "unexpected-character-in-attribute-name"; push_attribute_name char)
+                }
+                '\0' => {
+                    go!(self emit_error "unexpected-null-character"; push_attribute_name '\u{FFFD}')
+                }
+                c => self.push_attribute_name(c.to_ascii_lowercase()),
             },
 
             State::AfterAttributeName => match char {
                 '\t' | '\n' | '\x0C' | ' ' => (),
                 '/' => go!(self to SelfClosingStartTag),
                 '=' => go!(self to BeforeAttributeValue),
-                '>' => go!(self emit_tag; to Data),
-                c => match lower_ascii_letter(c) {
-                    Some(c) => go!(self create_attribute c; to AttributeName),
-                    None => self.emit_error(),
-                },
+                '>' => go!(self emit_tag),
+                _ => go!(self create_attribute; reconsume; to AttributeName),
             },
 
             State::BeforeAttributeValue => match char {
                 '\t' | '\n' | '\r' | '\x0C' | ' ' => (),
                 '"' => go!(self to AttributeValue DoubleQuoted),
                 '\'' => go!(self to AttributeValue SingleQuoted),
-                '>' => go!(self emit_tag; to Data),
-                _ => go!(self to AttributeValue Unquoted),
+                '>' => go!(self emit_error "missing-attribute-value"; emit_tag),
+                _ => go!(self reconsume; to AttributeValue Unquoted),
             },
 
             State::AttributeValue(AttrValueKind::DoubleQuoted) => match char {
-                '"' => go!(self to AfterAttributeValueQuoted),
-                c => self.push_attribute_value(c.to_ascii_lowercase()),
+                '"' => go!(self end_attribute; to AfterAttributeValueQuoted),
+                '&' => self.start_char_ref(),
+                '\0' => {
+                    go!(self emit_error "unexpected-null-character"; push_attribute_value '\u{FFFD}')
+                }
+                c => self.push_attribute_value(c),
             },
 
             State::AttributeValue(AttrValueKind::SingleQuoted) => match char {
-                '\'' => go!(self to AfterAttributeValueQuoted),
-                c => self.push_attribute_value(c.to_ascii_lowercase()),
+                '\'' => go!(self end_attribute; to AfterAttributeValueQuoted),
+                '&' => self.start_char_ref(),
+                '\0' => {
+                    go!(self emit_error "unexpected-null-character"; push_attribute_value '\u{FFFD}')
+                }
+                c => self.push_attribute_value(c),
             },
 
             State::AttributeValue(AttrValueKind::Unquoted) => match char {
                 '\t' | '\n' | '\r' | '\x0C' | ' ' => go!(self to BeforeAttributeName),
-                '/' => go!(self to SelfClosingStartTag),
-                '>' => go!(self emit_tag; to Data),
-                c => self.push_attribute_value(c.to_ascii_lowercase()),
+                '&' => self.start_char_ref(),
+                '>' => go!(self emit_tag),
+                '"' | '\'' | '<' | '=' | '`' => {
+                    go!(self emit_error "unexpected-character-in-unquoted-attribute-value"; push_attribute_value char)
+                }
+                '\0' => {
+                    go!(self emit_error "unexpected-null-character"; push_attribute_value '\u{FFFD}')
+                }
+                c => self.push_attribute_value(c),
             },
 
             State::AfterAttributeValueQuoted => match char {
                 '\t' | '\n' | '\r' | '\x0C' | ' ' => go!(self to BeforeAttributeName),
                 '/' => go!(self to SelfClosingStartTag),
-                '>' => go!(self emit_tag; to Data),
+                '>' => go!(self emit_tag),
+                _ => {
+                    go!(self emit_error "missing-whitespace-between-attributes"; reconsume; to BeforeAttributeName)
+                }
+            },
+
+            State::BogusComment => match char {
+                '>' => go!(self emit_comment; to Data),
+                '\0' => {
+                    go!(self emit_error "unexpected-null-character"; push_comment_char '\u{FFFD}')
+                }
+                c => self.push_comment_char(c),
+            },
+
+            // Handled above, as it looks ahead instead of consuming a character
+            State::MarkupDeclarationOpen => unreachable!(),
+
+            State::CommentStart => match char {
+                '-' => go!(self to CommentStartDash),
+                '>' => {
+                    go!(self emit_error "abrupt-closing-of-empty-comment"; emit_comment; to Data)
+                }
+                _ => go!(self reconsume; to Comment),
+            },
+
+            State::CommentStartDash => match char {
+                '-' => go!(self to CommentEnd),
+                '>' => {
+                    go!(self emit_error "abrupt-closing-of-empty-comment"; emit_comment; to Data)
+                }
+                _ => go!(self push_comment "-"; reconsume; to Comment),
+            },
+
+            State::Comment => match char {
+                '<' => go!(self push_comment "<"; to CommentLessThanSign),
+                '-' => go!(self to CommentEndDash),
+                '\0' => {
+                    go!(self emit_error "unexpected-null-character"; push_comment_char '\u{FFFD}')
+                }
+                c => self.push_comment_char(c),
+            },
+
+            State::CommentLessThanSign => match char {
+                '!' => go!(self push_comment "!"; to CommentLessThanSignBang),
+                '<' => self.push_comment_char('<'),
+                _ => go!(self reconsume; to Comment),
+            },
+
+            State::CommentLessThanSignBang => match char {
+                '-' => go!(self to CommentLessThanSignBangDash),
+                _ => go!(self reconsume; to Comment),
+            },
+
+            State::CommentLessThanSignBangDash => match char {
+                '-' => go!(self to CommentLessThanSignBangDashDash),
+                _ => go!(self reconsume; to CommentEndDash),
+            },
+
+            State::CommentLessThanSignBangDashDash => match char {
+                '>' => go!(self reconsume; to CommentEnd),
+                _ => go!(self emit_error "nested-comment"; reconsume; to CommentEnd),
+            },
+
+            State::CommentEndDash => match char {
+                '-' => go!(self to CommentEnd),
+                _ => go!(self push_comment "-"; reconsume; to Comment),
+            },
+
+            State::CommentEnd => match char {
+                '>' => go!(self emit_comment; to Data),
+                '!' => go!(self to CommentEndBang),
+                '-' => self.push_comment_char('-'),
+                _ => go!(self push_comment "--"; reconsume; to Comment),
+            },
+
+            State::CommentEndBang => match char {
+                '-' => go!(self push_comment "--!"; to CommentEndDash),
+                '>' => go!(self emit_error "incorrectly-closed-comment"; emit_comment; to Data),
+                _ => go!(self push_comment "--!"; reconsume; to Comment),
+            },
+
+            State::Doctype => match char {
+                '\t' | '\n' | '\x0C' | ' ' => go!(self to BeforeDoctypeName),
+                '>' => go!(self reconsume; to BeforeDoctypeName),
+                _ => {
+                    go!(self emit_error "missing-whitespace-before-doctype-name"; reconsume; to BeforeDoctypeName)
+                }
+            },
+
+            State::BeforeDoctypeName => match char {
+                '\t' | '\n' | '\x0C' | ' ' => (),
+                '>' => {
+                    go!(self emit_error "missing-doctype-name"; create_doctype; set_force_quirks; emit_doctype; to Data)
+                }
+                '\0' => {
+                    go!(self emit_error "unexpected-null-character"; create_doctype; push_doctype_name '\u{FFFD}'; to DoctypeName)
+                }
+                c => go!(self create_doctype; push_doctype_name c; to DoctypeName),
+            },
+
+            State::DoctypeName => match char {
+                '\t' | '\n' | '\x0C' | ' ' => go!(self to AfterDoctypeName),
+                '>' => go!(self emit_doctype; to Data),
+                '\0' => {
+                    go!(self emit_error "unexpected-null-character"; push_doctype_name '\u{FFFD}')
+                }
+                c => self.push_doctype_name(c),
+            },
+
+            State::AfterDoctypeName => match char {
+                '\t' | '\n' | '\x0C' | ' ' => (),
+                '>' => go!(self emit_doctype; to Data),
+                'p' | 'P' if buffer.eat("ublic", true) => go!(self to AfterDoctypeKeyword Public),
+                's' | 'S' if buffer.eat("ystem", true) => go!(self to AfterDoctypeKeyword System),
+                _ => {
+                    go!(self emit_error "invalid-character-sequence-after-doctype-name"; set_force_quirks; reconsume; to BogusDoctype)
+                }
+            },
+
+            State::AfterDoctypeKeyword(kind) => match char {
+                '\t' | '\n' | '\x0C' | ' ' => go!(self to BeforeDoctypeIdentifier kind),
+                '"' => {
+                    self.emit_error(by_kind(
+                        kind,
+                        "missing-whitespace-after-doctype-public-keyword",
+                        "missing-whitespace-after-doctype-system-keyword",
+                    ));
+                    go!(self clear_doctype_id kind; to DoctypeIdentifierDoubleQuoted kind);
+                }
+                '\'' => {
+                    self.emit_error(by_kind(
+                        kind,
+                        "missing-whitespace-after-doctype-public-keyword",
+                        "missing-whitespace-after-doctype-system-keyword",
+                    ));
+                    go!(self clear_doctype_id kind; to DoctypeIdentifierSingleQuoted kind);
+                }
+                '>' => {
+                    self.emit_error(by_kind(
+                        kind,
+                        "missing-doctype-public-identifier",
+                        "missing-doctype-system-identifier",
+                    ));
+                    go!(self set_force_quirks; emit_doctype; to Data);
+                }
+                _ => {
+                    self.emit_error(by_kind(
+                        kind,
+                        "missing-quote-before-doctype-public-identifier",
+                        "missing-quote-before-doctype-system-identifier",
+                    ));
+                    go!(self set_force_quirks; reconsume; to BogusDoctype);
+                }
+            },
+
+            State::BeforeDoctypeIdentifier(kind) => match char {
+                '\t' | '\n' | '\x0C' | ' ' => (),
+                '"' => go!(self clear_doctype_id kind; to DoctypeIdentifierDoubleQuoted kind),
+                '\'' => go!(self clear_doctype_id kind; to DoctypeIdentifierSingleQuoted kind),
+                '>' => {
+                    self.emit_error(by_kind(
+                        kind,
+                        "missing-doctype-public-identifier",
+                        "missing-doctype-system-identifier",
+                    ));
+                    go!(self set_force_quirks; emit_doctype; to Data);
+                }
+                _ => {
+                    self.emit_error(by_kind(
+                        kind,
+                        "missing-quote-before-doctype-public-identifier",
+                        "missing-quote-before-doctype-system-identifier",
+                    ));
+                    go!(self set_force_quirks; reconsume; to BogusDoctype);
+                }
+            },
+
+            State::DoctypeIdentifierDoubleQuoted(kind) => match char {
+                '"' => self.finish_doctype_id(kind),
+                '>' => {
+                    self.emit_error(by_kind(
+                        kind,
+                        "abrupt-doctype-public-identifier",
+                        "abrupt-doctype-system-identifier",
+                    ));
+                    go!(self set_force_quirks; emit_doctype; to Data);
+                }
+                '\0' => {
+                    self.emit_error("unexpected-null-character");
+                    self.push_doctype_id(kind, '\u{FFFD}');
+                }
+                c => self.push_doctype_id(kind, c),
+            },
+
+            State::DoctypeIdentifierSingleQuoted(kind) => match char {
+                '\'' => self.finish_doctype_id(kind),
+                '>' => {
+                    self.emit_error(by_kind(
+                        kind,
+                        "abrupt-doctype-public-identifier",
+                        "abrupt-doctype-system-identifier",
+                    ));
+                    go!(self set_force_quirks; emit_doctype; to Data);
+                }
+                '\0' => {
+                    self.emit_error("unexpected-null-character");
+                    self.push_doctype_id(kind, '\u{FFFD}');
+                }
+                c => self.push_doctype_id(kind, c),
+            },
+
+            State::AfterDoctypePublicIdentifier => match char {
+                '\t' | '\n' | '\x0C' | ' ' => go!(self to BetweenDoctypePublicAndSystemIdentifiers),
+                '>' => go!(self emit_doctype; to Data),
+                '"' => {
+                    go!(self emit_error "missing-whitespace-between-doctype-public-and-system-identifiers"; clear_doctype_id System; to DoctypeIdentifierDoubleQuoted System)
+                }
+                '\'' => {
+                    go!(self emit_error "missing-whitespace-between-doctype-public-and-system-identifiers"; clear_doctype_id System; to DoctypeIdentifierSingleQuoted System)
+                }
+                _ => {
+                    go!(self emit_error "missing-quote-before-doctype-system-identifier"; set_force_quirks; reconsume; to BogusDoctype)
+                }
+            },
+
+            State::BetweenDoctypePublicAndSystemIdentifiers => match char {
+                '\t' | '\n' | '\x0C' | ' ' => (),
+                '>' => go!(self emit_doctype; to Data),
+                '"' => go!(self clear_doctype_id System; to DoctypeIdentifierDoubleQuoted System),
+                '\'' => go!(self clear_doctype_id System; to DoctypeIdentifierSingleQuoted System),
+                _ => {
+                    go!(self emit_error "missing-quote-before-doctype-system-identifier"; set_force_quirks; reconsume; to BogusDoctype)
+                }
+            },
+
+            State::AfterDoctypeSystemIdentifier => match char {
+                '\t' | '\n' | '\x0C' | ' ' => (),
+                '>' => go!(self emit_doctype; to Data),
+                _ => {
+                    go!(self emit_error "unexpected-character-after-doctype-system-identifier"; reconsume; to BogusDoctype)
+                }
+            },
+
+            State::BogusDoctype => match char {
+                '>' => go!(self emit_doctype; to Data),
+                '\0' => self.emit_error("unexpected-null-character"),
                 _ => (),
             },
+
+            State::CdataSection => match char {
+                ']' => go!(self to CdataSectionBracket),
+                c => self.push_char(c),
+            },
+
+            State::CdataSectionBracket => match char {
+                ']' => go!(self to CdataSectionEnd),
+                _ => go!(self push_char ']'; reconsume; to CdataSection),
+            },
+
+            State::CdataSectionEnd => match char {
+                ']' => self.push_char(']'),
+                '>' => go!(self to Data),
+                _ => go!(self push_char ']'; push_char ']'; reconsume; to CdataSection),
+            },
+
+            // Handled above, as they look ahead instead of consuming a character
+            State::CharacterReference | State::NamedCharacterReference => unreachable!(),
+
+            State::AmbiguousAmpersand => match char {
+                c if c.is_ascii_alphanumeric() => {
+                    if self.is_in_attribute() {
+                        self.push_attribute_value(c);
+                    } else {
+                        self.push_char(c);
+                    }
+                }
+                ';' => {
+                    go!(self emit_error "unknown-named-character-reference"; reconsume; leave_char_ref)
+                }
+                _ => go!(self reconsume; leave_char_ref),
+            },
+
+            State::NumericCharacterReference => {
+                self.char_ref_code = 0;
+
+                match char {
+                    'x' | 'X' => {
+                        go!(self push_temporary char; to HexadecimalCharacterReferenceStart)
+                    }
+                    _ => go!(self reconsume; to DecimalCharacterReferenceStart),
+                }
+            }
+
+            State::HexadecimalCharacterReferenceStart => match char {
+                c if c.is_ascii_hexdigit() => go!(self reconsume; to HexadecimalCharacterReference),
+                _ => {
+                    go!(self emit_error "absence-of-digits-in-numeric-character-reference"; flush_char_ref; reconsume; leave_char_ref)
+                }
+            },
+
+            State::DecimalCharacterReferenceStart => match char {
+                c if c.is_ascii_digit() => go!(self reconsume; to DecimalCharacterReference),
+                _ => {
+                    go!(self emit_error "absence-of-digits-in-numeric-character-reference"; flush_char_ref; reconsume; leave_char_ref)
+                }
+            },
+
+            State::HexadecimalCharacterReference => match char {
+                c if c.is_ascii_hexdigit() => self.push_char_ref_digit(c, 16),
+                ';' => self.finish_numeric_char_ref(),
+                _ => {
+                    go!(self emit_error "missing-semicolon-after-character-reference"; finish_numeric_char_ref; reconsume)
+                }
+            },
+
+            State::DecimalCharacterReference => match char {
+                c if c.is_ascii_digit() => self.push_char_ref_digit(c, 10),
+                ';' => self.finish_numeric_char_ref(),
+                _ => {
+                    go!(self emit_error "missing-semicolon-after-character-reference"; finish_numeric_char_ref; reconsume)
+                }
+            },
+        }
+
+        true
+    }
+
+    /// Matches the longest name of the named character references table
+    /// Returns false if the reference could go on in the next chunk
+    fn consume_named_char_ref(&mut self, buffer: &mut Buffer) -> bool {
+        let mut name = String::new();
+        let mut matched: Option<(usize, &str)> = None;
+
+        loop {
+            let c = match buffer.peek(name.len()) {
+                Some(c) => c,
+                None if buffer.is_ended() => break,
+                None => return false,
+            };
+
+            name.push(c);
+
+            let (is_prefix, characters) = entities::lookup(&name);
+
+            if let Some(characters) = characters {
+                matched = Some((name.len(), characters));
+            }
+
+            if !is_prefix {
+                break;
+            }
+        }
+
+        let (length, characters) = match matched {
+            Some(m) => m,
+            None => {
+                go!(self flush_char_ref; to AmbiguousAmpersand);
+                return true;
+            }
+        };
+
+        for _ in 0..length {
+            if let Some(c) = buffer.next() {
+                self.push_temporary(c);
+            }
+        }
+
+        if self.temporary_buffer.ends_with(';') {
+            self.temporary_buffer = String::from(characters);
+        } else if self.is_in_attribute()
+            && buffer
+                .peek(0)
+                .is_some_and(|c| c == '=' || c.is_ascii_alphanumeric())
+        {
+            // Left as is for historical reasons, e.g href="?a=1&copy=2"
+        } else {
+            self.emit_error("missing-semicolon-after-character-reference");
+            self.temporary_buffer = String::from(characters);
+        }
+
+        go!(self flush_char_ref; leave_char_ref);
+        true
+    }
+
+    /// How many characters the current state looks at past the current one
+    fn lookahead(&self) -> usize {
+        match self.state {
+            State::CharacterReference => 1,
+            // doctype and [CDATA[
+            State::MarkupDeclarationOpen => 7,
+            // The current character and ublic or ystem
+            State::AfterDoctypeName => 6,
+            _ => 0,
+        }
+    }
+
+    /// Handles the end of the input in the current state
+    fn eof_step(&mut self) {
+        match self.state {
+            State::NumericCharacterReference
+            | State::HexadecimalCharacterReferenceStart
+            | State::DecimalCharacterReferenceStart => {
+                go!(self emit_error "absence-of-digits-in-numeric-character-reference"; flush_char_ref; leave_char_ref)
+            }
+
+            State::HexadecimalCharacterReference | State::DecimalCharacterReference => {
+                go!(self emit_error "missing-semicolon-after-character-reference"; finish_numeric_char_ref)
+            }
+
+            State::AmbiguousAmpersand => self.leave_char_ref(),
+
+            _ => (),
+        }
+
+        match self.state {
+            State::Data | State::BogusComment | State::PlainText => (),
+
+            State::RawData(kind) => self.eof_in_raw_data(kind),
+
+            State::RawLessThanSign(kind) => {
+                if kind != ScriptDataEscaped(DoubleEscaped) {
+                    self.push_char('<');
+                }
+
+                self.eof_in_raw_data(kind);
+            }
+
+            State::RawEndTagOpen(kind) => go!(self push_chars "</"; eof_in_raw_data kind),
+
+            State::RawEndTagName(kind) => {
+                go!(self push_chars "</"; flush_temporary; eof_in_raw_data kind)
+            }
+
+            State::ScriptDataEscapeStart(Escaped) | State::ScriptDataEscapeStartDash => (),
+
+            State::ScriptDataEscapeStart(DoubleEscaped)
+            | State::ScriptDataEscapedDash(_)
+            | State::ScriptDataEscapedDashDash(_)
+            | State::ScriptDataDoubleEscapeEnd => {
+                self.emit_error("eof-in-script-html-comment-like-text")
+            }
+
+            State::TagOpen => go!(self emit_error "eof-before-tag-name"; push_char '<'; to Data),
+
+            State::EndTagOpen => {
+                self.emit_error("eof-before-tag-name");
+                self.current_chars.push_str("</");
+            }
+
+            State::TagName
+            | State::BeforeAttributeName
+            | State::AttributeName
+            | State::AfterAttributeName
+            | State::BeforeAttributeValue
+            | State::AttributeValue(_)
+            | State::AfterAttributeValueQuoted
+            | State::SelfClosingStartTag => self.emit_error("eof-in-tag"),
+
+            State::MarkupDeclarationOpen => {
+                go!(self emit_error "incorrectly-opened-comment"; create_comment; to BogusComment)
+            }
+
+            State::CommentStart
+            | State::CommentStartDash
+            | State::Comment
+            | State::CommentLessThanSign
+            | State::CommentLessThanSignBang
+            | State::CommentLessThanSignBangDash
+            | State::CommentLessThanSignBangDashDash
+            | State::CommentEndDash
+            | State::CommentEnd
+            | State::CommentEndBang => self.emit_error("eof-in-comment"),
+
+            State::Doctype | State::BeforeDoctypeName => {
+                go!(self emit_error "eof-in-doctype"; create_doctype; set_force_quirks; to BogusDoctype)
+            }
+
+            State::DoctypeName
+            | State::AfterDoctypeName
+            | State::AfterDoctypeKeyword(_)
+            | State::BeforeDoctypeIdentifier(_)
+            | State::DoctypeIdentifierDoubleQuoted(_)
+            | State::DoctypeIdentifierSingleQuoted(_)
+            | State::AfterDoctypePublicIdentifier
+            | State::BetweenDoctypePublicAndSystemIdentifiers
+            | State::AfterDoctypeSystemIdentifier => {
+                go!(self emit_error "eof-in-doctype"; set_force_quirks; to BogusDoctype)
+            }
+
+            State::BogusDoctype => (),
+
+            State::CharacterReference
+            | State::NamedCharacterReference
+            | State::AmbiguousAmpersand
+            | State::NumericCharacterReference
+            | State::HexadecimalCharacterReferenceStart
+            | State::DecimalCharacterReferenceStart
+            | State::HexadecimalCharacterReference
+            | State::DecimalCharacterReference => unreachable!(),
+
+            State::CdataSection => self.emit_error("eof-in-cdata"),
+            State::CdataSectionBracket => {
+                go!(self emit_error "eof-in-cdata"; push_char ']'; to Data)
+            }
+            State::CdataSectionEnd => {
+                go!(self emit_error "eof-in-cdata"; push_char ']'; push_char ']'; to Data)
+            }
+        }
+
+        match self.state {
+            State::BogusComment
+            | State::CommentStart
+            | State::CommentStartDash
+            | State::Comment
+            | State::CommentLessThanSign
+            | State::CommentLessThanSignBang
+            | State::CommentLessThanSignBangDash
+            | State::CommentLessThanSignBangDashDash
+            | State::CommentEndDash
+            | State::CommentEnd
+            | State::CommentEndBang => self.emit_comment(),
+
+            State::BogusDoctype => self.emit_doctype(),
+
+            _ => (),
         }
+
+        go!(self emit_eof; to Data);
+    }
+
+    fn eof_in_raw_data(&mut self, kind: RawKind) {
+        if let ScriptDataEscaped(_) = kind {
+            self.emit_error("eof-in-script-html-comment-like-text");
+        }
+    }
+
+    fn reconsume(&mut self) {
+        self.reconsume = true;
     }
 
-    fn emit_error(&mut self) {
-        let msg = format!(
-            "Unexpected character '{}' in state {:?}",
-            self.current_char, self.state
-        );
+    fn emit_error(&mut self, code: &'static str) {
+        let span = Span::new(self.char_start, self.char_end);
+        let error = ParseError {
+            code,
+            message: String::from(describe_error(code)),
+            span,
+        };
 
-        self.process_token(Token::ParseError(msg))
+        self.process_token(Token::ParseError(error), span)
     }
 
+    /// Emits the characters up to the start of the current token
     fn emit_chars(&mut self) {
-        self.process_token(Token::Characters(String::from(&self.current_chars)));
+        if self.current_chars.is_empty() {
+            return;
+        }
+
+        let span = Span::new(self.text_start, self.token_start);
+        self.text_start = self.token_start;
+
+        self.process_token(Token::Characters(String::from(&self.current_chars)), span);
         self.current_chars.clear();
     }
 
+    /// Emits the current tag and goes back to the data state, unless the sink switches to another one
     fn emit_tag(&mut self) {
         self.finish_attribute();
+        self.emit_chars();
+
+        if let EndTag = self.current_tag_kind {
+            if !self.current_tag_attrs.is_empty() {
+                self.emit_error("end-tag-with-attributes");
+            }
+
+            if self.current_tag_self_closing {
+                self.emit_error("end-tag-with-trailing-solidus");
+            }
+        }
+
+        if let StartTag = self.current_tag_kind {
+            self.last_start_tag_name = String::from(&self.current_tag_name);
+        }
 
         let token = Token::Tag(TagToken {
             name: String::from(&self.current_tag_name),
@@ -197,13 +1024,58 @@ impl<Sink: TokenSink> Tokenizer<Sink> {
             attrs: Vec::clone(&self.current_tag_attrs),
         });
 
-        self.process_token(token)
+        let span = self.end_token();
+
+        self.state = State::Data;
+        self.process_token(token, span)
+    }
+
+    fn emit_comment(&mut self) {
+        let comment = String::from(&self.current_comment);
+        let span = self.end_token();
+
+        self.process_token(Token::Comment(comment), span);
+    }
+
+    fn emit_doctype(&mut self) {
+        let doctype = Doctype::clone(&self.current_doctype);
+        let span = self.end_token();
+
+        self.process_token(Token::Doctype(doctype), span);
+    }
+
+    fn emit_eof(&mut self) {
+        self.token_start = self.char_end;
+        self.emit_chars();
+
+        self.process_token(Token::EOF, Span::at(self.char_end));
+    }
+
+    /// Marks the current character as the start of a tag, comment or doctype
+    fn start_token(&mut self) {
+        self.token_start = self.char_start;
+    }
+
+    /// The span of the token ending with the current character, the text following it starts after
+    fn end_token(&mut self) -> Span {
+        self.text_start = self.char_end;
+
+        Span::new(self.token_start, self.char_end)
     }
 
     fn push_char(&mut self, c: char) {
         self.current_chars.push(c)
     }
 
+    /// U+0000 is replaced in the text of elements which can't contain markup
+    fn push_replacement_char(&mut self) {
+        go!(self emit_error "unexpected-null-character"; push_char '\u{FFFD}');
+    }
+
+    fn push_chars(&mut self, s: &str) {
+        self.current_chars.push_str(s)
+    }
+
     fn create_tag(&mut self, kind: TagKind, c: char) {
         self.discard_tag();
         self.current_tag_name.push(c);
@@ -214,24 +1086,35 @@ impl<Sink: TokenSink> Tokenizer<Sink> {
         self.current_tag_name.push(c)
     }
 
+    /// https://html.spec.whatwg.org/multipage/parsing.html#appropriate-end-tag-token
+    fn is_appropriate_end_tag(&self) -> bool {
+        self.current_tag_name == self.last_start_tag_name
+    }
+
     fn discard_tag(&mut self) {
         self.current_tag_name.clear();
         self.current_tag_attrs.clear();
         self.current_tag_self_closing = false;
     }
 
-    fn create_attribute(&mut self, c: char) {
+    fn create_attribute(&mut self) {
         self.finish_attribute();
-
-        self.current_attr_name.push(c);
+        self.attr_start = self.char_start;
     }
 
     fn push_attribute_name(&mut self, c: char) {
         self.current_attr_name.push(c);
+        self.end_attribute();
     }
 
     fn push_attribute_value(&mut self, c: char) {
         self.current_attr_value.push(c);
+        self.end_attribute();
+    }
+
+    /// Extends the attribute span up to the current character
+    fn end_attribute(&mut self) {
+        self.attr_end = self.char_end;
     }
 
     fn finish_attribute(&mut self) {
@@ -239,17 +1122,174 @@ impl<Sink: TokenSink> Tokenizer<Sink> {
             return;
         }
 
-        self.current_tag_attrs.push(Attribute {
-            name: String::from(&self.current_attr_name),
-            value: String::from(&self.current_attr_value),
-        });
+        let duplicate = self
+            .current_tag_attrs
+            .iter()
+            .any(|attr| attr.name == self.current_attr_name);
+
+        // Only the first of duplicate attributes is kept
+        if duplicate {
+            self.emit_error("duplicate-attribute");
+        } else {
+            self.current_tag_attrs.push(Attribute {
+                name: String::from(&self.current_attr_name),
+                value: String::from(&self.current_attr_value),
+                span: Span::new(self.attr_start, self.attr_end),
+            });
+        }
 
         self.current_attr_name.clear();
         self.current_attr_value.clear();
     }
 
-    fn process_token(&mut self, token: Token) {
-        self.sink.process_token(token);
+    fn create_comment(&mut self) {
+        self.current_comment.clear();
+    }
+
+    fn push_comment(&mut self, s: &str) {
+        self.current_comment.push_str(s);
+    }
+
+    fn push_comment_char(&mut self, c: char) {
+        self.current_comment.push(c);
+    }
+
+    fn create_doctype(&mut self) {
+        self.current_doctype = Doctype::default();
+    }
+
+    fn push_doctype_name(&mut self, c: char) {
+        self.current_doctype
+            .name
+            .get_or_insert_with(String::new)
+            .push(c.to_ascii_lowercase());
+    }
+
+    fn set_force_quirks(&mut self) {
+        self.current_doctype.force_quirks = true;
+    }
+
+    fn doctype_id(&mut self, kind: DoctypeIdKind) -> &mut Option<String> {
+        match kind {
+            Public => &mut self.current_doctype.public_id,
+            System => &mut self.current_doctype.system_id,
+        }
+    }
+
+    fn clear_doctype_id(&mut self, kind: DoctypeIdKind) {
+        *self.doctype_id(kind) = Some(String::new());
+    }
+
+    fn push_doctype_id(&mut self, kind: DoctypeIdKind, c: char) {
+        self.doctype_id(kind)
+            .get_or_insert_with(String::new)
+            .push(c);
+    }
+
+    fn finish_doctype_id(&mut self, kind: DoctypeIdKind) {
+        match kind {
+            Public => go!(self to AfterDoctypePublicIdentifier),
+            System => go!(self to AfterDoctypeSystemIdentifier),
+        }
+    }
+
+    fn start_char_ref(&mut self) {
+        self.return_state = self.state;
+        self.temporary_buffer.clear();
+        self.temporary_buffer.push('&');
+
+        go!(self to CharacterReference);
+    }
+
+    fn leave_char_ref(&mut self) {
+        self.state = self.return_state;
+    }
+
+    fn is_in_attribute(&self) -> bool {
+        matches!(self.return_state, State::AttributeValue(_))
+    }
+
+    fn push_temporary(&mut self, c: char) {
+        self.temporary_buffer.push(c);
+    }
+
+    fn clear_temporary(&mut self) {
+        self.temporary_buffer.clear();
+    }
+
+    /// Emits the characters of an end tag which didn't end the raw text
+    fn flush_temporary(&mut self) {
+        self.current_chars.push_str(&self.temporary_buffer);
+        self.temporary_buffer.clear();
+    }
+
+    /// Flushes the code points consumed as a character reference
+    fn flush_char_ref(&mut self) {
+        if self.is_in_attribute() {
+            self.current_attr_value.push_str(&self.temporary_buffer);
+            self.end_attribute();
+        } else {
+            self.current_chars.push_str(&self.temporary_buffer);
+        }
+
+        self.temporary_buffer.clear();
+    }
+
+    fn push_char_ref_digit(&mut self, c: char, radix: u32) {
+        let digit = c.to_digit(radix).unwrap();
+
+        self.char_ref_code = self
+            .char_ref_code
+            .saturating_mul(radix)
+            .saturating_add(digit);
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#numeric-character-reference-end-state
+    fn finish_numeric_char_ref(&mut self) {
+        let code = self.char_ref_code;
+
+        let c = match code {
+            0 => {
+                self.emit_error("null-character-reference");
+                '\u{FFFD}'
+            }
+
+            0xD800..=0xDFFF => {
+                self.emit_error("surrogate-character-reference");
+                '\u{FFFD}'
+            }
+
+            0x110000.. => {
+                self.emit_error("character-reference-outside-unicode-range");
+                '\u{FFFD}'
+            }
+
+            0x80..=0x9F => {
+                self.emit_error("control-character-reference");
+                c1_replacement(code).unwrap_or_else(|| char::from_u32(code).unwrap())
+            }
+
+            _ => {
+                if is_noncharacter(code) {
+                    self.emit_error("noncharacter-character-reference");
+                } else if code == 0x0D || is_control(code) {
+                    self.emit_error("control-character-reference");
+                }
+
+                char::from_u32(code).unwrap()
+            }
+        };
+
+        self.temporary_buffer.clear();
+        self.temporary_buffer.push(c);
+
+        go!(self flush_char_ref; leave_char_ref);
+    }
+
+    fn process_token(&mut self, token: Token, span: Span) {
+        if let TokenSinkResult::SwitchTo(state) = self.sink.process_token(token, span) {
+            self.state = state;
+        }
     }
 }
 
@@ -260,3 +1300,135 @@ pub fn lower_ascii_letter(c: char) -> Option<char> {
         _ => None,
     }
 }
+
+fn is_noncharacter(code: u32) -> bool {
+    matches!(code, 0xFDD0..=0xFDEF) || code & 0xFFFE == 0xFFFE
+}
+
+/// Controls other than ASCII whitespace
+fn is_control(code: u32) -> bool {
+    matches!(code, 0x01..=0x08 | 0x0B | 0x0E..=0x1F | 0x7F..=0x9F)
+}
+
+/// The replacements of numeric references to C1 controls, taken from windows-1252
+fn c1_replacement(code: u32) -> Option<char> {
+    let c = match code {
+        0x80 => '\u{20AC}',
+        0x82 => '\u{201A}',
+        0x83 => '\u{0192}',
+        0x84 => '\u{201E}',
+        0x85 => '\u{2026}',
+        0x86 => '\u{2020}',
+        0x87 => '\u{2021}',
+        0x88 => '\u{02C6}',
+        0x89 => '\u{2030}',
+        0x8A => '\u{0160}',
+        0x8B => '\u{2039}',
+        0x8C => '\u{0152}',
+        0x8E => '\u{017D}',
+        0x91 => '\u{2018}',
+        0x92 => '\u{2019}',
+        0x93 => '\u{201C}',
+        0x94 => '\u{201D}',
+        0x95 => '\u{2022}',
+        0x96 => '\u{2013}',
+        0x97 => '\u{2014}',
+        0x98 => '\u{02DC}',
+        0x99 => '\u{2122}',
+        0x9A => '\u{0161}',
+        0x9B => '\u{203A}',
+        0x9C => '\u{0153}',
+        0x9E => '\u{017E}',
+        0x9F => '\u{0178}',
+        _ => return None,
+    };
+
+    Some(c)
+}
+
+/// Picks the public or the system variant of a DOCTYPE identifier error
+fn by_kind(kind: DoctypeIdKind, public: &'static str, system: &'static str) -> &'static str {
+    match kind {
+        Public => public,
+        System => system,
+    }
+}
+
+fn describe_error(code: &str) -> &'static str {
+    match code {
+        "abrupt-closing-of-empty-comment" => "Empty comment closed by '>' too early",
+        "abrupt-doctype-public-identifier" => "DOCTYPE public identifier closed by '>' too early",
+        "abrupt-doctype-system-identifier" => "DOCTYPE system identifier closed by '>' too early",
+        "absence-of-digits-in-numeric-character-reference" => {
+            "Numeric character reference without digits"
+        }
+        "cdata-in-html-content" => "CDATA section outside of SVG or MathML content",
+        "character-reference-outside-unicode-range" => "Character reference beyond U+10FFFF",
+        "control-character-reference" => "Character reference to a control character",
+        "duplicate-attribute" => "Attribute specified more than once",
+        "end-tag-with-attributes" => "End tag with attributes",
+        "end-tag-with-trailing-solidus" => "End tag with a trailing '/'",
+        "eof-before-tag-name" => "Unexpected end of file before a tag name",
+        "eof-in-cdata" => "Unexpected end of file in a CDATA section",
+        "eof-in-comment" => "Unexpected end of file in a comment",
+        "eof-in-doctype" => "Unexpected end of file in a DOCTYPE",
+        "unexpected-null-character" => "Unexpected U+0000 character",
+        "noncharacter-in-input-stream" => "Noncharacter code point in the input",
+        "control-character-in-input-stream" => "Control character in the input",
+        "eof-in-script-html-comment-like-text" => {
+            "Unexpected end of file in a '<!--' section of a script"
+        }
+        "eof-in-tag" => "Unexpected end of file in a tag",
+        "incorrectly-closed-comment" => "Comment closed by '--!>'",
+        "incorrectly-opened-comment" => {
+            "Markup declaration which isn't a comment, DOCTYPE or CDATA"
+        }
+        "invalid-character-sequence-after-doctype-name" => {
+            "Expected PUBLIC or SYSTEM after the DOCTYPE name"
+        }
+        "invalid-first-character-of-tag-name" => "Tag name starting with a non ASCII letter",
+        "missing-attribute-value" => "Missing attribute value after '='",
+        "missing-doctype-name" => "DOCTYPE without a name",
+        "missing-doctype-public-identifier" => "Missing DOCTYPE public identifier",
+        "missing-doctype-system-identifier" => "Missing DOCTYPE system identifier",
+        "missing-end-tag-name" => "End tag without a name",
+        "missing-quote-before-doctype-public-identifier" => {
+            "DOCTYPE public identifier without quotes"
+        }
+        "missing-quote-before-doctype-system-identifier" => {
+            "DOCTYPE system identifier without quotes"
+        }
+        "missing-semicolon-after-character-reference" => {
+            "Character reference without a trailing ';'"
+        }
+        "missing-whitespace-after-doctype-public-keyword" => {
+            "Missing whitespace after the PUBLIC keyword"
+        }
+        "missing-whitespace-after-doctype-system-keyword" => {
+            "Missing whitespace after the SYSTEM keyword"
+        }
+        "missing-whitespace-before-doctype-name" => "Missing whitespace before the DOCTYPE name",
+        "missing-whitespace-between-attributes" => "Missing whitespace between attributes",
+        "missing-whitespace-between-doctype-public-and-system-identifiers" => {
+            "Missing whitespace between the DOCTYPE identifiers"
+        }
+        "nested-comment" => "Nested comment",
+        "noncharacter-character-reference" => "Character reference to a noncharacter",
+        "null-character-reference" => "Character reference to U+0000",
+        "surrogate-character-reference" => "Character reference to a surrogate",
+        "unexpected-character-after-doctype-system-identifier" => {
+            "Unexpected character after the DOCTYPE system identifier"
+        }
+        "unexpected-character-in-attribute-name" => "Unexpected quote or '<' in an attribute name",
+        "unexpected-character-in-unquoted-attribute-value" => {
+            "Unexpected character in an unquoted attribute value"
+        }
+        "unexpected-equals-sign-before-attribute-name" => "Unexpected '=' before an attribute name",
+        "unexpected-question-mark-instead-of-tag-name" => {
+            "Processing instruction treated as a comment"
+        }
+        "unexpected-solidus-in-tag" => "Unexpected '/' in a tag",
+        "unknown-named-character-reference" => "Unknown named character reference",
+        _ => "Parse error",
+    }
+}
@@ -0,0 +1,1116 @@
+use std::mem;
+
+use encoding_rs::Encoding;
+
+use self::tag_sets::{
+    adjust_svg_attribute_name, adjust_svg_tag_name, has_implied_end_tag,
+    has_implied_end_tag_thoroughly, is_mathml_text_integration_point, is_special, Scope,
+};
+use super::dom::{Document, NodeId};
+use super::interface::{
+    Doctype, Element, Namespace, NodeData, ParseError, QuirksMode, RawKind, State, TagKind,
+    TagToken, Token, TokenSink, TokenSinkResult,
+};
+use crate::span::Span;
+
+mod rules;
+mod tag_sets;
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#the-insertion-mode
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InsertionMode {
+    Initial,
+    BeforeHtml,
+    BeforeHead,
+    InHead,
+    InHeadNoscript,
+    AfterHead,
+    InBody,
+    Text,
+    InTable,
+    InTableText,
+    InCaption,
+    InColumnGroup,
+    InTableBody,
+    InRow,
+    InCell,
+    InSelect,
+    InSelectInTable,
+    InTemplate,
+    AfterBody,
+    InFrameset,
+    AfterFrameset,
+    AfterAfterBody,
+    AfterAfterFrameset,
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#list-of-active-formatting-elements
+enum FormatEntry {
+    Element(NodeId, TagToken),
+    Marker,
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#appropriate-place-for-inserting-a-node
+struct InsertionPoint {
+    parent: NodeId,
+    before: Option<NodeId>,
+}
+
+/// Builds the document tree out of the tokens, following
+/// https://html.spec.whatwg.org/multipage/parsing.html#tree-construction
+pub struct TreeBuilder {
+    pub errors: Vec<ParseError>,
+    /// The encoding of the first <meta> declaring one, see
+    /// https://html.spec.whatwg.org/multipage/parsing.html#changing-the-encoding-while-parsing
+    pub declared_encoding: Option<&'static Encoding>,
+    document: Document,
+    mode: InsertionMode,
+    original_mode: InsertionMode,
+    template_modes: Vec<InsertionMode>,
+    open_elements: Vec<NodeId>,
+    active_formatting: Vec<FormatEntry>,
+    head: Option<NodeId>,
+    form: Option<NodeId>,
+    frameset_ok: bool,
+    foster_parenting: bool,
+    ignore_lf: bool,
+    self_closing_acknowledged: bool,
+    pending_table_text: Vec<String>,
+    tokenizer_state: Option<State>,
+    /// Span of the token being processed
+    span: Span,
+}
+
+impl TokenSink for TreeBuilder {
+    fn process_token(&mut self, token: Token, span: Span) -> TokenSinkResult {
+        self.span = span;
+
+        match token {
+            Token::ParseError(error) => self.errors.push(error),
+
+            Token::Characters(mut text) => {
+                // A newline right after <pre>, <listing> or <textarea> is ignored
+                if mem::replace(&mut self.ignore_lf, false) && text.starts_with('\n') {
+                    text.remove(0);
+                }
+
+                for run in split_whitespace(&text) {
+                    if run.starts_with('\0') {
+                        self.process_null_characters(run);
+                    } else {
+                        self.process(Token::Characters(run));
+                    }
+                }
+            }
+
+            token => {
+                let self_closing = matches!(
+                    &token,
+                    Token::Tag(TagToken {
+                        kind: TagKind::StartTag,
+                        self_closing: true,
+                        ..
+                    })
+                );
+
+                self.ignore_lf = false;
+                self.self_closing_acknowledged = false;
+                self.process(token);
+
+                // Only void and foreign elements can be written as <element />
+                if self_closing && !self.self_closing_acknowledged {
+                    self.error(
+                        "non-void-html-element-start-tag-with-trailing-solidus",
+                        String::from("\"/>\" can only close void and foreign elements"),
+                    );
+                }
+            }
+        }
+
+        match self.tokenizer_state.take() {
+            Some(state) => TokenSinkResult::SwitchTo(state),
+            None => TokenSinkResult::Continue,
+        }
+    }
+
+    fn is_foreign_content(&self) -> bool {
+        self.current()
+            .and_then(|h| self.element(h))
+            .is_some_and(|element| element.namespace != Namespace::Html)
+    }
+}
+
+impl TreeBuilder {
+    pub fn new() -> TreeBuilder {
+        TreeBuilder {
+            errors: vec![],
+            declared_encoding: None,
+            document: Document::new(),
+            mode: InsertionMode::Initial,
+            original_mode: InsertionMode::Initial,
+            template_modes: vec![],
+            open_elements: vec![],
+            active_formatting: vec![],
+            head: None,
+            form: None,
+            frameset_ok: true,
+            foster_parenting: false,
+            ignore_lf: false,
+            self_closing_acknowledged: false,
+            pending_table_text: vec![],
+            tokenizer_state: None,
+            span: Span::default(),
+        }
+    }
+
+    /// Moves the document out of the builder, along with the parse errors
+    pub fn finish(self) -> (Document, Vec<ParseError>) {
+        (self.document, self.errors)
+    }
+
+    /// Processes the token according to the current insertion mode, or as
+    /// foreign content inside SVG and MathML
+    fn process(&mut self, token: Token) {
+        if self.is_foreign(&token) {
+            self.in_foreign_content(token);
+        } else {
+            self.step(self.mode, token);
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#tree-construction-dispatcher
+    fn is_foreign(&self, token: &Token) -> bool {
+        let element = match self.current().and_then(|h| self.element(h)) {
+            Some(element) if element.namespace != Namespace::Html => element,
+            _ => return false,
+        };
+
+        let start_tag = match token {
+            Token::Tag(tag) if matches!(tag.kind, TagKind::StartTag) => Some(tag.name.as_str()),
+            _ => None,
+        };
+        let characters = matches!(token, Token::Characters(_));
+
+        if let Token::EOF = token {
+            return false;
+        }
+
+        if element.namespace == Namespace::MathMl
+            && is_mathml_text_integration_point(&element.name)
+            && (characters
+                || start_tag.is_some_and(|name| name != "mglyph" && name != "malignmark"))
+        {
+            return false;
+        }
+
+        if element.namespace == Namespace::MathMl
+            && element.name == "annotation-xml"
+            && start_tag == Some("svg")
+        {
+            return false;
+        }
+
+        !(is_html_integration_point(element) && (characters || start_tag.is_some()))
+    }
+
+    fn element(&self, handle: NodeId) -> Option<&Element> {
+        self.document[handle].as_element()
+    }
+
+    /// The name of an HTML element, foreign elements never match HTML names
+    fn name(&self, handle: NodeId) -> &str {
+        match self.element(handle) {
+            Some(element) if element.namespace == Namespace::Html => &element.name,
+            _ => "",
+        }
+    }
+
+    fn is_special(&self, handle: NodeId) -> bool {
+        self.element(handle)
+            .is_some_and(|element| is_special(element.namespace, &element.name))
+    }
+
+    fn current(&self) -> Option<NodeId> {
+        self.open_elements.last().copied()
+    }
+
+    /// The tokenizer already reported U+0000 in text, it's dropped outside of
+    /// foreign content and replaced inside
+    fn process_null_characters(&mut self, run: String) {
+        let replaced = run.replace('\0', "\u{FFFD}");
+        let token = Token::Characters(replaced);
+
+        if self.is_foreign(&token) {
+            self.process(token);
+        }
+    }
+
+    fn current_name(&self) -> &str {
+        self.current().map_or("", |h| self.name(h))
+    }
+
+    fn has_open(&self, name: &str) -> bool {
+        self.open_elements.iter().any(|&h| self.name(h) == name)
+    }
+
+    fn new_node(&mut self, data: NodeData) -> NodeId {
+        self.document.create_node(data, self.span)
+    }
+
+    fn create_element(&mut self, tag: &TagToken) -> NodeId {
+        self.create_element_in(tag, Namespace::Html)
+    }
+
+    fn create_element_in(&mut self, tag: &TagToken, namespace: Namespace) -> NodeId {
+        self.new_node(NodeData::Element(Element {
+            name: tag.name.clone(),
+            namespace,
+            attrs: tag.attrs.clone(),
+        }))
+    }
+
+    fn append_child(&mut self, parent: NodeId, child: NodeId) {
+        self.document.append(parent, child);
+    }
+
+    fn insert_at(&mut self, point: InsertionPoint, child: NodeId) {
+        match point.before {
+            Some(before) => self.document.move_before(before, child),
+            None => self.document.append(point.parent, child),
+        }
+    }
+
+    fn detach(&mut self, handle: NodeId) {
+        self.document.detach(handle);
+    }
+
+    fn reparent_children(&mut self, from: NodeId, to: NodeId) {
+        while let Some(child) = self.document[from].first_child() {
+            self.document.append(to, child);
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#appropriate-place-for-inserting-a-node
+    fn appropriate_place(&self, target: Option<NodeId>) -> InsertionPoint {
+        let target = target
+            .or_else(|| self.current())
+            .unwrap_or(self.document.root());
+
+        if self.foster_parenting
+            && matches!(
+                self.name(target),
+                "table" | "tbody" | "tfoot" | "thead" | "tr"
+            )
+        {
+            let last_template = self
+                .open_elements
+                .iter()
+                .rposition(|&h| self.name(h) == "template");
+            let last_table = self
+                .open_elements
+                .iter()
+                .rposition(|&h| self.name(h) == "table");
+
+            match (last_template, last_table) {
+                (Some(template), table) if table.is_none_or(|table| template > table) => {
+                    return InsertionPoint {
+                        parent: self.open_elements[template],
+                        before: None,
+                    };
+                }
+
+                (_, None) => {
+                    return InsertionPoint {
+                        parent: self.open_elements[0],
+                        before: None,
+                    };
+                }
+
+                (_, Some(index)) => {
+                    let table = self.open_elements[index];
+
+                    return match self.document[table].parent() {
+                        Some(parent) => InsertionPoint {
+                            parent,
+                            before: Some(table),
+                        },
+                        None => InsertionPoint {
+                            parent: self.open_elements[index - 1],
+                            before: None,
+                        },
+                    };
+                }
+            }
+        }
+
+        InsertionPoint {
+            parent: target,
+            before: None,
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-an-html-element
+    fn insert_element(&mut self, tag: &TagToken) -> NodeId {
+        let element = self.create_element(tag);
+        let point = self.appropriate_place(None);
+
+        self.insert_at(point, element);
+        self.open_elements.push(element);
+        element
+    }
+
+    /// Inserts an element which can't have children, like <br> or <img>
+    fn insert_void_element(&mut self, tag: &TagToken) {
+        self.insert_element(tag);
+        self.open_elements.pop();
+        self.self_closing_acknowledged = true;
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-a-foreign-element
+    fn insert_foreign_element(&mut self, tag: &TagToken, namespace: Namespace) {
+        let mut tag = tag.clone();
+
+        match namespace {
+            Namespace::Svg => {
+                if let Some(name) = adjust_svg_tag_name(&tag.name) {
+                    tag.name = String::from(name);
+                }
+
+                for attr in &mut tag.attrs {
+                    if let Some(name) = adjust_svg_attribute_name(&attr.name) {
+                        attr.name = String::from(name);
+                    }
+                }
+            }
+
+            Namespace::MathMl => {
+                for attr in &mut tag.attrs {
+                    if attr.name == "definitionurl" {
+                        attr.name = String::from("definitionURL");
+                    }
+                }
+            }
+
+            Namespace::Html => (),
+        }
+
+        let element = self.create_element_in(&tag, namespace);
+        let point = self.appropriate_place(None);
+
+        self.insert_at(point, element);
+
+        // Foreign elements can be closed with "/>"
+        if tag.self_closing {
+            self.self_closing_acknowledged = true;
+        } else {
+            self.open_elements.push(element);
+        }
+    }
+
+    /// Inserts an element whose start tag is implied, like <html> or <tbody>
+    fn insert_implied_element(&mut self, name: &str) -> NodeId {
+        self.insert_element(&implied_tag(name))
+    }
+
+    fn insert_characters(&mut self, text: &str) {
+        let point = self.appropriate_place(None);
+
+        if point.parent == self.document.root() {
+            return;
+        }
+
+        let previous = match point.before {
+            Some(before) => self.document[before].previous_sibling(),
+            None => self.document[point.parent].last_child(),
+        };
+
+        // Adjacent text is merged into a single node
+        if let Some(previous) = previous {
+            let node = &mut self.document[previous];
+
+            if let NodeData::Text(existing) = &mut node.data {
+                existing.push_str(text);
+                node.span = node.span.to(self.span);
+                return;
+            }
+        }
+
+        let node = self.new_node(NodeData::Text(String::from(text)));
+        self.insert_at(point, node);
+    }
+
+    fn insert_comment(&mut self, text: String) {
+        let point = self.appropriate_place(None);
+        let node = self.new_node(NodeData::Comment(text));
+
+        self.insert_at(point, node);
+    }
+
+    fn append_comment(&mut self, parent: NodeId, text: String) {
+        let node = self.new_node(NodeData::Comment(text));
+        self.append_child(parent, node);
+    }
+
+    fn append_doctype(&mut self, doctype: Doctype) {
+        let node = self.new_node(NodeData::Doctype(doctype));
+        self.append_child(self.document.root(), node);
+    }
+
+    /// Copies the attributes the element doesn't have yet, for a repeated <html> or <body>
+    fn add_missing_attributes(&mut self, handle: NodeId, tag: &TagToken) {
+        if let NodeData::Element(element) = &mut self.document[handle].data {
+            for attr in &tag.attrs {
+                if !element.attrs.iter().any(|a| a.name == attr.name) {
+                    element.attrs.push(attr.clone());
+                }
+            }
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#generic-raw-text-element-parsing-algorithm
+    fn parse_raw_text(&mut self, tag: &TagToken, kind: RawKind) {
+        self.insert_element(tag);
+        self.tokenizer_state = Some(State::RawData(kind));
+        self.original_mode = self.mode;
+        self.mode = InsertionMode::Text;
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-the-specific-scope
+    fn in_scope<F: Fn(&str) -> bool>(&self, scope: Scope, target: F) -> bool {
+        for &h in self.open_elements.iter().rev() {
+            let name = self.name(h);
+
+            if target(name) {
+                return true;
+            }
+
+            if self.is_scope_boundary(scope, h) {
+                return false;
+            }
+        }
+
+        false
+    }
+
+    fn in_scope_named(&self, scope: Scope, name: &str) -> bool {
+        self.in_scope(scope, |n| n == name)
+    }
+
+    fn element_in_scope(&self, scope: Scope, handle: NodeId) -> bool {
+        for &h in self.open_elements.iter().rev() {
+            if h == handle {
+                return true;
+            }
+
+            if self.is_scope_boundary(scope, h) {
+                return false;
+            }
+        }
+
+        false
+    }
+
+    fn is_scope_boundary(&self, scope: Scope, handle: NodeId) -> bool {
+        self.element(handle)
+            .is_some_and(|element| scope.is_boundary(element.namespace, &element.name))
+    }
+
+    /// Pops elements up to and including the first one matching
+    fn pop_until<F: Fn(&str) -> bool>(&mut self, target: F) {
+        while let Some(h) = self.open_elements.pop() {
+            if target(self.name(h)) {
+                break;
+            }
+        }
+    }
+
+    fn pop_until_named(&mut self, name: &str) {
+        self.pop_until(|n| n == name);
+    }
+
+    /// Pops elements until the current node is one of `names`
+    fn clear_stack_back_to(&mut self, names: &[&str]) {
+        while !names.contains(&self.current_name()) && self.open_elements.len() > 1 {
+            self.open_elements.pop();
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#generate-implied-end-tags
+    fn generate_implied_end_tags(&mut self, except: &str) {
+        loop {
+            let name = self.current_name();
+
+            if !has_implied_end_tag(name) || name == except {
+                break;
+            }
+
+            self.open_elements.pop();
+        }
+    }
+
+    fn generate_implied_end_tags_thoroughly(&mut self) {
+        while has_implied_end_tag_thoroughly(self.current_name()) {
+            self.open_elements.pop();
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#close-a-p-element
+    fn close_p_element(&mut self) {
+        self.generate_implied_end_tags("p");
+
+        if self.current_name() != "p" {
+            self.unclosed_elements_error("p");
+        }
+
+        self.pop_until_named("p");
+    }
+
+    fn close_p_in_button_scope(&mut self) {
+        if self.in_scope_named(Scope::Button, "p") {
+            self.close_p_element();
+        }
+    }
+
+    /// Closes an open element before an <li>, <dd> or <dt> start tag
+    fn close_list_item(&mut self, names: &[&str]) {
+        for i in (0..self.open_elements.len()).rev() {
+            let name = String::from(self.name(self.open_elements[i]));
+
+            if names.contains(&name.as_str()) {
+                self.generate_implied_end_tags(&name);
+
+                if self.current_name() != name {
+                    self.unclosed_elements_error(&name);
+                }
+
+                self.pop_until_named(&name);
+                break;
+            }
+
+            if self.is_special(self.open_elements[i])
+                && !matches!(name.as_str(), "address" | "div" | "p")
+            {
+                break;
+            }
+        }
+    }
+
+    /// Ends a block whose end tag was found in the right scope
+    fn close_element(&mut self, name: &str) {
+        self.generate_implied_end_tags("");
+
+        if self.current_name() != name {
+            self.unclosed_elements_error(name);
+        }
+
+        self.pop_until_named(name);
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#reset-the-insertion-mode-appropriately
+    fn reset_insertion_mode(&mut self) {
+        self.mode = self.appropriate_mode();
+    }
+
+    fn appropriate_mode(&self) -> InsertionMode {
+        for (i, &node) in self.open_elements.iter().enumerate().rev() {
+            let last = i == 0;
+
+            match self.name(node) {
+                "select" => {
+                    for &ancestor in self.open_elements[..i].iter().rev() {
+                        match self.name(ancestor) {
+                            "template" => break,
+                            "table" => return InsertionMode::InSelectInTable,
+                            _ => (),
+                        }
+                    }
+
+                    return InsertionMode::InSelect;
+                }
+
+                "td" | "th" if !last => return InsertionMode::InCell,
+                "tr" => return InsertionMode::InRow,
+                "tbody" | "thead" | "tfoot" => return InsertionMode::InTableBody,
+                "caption" => return InsertionMode::InCaption,
+                "colgroup" => return InsertionMode::InColumnGroup,
+                "table" => return InsertionMode::InTable,
+                "template" => return *self.template_modes.last().unwrap_or(&InsertionMode::InBody),
+                "head" if !last => return InsertionMode::InHead,
+                "body" => return InsertionMode::InBody,
+                "frameset" => return InsertionMode::InFrameset,
+                "html" if self.head.is_none() => return InsertionMode::BeforeHead,
+                "html" => return InsertionMode::AfterHead,
+                _ => (),
+            }
+        }
+
+        InsertionMode::InBody
+    }
+
+    fn formatting_position(&self, handle: NodeId) -> Option<usize> {
+        self.active_formatting
+            .iter()
+            .position(|entry| matches!(entry, FormatEntry::Element(h, _) if *h == handle))
+    }
+
+    /// The last formatting element with that name after the last marker
+    fn formatting_element(&self, name: &str) -> Option<(usize, NodeId, TagToken)> {
+        for (i, entry) in self.active_formatting.iter().enumerate().rev() {
+            match entry {
+                FormatEntry::Marker => break,
+                FormatEntry::Element(h, tag) if tag.name == name => {
+                    return Some((i, *h, tag.clone()))
+                }
+                _ => (),
+            }
+        }
+
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#push-onto-the-list-of-active-formatting-elements
+    fn push_formatting_element(&mut self, handle: NodeId, tag: TagToken) {
+        let mut identical: Vec<usize> = vec![];
+
+        for (i, entry) in self.active_formatting.iter().enumerate().rev() {
+            match entry {
+                FormatEntry::Marker => break,
+                FormatEntry::Element(_, other) if is_same_element(&tag, other) => identical.push(i),
+                _ => (),
+            }
+        }
+
+        // Only three identical elements are kept, the earliest one goes first
+        if identical.len() >= 3 {
+            self.active_formatting
+                .remove(identical[identical.len() - 1]);
+        }
+
+        self.active_formatting
+            .push(FormatEntry::Element(handle, tag));
+    }
+
+    fn push_marker(&mut self) {
+        self.active_formatting.push(FormatEntry::Marker);
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#clear-the-list-of-active-formatting-elements-up-to-the-last-marker
+    fn clear_formatting_to_last_marker(&mut self) {
+        while let Some(entry) = self.active_formatting.pop() {
+            if let FormatEntry::Marker = entry {
+                break;
+            }
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#reconstruct-the-active-formatting-elements
+    fn reconstruct_active_formatting(&mut self) {
+        let is_open = |builder: &TreeBuilder, entry: &FormatEntry| match entry {
+            FormatEntry::Marker => true,
+            FormatEntry::Element(h, _) => builder.open_elements.contains(h),
+        };
+
+        match self.active_formatting.last() {
+            Some(entry) if !is_open(self, entry) => (),
+            _ => return,
+        }
+
+        let mut index = self.active_formatting.len() - 1;
+        while index > 0 && !is_open(self, &self.active_formatting[index - 1]) {
+            index -= 1;
+        }
+
+        for i in index..self.active_formatting.len() {
+            let tag = match &self.active_formatting[i] {
+                FormatEntry::Element(_, tag) => tag.clone(),
+                FormatEntry::Marker => continue,
+            };
+
+            let element = self.insert_element(&tag);
+            self.active_formatting[i] = FormatEntry::Element(element, tag);
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#adoption-agency-algorithm
+    fn adoption_agency(&mut self, tag: &TagToken) {
+        let subject = tag.name.as_str();
+
+        if let Some(current) = self.current() {
+            if self.name(current) == subject && self.formatting_position(current).is_none() {
+                self.open_elements.pop();
+                return;
+            }
+        }
+
+        for _ in 0..8 {
+            let (formatting_index, formatting_element, formatting_tag) =
+                match self.formatting_element(subject) {
+                    Some(found) => found,
+                    None => return self.close_any_element(tag),
+                };
+
+            let stack_index = match self
+                .open_elements
+                .iter()
+                .position(|&h| h == formatting_element)
+            {
+                Some(index) => index,
+                None => {
+                    self.misnested_tags_error(subject);
+                    self.active_formatting.remove(formatting_index);
+                    return;
+                }
+            };
+
+            if !self.element_in_scope(Scope::Default, formatting_element) {
+                self.misnested_tags_error(subject);
+                return;
+            }
+
+            if self.current() != Some(formatting_element) {
+                self.misnested_tags_error(subject);
+            }
+
+            let furthest_block_index = match self.open_elements[stack_index + 1..]
+                .iter()
+                .position(|&h| self.is_special(h))
+            {
+                Some(offset) => stack_index + 1 + offset,
+                None => {
+                    self.open_elements.truncate(stack_index);
+                    self.active_formatting.remove(formatting_index);
+                    return;
+                }
+            };
+
+            let furthest_block = self.open_elements[furthest_block_index];
+            let common_ancestor = self.open_elements[stack_index - 1];
+            let mut bookmark = formatting_index;
+            let mut node_index = furthest_block_index;
+            let mut last_node = furthest_block;
+
+            for inner in 1.. {
+                node_index -= 1;
+                let node = self.open_elements[node_index];
+
+                if node == formatting_element {
+                    break;
+                }
+
+                let mut position = self.formatting_position(node);
+
+                if inner > 3 {
+                    if let Some(p) = position.take() {
+                        self.active_formatting.remove(p);
+
+                        if p < bookmark {
+                            bookmark -= 1;
+                        }
+                    }
+                }
+
+                let position = match position {
+                    Some(p) => p,
+                    None => {
+                        self.open_elements.remove(node_index);
+                        continue;
+                    }
+                };
+
+                let node_tag = match &self.active_formatting[position] {
+                    FormatEntry::Element(_, tag) => tag.clone(),
+                    FormatEntry::Marker => break,
+                };
+
+                let replacement = self.create_element(&node_tag);
+                self.active_formatting[position] = FormatEntry::Element(replacement, node_tag);
+                self.open_elements[node_index] = replacement;
+
+                if last_node == furthest_block {
+                    bookmark = position + 1;
+                }
+
+                self.detach(last_node);
+                self.append_child(replacement, last_node);
+                last_node = replacement;
+            }
+
+            self.detach(last_node);
+            let point = self.appropriate_place(Some(common_ancestor));
+            self.insert_at(point, last_node);
+
+            let element = self.create_element(&formatting_tag);
+            self.reparent_children(furthest_block, element);
+            self.append_child(furthest_block, element);
+
+            if let Some(p) = self.formatting_position(formatting_element) {
+                self.active_formatting.remove(p);
+
+                if p < bookmark {
+                    bookmark -= 1;
+                }
+            }
+
+            let bookmark = bookmark.min(self.active_formatting.len());
+            self.active_formatting
+                .insert(bookmark, FormatEntry::Element(element, formatting_tag));
+
+            self.open_elements.retain(|&h| h != formatting_element);
+            if let Some(index) = self.open_elements.iter().position(|&h| h == furthest_block) {
+                self.open_elements.insert(index + 1, element);
+            }
+        }
+    }
+
+    /// The "any other end tag" steps of the "in body" insertion mode
+    fn close_any_element(&mut self, tag: &TagToken) {
+        for i in (0..self.open_elements.len()).rev() {
+            let node = self.open_elements[i];
+
+            if self.name(node) == tag.name {
+                self.generate_implied_end_tags(&tag.name);
+
+                if self.current() != Some(node) {
+                    self.unclosed_elements_error(&tag.name);
+                }
+
+                self.open_elements.truncate(i);
+                return;
+            }
+
+            if self.is_special(node) {
+                self.unexpected(&Token::Tag(tag.clone()));
+                return;
+            }
+        }
+    }
+
+    fn error(&mut self, code: &'static str, message: String) {
+        self.errors.push(ParseError {
+            code,
+            message,
+            span: self.span,
+        });
+    }
+
+    fn unexpected(&mut self, token: &Token) {
+        let (code, message) = match token {
+            Token::Tag(TagToken {
+                kind: TagKind::StartTag,
+                name,
+                ..
+            }) => ("unexpected-start-tag", format!("Unexpected <{}>", name)),
+
+            Token::Tag(TagToken {
+                kind: TagKind::EndTag,
+                name,
+                ..
+            }) => ("unexpected-end-tag", format!("Unexpected </{}>", name)),
+
+            Token::Doctype(_) => ("unexpected-doctype", String::from("Unexpected DOCTYPE")),
+
+            Token::Comment(_) => ("unexpected-comment", String::from("Unexpected comment")),
+
+            Token::Characters(_) => (
+                "unexpected-characters",
+                String::from("Unexpected non-whitespace text"),
+            ),
+
+            Token::ParseError(_) | Token::EOF => {
+                ("unexpected-eof", String::from("Unexpected end of file"))
+            }
+        };
+
+        self.error(
+            code,
+            format!("{} in {:?} insertion mode", message, self.mode),
+        );
+    }
+
+    fn unclosed_elements_error(&mut self, name: &str) {
+        self.error(
+            "unclosed-elements",
+            format!("</{}> closed elements that were still open", name),
+        );
+    }
+
+    fn misnested_tags_error(&mut self, name: &str) {
+        self.error(
+            "misnested-tags",
+            format!("</{}> doesn't match the elements it contains", name),
+        );
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#html-integration-point
+fn is_html_integration_point(element: &Element) -> bool {
+    match element.namespace {
+        Namespace::MathMl => {
+            element.name == "annotation-xml"
+                && element.attrs.iter().any(|attr| {
+                    attr.name == "encoding"
+                        && (attr.value.eq_ignore_ascii_case("text/html")
+                            || attr.value.eq_ignore_ascii_case("application/xhtml+xml"))
+                })
+        }
+        Namespace::Svg => matches!(element.name.as_str(), "foreignObject" | "desc" | "title"),
+        Namespace::Html => false,
+    }
+}
+
+fn implied_tag(name: &str) -> TagToken {
+    TagToken {
+        kind: TagKind::StartTag,
+        name: String::from(name),
+        self_closing: false,
+        attrs: vec![],
+    }
+}
+
+fn is_start_tag(tag: &TagToken, names: &[&str]) -> bool {
+    matches!(tag.kind, TagKind::StartTag) && names.contains(&tag.name.as_str())
+}
+
+fn is_end_tag(tag: &TagToken, names: &[&str]) -> bool {
+    matches!(tag.kind, TagKind::EndTag) && names.contains(&tag.name.as_str())
+}
+
+fn is_whitespace(text: &str) -> bool {
+    text.chars().all(is_whitespace_char)
+}
+
+fn is_whitespace_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\x0C' | '\r' | ' ')
+}
+
+/// Splits text into runs of whitespace, U+0000 and other characters, as most
+/// insertion modes treat them differently
+fn split_whitespace(text: &str) -> Vec<String> {
+    let mut runs: Vec<String> = vec![];
+    let mut last_kind: Option<(bool, bool)> = None;
+
+    for c in text.chars() {
+        let kind = (is_whitespace_char(c), c == '\0');
+
+        if last_kind != Some(kind) {
+            runs.push(String::new());
+            last_kind = Some(kind);
+        }
+
+        if let Some(run) = runs.last_mut() {
+            run.push(c);
+        }
+    }
+
+    runs
+}
+
+/// Same tag name and attributes, in any order
+fn is_same_element(a: &TagToken, b: &TagToken) -> bool {
+    a.name == b.name
+        && a.attrs.len() == b.attrs.len()
+        && a.attrs.iter().all(|attr| {
+            b.attrs
+                .iter()
+                .any(|other| other.name == attr.name && other.value == attr.value)
+        })
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode
+fn quirks_mode(doctype: &Doctype) -> QuirksMode {
+    const QUIRKS_PUBLIC_ID_PREFIXES: [&str; 55] = [
+        "+//silmaril//dtd html pro v0r11 19970101//",
+        "-//as//dtd html 3.0 aswedit + extensions//",
+        "-//advasoft ltd//dtd html 3.0 aswedit + extensions//",
+        "-//ietf//dtd html 2.0 level 1//",
+        "-//ietf//dtd html 2.0 level 2//",
+        "-//ietf//dtd html 2.0 strict level 1//",
+        "-//ietf//dtd html 2.0 strict level 2//",
+        "-//ietf//dtd html 2.0 strict//",
+        "-//ietf//dtd html 2.0//",
+        "-//ietf//dtd html 2.1e//",
+        "-//ietf//dtd html 3.0//",
+        "-//ietf//dtd html 3.2 final//",
+        "-//ietf//dtd html 3.2//",
+        "-//ietf//dtd html 3//",
+        "-//ietf//dtd html level 0//",
+        "-//ietf//dtd html level 1//",
+        "-//ietf//dtd html level 2//",
+        "-//ietf//dtd html level 3//",
+        "-//ietf//dtd html strict level 0//",
+        "-//ietf//dtd html strict level 1//",
+        "-//ietf//dtd html strict level 2//",
+        "-//ietf//dtd html strict level 3//",
+        "-//ietf//dtd html strict//",
+        "-//ietf//dtd html//",
+        "-//metrius//dtd metrius presentational//",
+        "-//microsoft//dtd internet explorer 2.0 html strict//",
+        "-//microsoft//dtd internet explorer 2.0 html//",
+        "-//microsoft//dtd internet explorer 2.0 tables//",
+        "-//microsoft//dtd internet explorer 3.0 html strict//",
+        "-//microsoft//dtd internet explorer 3.0 html//",
+        "-//microsoft//dtd internet explorer 3.0 tables//",
+        "-//netscape comm. corp.//dtd html//",
+        "-//netscape comm. corp.//dtd strict html//",
+        "-//o'reilly and associates//dtd html 2.0//",
+        "-//o'reilly and associates//dtd html extended 1.0//",
+        "-//o'reilly and associates//dtd html extended relaxed 1.0//",
+        "-//sq//dtd html 2.0 hotmetal + extensions//",
+        "-//softquad software//dtd hotmetal pro 6.0::19990601::extensions to html 4.0//",
+        "-//softquad//dtd hotmetal pro 4.0::19971010::extensions to html 4.0//",
+        "-//spyglass//dtd html 2.0 extended//",
+        "-//sun microsystems corp.//dtd hotjava html//",
+        "-//sun microsystems corp.//dtd hotjava strict html//",
+        "-//w3c//dtd html 3 1995-03-24//",
+        "-//w3c//dtd html 3.2 draft//",
+        "-//w3c//dtd html 3.2 final//",
+        "-//w3c//dtd html 3.2//",
+        "-//w3c//dtd html 3.2s draft//",
+        "-//w3c//dtd html 4.0 frameset//",
+        "-//w3c//dtd html 4.0 transitional//",
+        "-//w3c//dtd html experimental 19960712//",
+        "-//w3c//dtd html experimental 970421//",
+        "-//w3c//dtd w3 html//",
+        "-//w3o//dtd w3 html 3.0//",
+        "-//webtechs//dtd mozilla html 2.0//",
+        "-//webtechs//dtd mozilla html//",
+    ];
+    const HTML4_PUBLIC_ID_PREFIXES: [&str; 2] = [
+        "-//w3c//dtd html 4.01 frameset//",
+        "-//w3c//dtd html 4.01 transitional//",
+    ];
+    const XHTML_PUBLIC_ID_PREFIXES: [&str; 2] = [
+        "-//w3c//dtd xhtml 1.0 frameset//",
+        "-//w3c//dtd xhtml 1.0 transitional//",
+    ];
+
+    let public_id = doctype.public_id.as_deref().map(str::to_ascii_lowercase);
+    let system_id = doctype.system_id.as_deref().map(str::to_ascii_lowercase);
+    let public_starts_with = |prefixes: &[&str]| {
+        public_id
+            .as_deref()
+            .is_some_and(|id| prefixes.iter().any(|p| id.starts_with(p)))
+    };
+
+    if doctype.force_quirks
+        || doctype.name.as_deref() != Some("html")
+        || matches!(
+            public_id.as_deref(),
+            Some("-//w3o//dtd w3 html strict 3.0//en//")
+                | Some("-/w3c/dtd html 4.0 transitional/en")
+                | Some("html")
+        )
+        || system_id.as_deref()
+            == Some("http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd")
+        || public_starts_with(&QUIRKS_PUBLIC_ID_PREFIXES)
+        || (system_id.is_none() && public_starts_with(&HTML4_PUBLIC_ID_PREFIXES))
+    {
+        QuirksMode::Quirks
+    } else if public_starts_with(&XHTML_PUBLIC_ID_PREFIXES)
+        || (system_id.is_some() && public_starts_with(&HTML4_PUBLIC_ID_PREFIXES))
+    {
+        QuirksMode::LimitedQuirks
+    } else {
+        QuirksMode::NoQuirks
+    }
+}
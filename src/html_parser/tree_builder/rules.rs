@@ -0,0 +1,1660 @@
+//! The rules of each insertion mode, https://html.spec.whatwg.org/multipage/parsing.html#tree-construction
+
+use std::mem;
+
+use super::tag_sets::{
+    is_foreign_breakout, is_formatting, is_heading, is_mathml_text_integration_point,
+    may_be_left_open, Scope,
+};
+use super::{
+    implied_tag, is_end_tag, is_html_integration_point, is_start_tag, is_whitespace, quirks_mode,
+    InsertionMode, TreeBuilder,
+};
+use crate::html_parser::encoding;
+use crate::html_parser::interface::{
+    Namespace, QuirksMode, RawKind, State, TagKind, TagToken, Token,
+};
+
+/// Elements handled by the "in head" insertion mode wherever they appear
+const HEAD_ELEMENTS: [&str; 10] = [
+    "base", "basefont", "bgsound", "link", "meta", "noframes", "script", "style", "template",
+    "title",
+];
+
+impl TreeBuilder {
+    pub(super) fn step(&mut self, mode: InsertionMode, token: Token) {
+        match mode {
+            InsertionMode::Initial => self.initial(token),
+            InsertionMode::BeforeHtml => self.before_html(token),
+            InsertionMode::BeforeHead => self.before_head(token),
+            InsertionMode::InHead => self.in_head(token),
+            InsertionMode::InHeadNoscript => self.in_head_noscript(token),
+            InsertionMode::AfterHead => self.after_head(token),
+            InsertionMode::InBody => self.in_body(token),
+            InsertionMode::Text => self.text(token),
+            InsertionMode::InTable => self.in_table(token),
+            InsertionMode::InTableText => self.in_table_text(token),
+            InsertionMode::InCaption => self.in_caption(token),
+            InsertionMode::InColumnGroup => self.in_column_group(token),
+            InsertionMode::InTableBody => self.in_table_body(token),
+            InsertionMode::InRow => self.in_row(token),
+            InsertionMode::InCell => self.in_cell(token),
+            InsertionMode::InSelect => self.in_select(token),
+            InsertionMode::InSelectInTable => self.in_select_in_table(token),
+            InsertionMode::InTemplate => self.in_template(token),
+            InsertionMode::AfterBody => self.after_body(token),
+            InsertionMode::InFrameset => self.in_frameset(token),
+            InsertionMode::AfterFrameset => self.after_frameset(token),
+            InsertionMode::AfterAfterBody => self.after_after_body(token),
+            InsertionMode::AfterAfterFrameset => self.after_after_frameset(token),
+        }
+    }
+
+    fn initial(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_whitespace(text) => (),
+
+            Token::Comment(text) => self.append_comment(self.document.root(), text),
+
+            Token::Doctype(doctype) => {
+                let conforming = doctype.name.as_deref() == Some("html")
+                    && doctype.public_id.is_none()
+                    && doctype
+                        .system_id
+                        .as_deref()
+                        .is_none_or(|id| id == "about:legacy-compat");
+
+                if !conforming {
+                    self.error(
+                        "non-conforming-doctype",
+                        String::from("Expected <!DOCTYPE html>"),
+                    );
+                }
+
+                self.document.quirks_mode = quirks_mode(&doctype);
+                self.append_doctype(doctype);
+                self.mode = InsertionMode::BeforeHtml;
+            }
+
+            token => {
+                self.error(
+                    "missing-doctype",
+                    String::from("Expected <!DOCTYPE html> before the content"),
+                );
+                self.document.quirks_mode = QuirksMode::Quirks;
+                self.mode = InsertionMode::BeforeHtml;
+                self.process(token);
+            }
+        }
+    }
+
+    fn before_html(&mut self, token: Token) {
+        match token {
+            Token::Doctype(_) => self.unexpected(&token),
+
+            Token::Comment(text) => self.append_comment(self.document.root(), text),
+
+            Token::Characters(ref text) if is_whitespace(text) => (),
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["html"]) => {
+                let html = self.create_element(tag);
+                self.append_child(self.document.root(), html);
+                self.open_elements.push(html);
+                self.mode = InsertionMode::BeforeHead;
+            }
+
+            Token::Tag(ref tag)
+                if is_end(tag) && !is_end_tag(tag, &["head", "body", "html", "br"]) =>
+            {
+                self.unexpected(&token)
+            }
+
+            token => {
+                let html = self.create_element(&implied_tag("html"));
+                self.append_child(self.document.root(), html);
+                self.open_elements.push(html);
+                self.mode = InsertionMode::BeforeHead;
+                self.process(token);
+            }
+        }
+    }
+
+    fn before_head(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_whitespace(text) => (),
+
+            Token::Comment(text) => self.insert_comment(text),
+
+            Token::Doctype(_) => self.unexpected(&token),
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["html"]) => {
+                self.step(InsertionMode::InBody, token)
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["head"]) => {
+                self.head = Some(self.insert_element(tag));
+                self.mode = InsertionMode::InHead;
+            }
+
+            Token::Tag(ref tag)
+                if is_end(tag) && !is_end_tag(tag, &["head", "body", "html", "br"]) =>
+            {
+                self.unexpected(&token)
+            }
+
+            token => {
+                self.head = Some(self.insert_implied_element("head"));
+                self.mode = InsertionMode::InHead;
+                self.process(token);
+            }
+        }
+    }
+
+    fn in_head(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_whitespace(text) => self.insert_characters(text),
+
+            Token::Comment(text) => self.insert_comment(text),
+
+            Token::Doctype(_) => self.unexpected(&token),
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["html"]) => {
+                self.step(InsertionMode::InBody, token)
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["base", "basefont", "bgsound", "link"]) => {
+                self.insert_void_element(tag)
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["meta"]) => {
+                self.insert_void_element(tag);
+
+                if self.declared_encoding.is_none() {
+                    self.declared_encoding = encoding::from_meta(&tag.attrs);
+                }
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["title"]) => {
+                self.parse_raw_text(tag, RawKind::RcData)
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["noframes", "style"]) => {
+                self.parse_raw_text(tag, RawKind::RawText)
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["script"]) => {
+                self.parse_raw_text(tag, RawKind::ScriptData)
+            }
+
+            // Scripting is disabled, so <noscript> content is parsed as markup
+            Token::Tag(ref tag) if is_start_tag(tag, &["noscript"]) => {
+                self.insert_element(tag);
+                self.mode = InsertionMode::InHeadNoscript;
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["head"]) => {
+                self.open_elements.pop();
+                self.mode = InsertionMode::AfterHead;
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["template"]) => {
+                self.insert_element(tag);
+                self.push_marker();
+                self.frameset_ok = false;
+                self.mode = InsertionMode::InTemplate;
+                self.template_modes.push(InsertionMode::InTemplate);
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["template"]) => {
+                if !self.has_open("template") {
+                    return self.unexpected(&token);
+                }
+
+                self.generate_implied_end_tags_thoroughly();
+
+                if self.current_name() != "template" {
+                    self.unclosed_elements_error("template");
+                }
+
+                self.pop_until_named("template");
+                self.clear_formatting_to_last_marker();
+                self.template_modes.pop();
+                self.reset_insertion_mode();
+            }
+
+            Token::Tag(ref tag)
+                if is_start_tag(tag, &["head"])
+                    || (is_end(tag) && !is_end_tag(tag, &["body", "html", "br"])) =>
+            {
+                self.unexpected(&token)
+            }
+
+            token => {
+                self.open_elements.pop();
+                self.mode = InsertionMode::AfterHead;
+                self.process(token);
+            }
+        }
+    }
+
+    fn in_head_noscript(&mut self, token: Token) {
+        match token {
+            Token::Doctype(_) => self.unexpected(&token),
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["html"]) => {
+                self.step(InsertionMode::InBody, token)
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["noscript"]) => {
+                self.open_elements.pop();
+                self.mode = InsertionMode::InHead;
+            }
+
+            Token::Characters(ref text) if is_whitespace(text) => {
+                self.step(InsertionMode::InHead, token)
+            }
+
+            Token::Comment(_) => self.step(InsertionMode::InHead, token),
+
+            Token::Tag(ref tag)
+                if is_start_tag(
+                    tag,
+                    &["basefont", "bgsound", "link", "meta", "noframes", "style"],
+                ) =>
+            {
+                self.step(InsertionMode::InHead, token)
+            }
+
+            Token::Tag(ref tag)
+                if is_start_tag(tag, &["head", "noscript"])
+                    || (is_end(tag) && !is_end_tag(tag, &["br"])) =>
+            {
+                self.unexpected(&token)
+            }
+
+            token => {
+                self.unexpected(&token);
+                self.open_elements.pop();
+                self.mode = InsertionMode::InHead;
+                self.process(token);
+            }
+        }
+    }
+
+    fn after_head(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_whitespace(text) => self.insert_characters(text),
+
+            Token::Comment(text) => self.insert_comment(text),
+
+            Token::Doctype(_) => self.unexpected(&token),
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["html"]) => {
+                self.step(InsertionMode::InBody, token)
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["body"]) => {
+                self.insert_element(tag);
+                self.frameset_ok = false;
+                self.mode = InsertionMode::InBody;
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["frameset"]) => {
+                self.insert_element(tag);
+                self.mode = InsertionMode::InFrameset;
+            }
+
+            // Elements belonging to the head are put back into it
+            Token::Tag(ref tag) if is_start_tag(tag, &HEAD_ELEMENTS) => {
+                self.unexpected(&token);
+
+                if let Some(head) = self.head {
+                    self.open_elements.push(head);
+                    self.step(InsertionMode::InHead, token);
+                    self.open_elements.retain(|&h| h != head);
+                }
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["template"]) => {
+                self.step(InsertionMode::InHead, token)
+            }
+
+            Token::Tag(ref tag)
+                if is_start_tag(tag, &["head"])
+                    || (is_end(tag) && !is_end_tag(tag, &["body", "html", "br"])) =>
+            {
+                self.unexpected(&token)
+            }
+
+            token => {
+                self.insert_implied_element("body");
+                self.mode = InsertionMode::InBody;
+                self.process(token);
+            }
+        }
+    }
+
+    fn in_body(&mut self, token: Token) {
+        match token {
+            Token::Characters(text) => {
+                self.reconstruct_active_formatting();
+                self.insert_characters(&text);
+
+                if !is_whitespace(&text) {
+                    self.frameset_ok = false;
+                }
+            }
+
+            Token::Comment(text) => self.insert_comment(text),
+
+            Token::Doctype(_) => self.unexpected(&token),
+
+            Token::Tag(tag) => match tag.kind {
+                TagKind::StartTag => self.in_body_start_tag(tag),
+                TagKind::EndTag => self.in_body_end_tag(tag),
+            },
+
+            Token::EOF => {
+                if !self.template_modes.is_empty() {
+                    return self.step(InsertionMode::InTemplate, token);
+                }
+
+                self.check_open_elements();
+            }
+
+            Token::ParseError(error) => self.errors.push(error),
+        }
+    }
+
+    fn in_body_start_tag(&mut self, tag: TagToken) {
+        match tag.name.as_str() {
+            "html" => {
+                self.unexpected(&Token::Tag(tag.clone()));
+
+                if !self.has_open("template") {
+                    self.add_missing_attributes(self.open_elements[0], &tag);
+                }
+            }
+
+            name if HEAD_ELEMENTS.contains(&name) => {
+                self.step(InsertionMode::InHead, Token::Tag(tag))
+            }
+
+            "body" => {
+                self.unexpected(&Token::Tag(tag.clone()));
+
+                if self.open_elements.len() > 1
+                    && self.name(self.open_elements[1]) == "body"
+                    && !self.has_open("template")
+                {
+                    self.frameset_ok = false;
+                    self.add_missing_attributes(self.open_elements[1], &tag);
+                }
+            }
+
+            "frameset" => {
+                self.unexpected(&Token::Tag(tag.clone()));
+
+                if self.open_elements.len() > 1
+                    && self.name(self.open_elements[1]) == "body"
+                    && self.frameset_ok
+                {
+                    self.detach(self.open_elements[1]);
+                    self.open_elements.truncate(1);
+                    self.insert_element(&tag);
+                    self.mode = InsertionMode::InFrameset;
+                }
+            }
+
+            "address" | "article" | "aside" | "blockquote" | "center" | "details" | "dialog"
+            | "dir" | "div" | "dl" | "fieldset" | "figcaption" | "figure" | "footer" | "header"
+            | "hgroup" | "main" | "menu" | "nav" | "ol" | "p" | "search" | "section"
+            | "summary" | "ul" => {
+                self.close_p_in_button_scope();
+                self.insert_element(&tag);
+            }
+
+            name if is_heading(name) => {
+                self.close_p_in_button_scope();
+
+                if is_heading(self.current_name()) {
+                    self.unexpected(&Token::Tag(tag.clone()));
+                    self.open_elements.pop();
+                }
+
+                self.insert_element(&tag);
+            }
+
+            "pre" | "listing" => {
+                self.close_p_in_button_scope();
+                self.insert_element(&tag);
+                self.ignore_lf = true;
+                self.frameset_ok = false;
+            }
+
+            "form" => {
+                let in_template = self.has_open("template");
+
+                if self.form.is_some() && !in_template {
+                    return self.unexpected(&Token::Tag(tag));
+                }
+
+                self.close_p_in_button_scope();
+                let form = self.insert_element(&tag);
+
+                if !in_template {
+                    self.form = Some(form);
+                }
+            }
+
+            "li" => {
+                self.frameset_ok = false;
+                self.close_list_item(&["li"]);
+                self.close_p_in_button_scope();
+                self.insert_element(&tag);
+            }
+
+            "dd" | "dt" => {
+                self.frameset_ok = false;
+                self.close_list_item(&["dd", "dt"]);
+                self.close_p_in_button_scope();
+                self.insert_element(&tag);
+            }
+
+            "plaintext" => {
+                self.close_p_in_button_scope();
+                self.insert_element(&tag);
+                self.tokenizer_state = Some(State::PlainText);
+            }
+
+            "button" => {
+                if self.in_scope_named(Scope::Default, "button") {
+                    self.unexpected(&Token::Tag(tag.clone()));
+                    self.generate_implied_end_tags("");
+                    self.pop_until_named("button");
+                }
+
+                self.reconstruct_active_formatting();
+                self.insert_element(&tag);
+                self.frameset_ok = false;
+            }
+
+            "a" => {
+                if let Some((_, a, _)) = self.formatting_element("a") {
+                    self.unexpected(&Token::Tag(tag.clone()));
+                    self.adoption_agency(&tag);
+
+                    if let Some(position) = self.formatting_position(a) {
+                        self.active_formatting.remove(position);
+                    }
+
+                    self.open_elements.retain(|&h| h != a);
+                }
+
+                self.reconstruct_active_formatting();
+                let element = self.insert_element(&tag);
+                self.push_formatting_element(element, tag);
+            }
+
+            "nobr" => {
+                self.reconstruct_active_formatting();
+
+                if self.in_scope_named(Scope::Default, "nobr") {
+                    self.unexpected(&Token::Tag(tag.clone()));
+                    self.adoption_agency(&tag);
+                    self.reconstruct_active_formatting();
+                }
+
+                let element = self.insert_element(&tag);
+                self.push_formatting_element(element, tag);
+            }
+
+            name if is_formatting(name) => {
+                self.reconstruct_active_formatting();
+                let element = self.insert_element(&tag);
+                self.push_formatting_element(element, tag);
+            }
+
+            "applet" | "marquee" | "object" => {
+                self.reconstruct_active_formatting();
+                self.insert_element(&tag);
+                self.push_marker();
+                self.frameset_ok = false;
+            }
+
+            "table" => {
+                if self.document.quirks_mode != QuirksMode::Quirks {
+                    self.close_p_in_button_scope();
+                }
+
+                self.insert_element(&tag);
+                self.frameset_ok = false;
+                self.mode = InsertionMode::InTable;
+            }
+
+            "area" | "br" | "embed" | "img" | "keygen" | "wbr" => {
+                self.reconstruct_active_formatting();
+                self.insert_void_element(&tag);
+                self.frameset_ok = false;
+            }
+
+            "input" => {
+                self.reconstruct_active_formatting();
+                self.insert_void_element(&tag);
+
+                if !is_hidden_input(&tag) {
+                    self.frameset_ok = false;
+                }
+            }
+
+            "param" | "source" | "track" => self.insert_void_element(&tag),
+
+            "hr" => {
+                self.close_p_in_button_scope();
+                self.insert_void_element(&tag);
+                self.frameset_ok = false;
+            }
+
+            "image" => {
+                self.unexpected(&Token::Tag(tag.clone()));
+                self.process(Token::Tag(TagToken {
+                    name: String::from("img"),
+                    ..tag
+                }));
+            }
+
+            "textarea" => {
+                self.parse_raw_text(&tag, RawKind::RcData);
+                self.ignore_lf = true;
+                self.frameset_ok = false;
+            }
+
+            "xmp" => {
+                self.close_p_in_button_scope();
+                self.reconstruct_active_formatting();
+                self.frameset_ok = false;
+                self.parse_raw_text(&tag, RawKind::RawText);
+            }
+
+            "iframe" => {
+                self.frameset_ok = false;
+                self.parse_raw_text(&tag, RawKind::RawText);
+            }
+
+            "noembed" => self.parse_raw_text(&tag, RawKind::RawText),
+
+            "select" => {
+                self.reconstruct_active_formatting();
+                self.insert_element(&tag);
+                self.frameset_ok = false;
+
+                self.mode = match self.mode {
+                    InsertionMode::InTable
+                    | InsertionMode::InCaption
+                    | InsertionMode::InTableBody
+                    | InsertionMode::InRow
+                    | InsertionMode::InCell => InsertionMode::InSelectInTable,
+                    _ => InsertionMode::InSelect,
+                };
+            }
+
+            "optgroup" | "option" => {
+                if self.current_name() == "option" {
+                    self.open_elements.pop();
+                }
+
+                self.reconstruct_active_formatting();
+                self.insert_element(&tag);
+            }
+
+            "rb" | "rtc" => {
+                if self.in_scope_named(Scope::Default, "ruby") {
+                    self.generate_implied_end_tags("");
+
+                    if self.current_name() != "ruby" {
+                        self.unexpected(&Token::Tag(tag.clone()));
+                    }
+                }
+
+                self.insert_element(&tag);
+            }
+
+            "rp" | "rt" => {
+                if self.in_scope_named(Scope::Default, "ruby") {
+                    self.generate_implied_end_tags("rtc");
+
+                    if !matches!(self.current_name(), "rtc" | "ruby") {
+                        self.unexpected(&Token::Tag(tag.clone()));
+                    }
+                }
+
+                self.insert_element(&tag);
+            }
+
+            "math" => {
+                self.reconstruct_active_formatting();
+                self.insert_foreign_element(&tag, Namespace::MathMl);
+            }
+
+            "svg" => {
+                self.reconstruct_active_formatting();
+                self.insert_foreign_element(&tag, Namespace::Svg);
+            }
+
+            "caption" | "col" | "colgroup" | "frame" | "head" | "tbody" | "td" | "tfoot" | "th"
+            | "thead" | "tr" => self.unexpected(&Token::Tag(tag)),
+
+            _ => {
+                self.reconstruct_active_formatting();
+                self.insert_element(&tag);
+            }
+        }
+    }
+
+    fn in_body_end_tag(&mut self, tag: TagToken) {
+        match tag.name.as_str() {
+            "template" => self.step(InsertionMode::InHead, Token::Tag(tag)),
+
+            "body" | "html" => {
+                if !self.in_scope_named(Scope::Default, "body") {
+                    return self.unexpected(&Token::Tag(tag));
+                }
+
+                self.check_open_elements();
+                self.mode = InsertionMode::AfterBody;
+
+                if tag.name == "html" {
+                    self.process(Token::Tag(tag));
+                }
+            }
+
+            "address" | "article" | "aside" | "blockquote" | "button" | "center" | "details"
+            | "dialog" | "dir" | "div" | "dl" | "fieldset" | "figcaption" | "figure" | "footer"
+            | "header" | "hgroup" | "listing" | "main" | "menu" | "nav" | "ol" | "pre"
+            | "search" | "section" | "summary" | "ul" => {
+                if !self.in_scope_named(Scope::Default, &tag.name) {
+                    return self.unexpected(&Token::Tag(tag));
+                }
+
+                self.close_element(&tag.name);
+            }
+
+            "form" => {
+                if self.has_open("template") {
+                    if !self.in_scope_named(Scope::Default, "form") {
+                        return self.unexpected(&Token::Tag(tag));
+                    }
+
+                    return self.close_element("form");
+                }
+
+                let form = match self.form.take() {
+                    Some(form) if self.element_in_scope(Scope::Default, form) => form,
+                    _ => return self.unexpected(&Token::Tag(tag)),
+                };
+
+                self.generate_implied_end_tags("");
+
+                if self.current() != Some(form) {
+                    self.unclosed_elements_error("form");
+                }
+
+                self.open_elements.retain(|&h| h != form);
+            }
+
+            "p" => {
+                if !self.in_scope_named(Scope::Button, "p") {
+                    self.unexpected(&Token::Tag(tag));
+                    self.insert_implied_element("p");
+                }
+
+                self.close_p_element();
+            }
+
+            "li" | "dd" | "dt" => {
+                let scope = if tag.name == "li" {
+                    Scope::ListItem
+                } else {
+                    Scope::Default
+                };
+
+                if !self.in_scope_named(scope, &tag.name) {
+                    return self.unexpected(&Token::Tag(tag));
+                }
+
+                self.generate_implied_end_tags(&tag.name);
+
+                if self.current_name() != tag.name {
+                    self.unclosed_elements_error(&tag.name);
+                }
+
+                self.pop_until_named(&tag.name);
+            }
+
+            name if is_heading(name) => {
+                if !self.in_scope(Scope::Default, is_heading) {
+                    return self.unexpected(&Token::Tag(tag));
+                }
+
+                self.generate_implied_end_tags("");
+
+                if self.current_name() != tag.name {
+                    self.unclosed_elements_error(&tag.name);
+                }
+
+                self.pop_until(is_heading);
+            }
+
+            name if is_formatting(name) => self.adoption_agency(&tag),
+
+            "applet" | "marquee" | "object" => {
+                if !self.in_scope_named(Scope::Default, &tag.name) {
+                    return self.unexpected(&Token::Tag(tag));
+                }
+
+                self.close_element(&tag.name);
+                self.clear_formatting_to_last_marker();
+            }
+
+            "br" => {
+                self.unexpected(&Token::Tag(tag));
+                self.in_body_start_tag(implied_tag("br"));
+            }
+
+            _ => self.close_any_element(&tag),
+        }
+    }
+
+    /// Reports the elements which are left open when the body ends
+    fn check_open_elements(&mut self) {
+        if let Some(&h) = self
+            .open_elements
+            .iter()
+            .find(|&&h| !may_be_left_open(self.name(h)))
+        {
+            let name = String::from(self.name(h));
+            self.error("unclosed-elements", format!("<{}> was never closed", name));
+        }
+    }
+
+    fn text(&mut self, token: Token) {
+        match token {
+            Token::Characters(text) => self.insert_characters(&text),
+
+            Token::EOF => {
+                self.unexpected(&token);
+                self.open_elements.pop();
+                self.mode = self.original_mode;
+                self.process(token);
+            }
+
+            Token::Tag(ref tag) if is_end(tag) => {
+                self.open_elements.pop();
+                self.mode = self.original_mode;
+            }
+
+            token => self.unexpected(&token),
+        }
+    }
+
+    fn in_table(&mut self, token: Token) {
+        match token {
+            Token::Characters(_)
+                if matches!(
+                    self.current_name(),
+                    "table" | "tbody" | "template" | "tfoot" | "thead" | "tr"
+                ) =>
+            {
+                self.pending_table_text.clear();
+                self.original_mode = self.mode;
+                self.mode = InsertionMode::InTableText;
+                self.process(token);
+            }
+
+            Token::Comment(text) => self.insert_comment(text),
+
+            Token::Doctype(_) => self.unexpected(&token),
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["caption"]) => {
+                self.clear_stack_back_to(&["table", "template", "html"]);
+                self.push_marker();
+                self.insert_element(tag);
+                self.mode = InsertionMode::InCaption;
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["colgroup"]) => {
+                self.clear_stack_back_to(&["table", "template", "html"]);
+                self.insert_element(tag);
+                self.mode = InsertionMode::InColumnGroup;
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["col"]) => {
+                self.clear_stack_back_to(&["table", "template", "html"]);
+                self.insert_implied_element("colgroup");
+                self.mode = InsertionMode::InColumnGroup;
+                self.process(token);
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["tbody", "tfoot", "thead"]) => {
+                self.clear_stack_back_to(&["table", "template", "html"]);
+                self.insert_element(tag);
+                self.mode = InsertionMode::InTableBody;
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["td", "th", "tr"]) => {
+                self.clear_stack_back_to(&["table", "template", "html"]);
+                self.insert_implied_element("tbody");
+                self.mode = InsertionMode::InTableBody;
+                self.process(token);
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["table"]) => {
+                self.unexpected(&token);
+
+                if self.in_scope_named(Scope::Table, "table") {
+                    self.pop_until_named("table");
+                    self.reset_insertion_mode();
+                    self.process(token);
+                }
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["table"]) => {
+                if !self.in_scope_named(Scope::Table, "table") {
+                    return self.unexpected(&token);
+                }
+
+                self.pop_until_named("table");
+                self.reset_insertion_mode();
+            }
+
+            Token::Tag(ref tag)
+                if is_end_tag(
+                    tag,
+                    &[
+                        "body", "caption", "col", "colgroup", "html", "tbody", "td", "tfoot", "th",
+                        "thead", "tr",
+                    ],
+                ) =>
+            {
+                self.unexpected(&token)
+            }
+
+            Token::Tag(ref tag)
+                if is_start_tag(tag, &["style", "script", "template"])
+                    || is_end_tag(tag, &["template"]) =>
+            {
+                self.step(InsertionMode::InHead, token)
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["input"]) && is_hidden_input(tag) => {
+                self.unexpected(&token);
+                self.insert_void_element(tag);
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["form"]) => {
+                self.unexpected(&token);
+
+                if self.form.is_none() && !self.has_open("template") {
+                    self.form = Some(self.insert_element(tag));
+                    self.open_elements.pop();
+                }
+            }
+
+            Token::EOF => self.step(InsertionMode::InBody, token),
+
+            // Misplaced content ends up before the table
+            token => {
+                self.unexpected(&token);
+                self.foster_parenting = true;
+                self.step(InsertionMode::InBody, token);
+                self.foster_parenting = false;
+            }
+        }
+    }
+
+    fn in_table_text(&mut self, token: Token) {
+        match token {
+            Token::Characters(text) => self.pending_table_text.push(text),
+
+            token => {
+                let pending = mem::take(&mut self.pending_table_text);
+
+                if pending.iter().any(|text| !is_whitespace(text)) {
+                    self.error(
+                        "unexpected-characters",
+                        String::from("Unexpected non-whitespace text in a table"),
+                    );
+
+                    self.foster_parenting = true;
+                    for text in pending {
+                        self.step(InsertionMode::InBody, Token::Characters(text));
+                    }
+                    self.foster_parenting = false;
+                } else {
+                    for text in pending {
+                        self.insert_characters(&text);
+                    }
+                }
+
+                self.mode = self.original_mode;
+                self.process(token);
+            }
+        }
+    }
+
+    fn in_caption(&mut self, token: Token) {
+        match token {
+            Token::Tag(ref tag) if is_end_tag(tag, &["caption"]) => {
+                self.close_caption(&token);
+            }
+
+            Token::Tag(ref tag)
+                if is_start_tag(
+                    tag,
+                    &[
+                        "caption", "col", "colgroup", "tbody", "td", "tfoot", "th", "thead", "tr",
+                    ],
+                ) || is_end_tag(tag, &["table"]) =>
+            {
+                if self.close_caption(&token) {
+                    self.process(token);
+                }
+            }
+
+            Token::Tag(ref tag)
+                if is_end_tag(
+                    tag,
+                    &[
+                        "body", "col", "colgroup", "html", "tbody", "td", "tfoot", "th", "thead",
+                        "tr",
+                    ],
+                ) =>
+            {
+                self.unexpected(&token)
+            }
+
+            token => self.step(InsertionMode::InBody, token),
+        }
+    }
+
+    fn close_caption(&mut self, token: &Token) -> bool {
+        if !self.in_scope_named(Scope::Table, "caption") {
+            self.unexpected(token);
+            return false;
+        }
+
+        self.close_element("caption");
+        self.clear_formatting_to_last_marker();
+        self.mode = InsertionMode::InTable;
+        true
+    }
+
+    fn in_column_group(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_whitespace(text) => self.insert_characters(text),
+
+            Token::Comment(text) => self.insert_comment(text),
+
+            Token::Doctype(_) => self.unexpected(&token),
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["html"]) => {
+                self.step(InsertionMode::InBody, token)
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["col"]) => self.insert_void_element(tag),
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["colgroup"]) => {
+                if self.current_name() != "colgroup" {
+                    return self.unexpected(&token);
+                }
+
+                self.open_elements.pop();
+                self.mode = InsertionMode::InTable;
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["col"]) => self.unexpected(&token),
+
+            Token::Tag(ref tag) if tag.name == "template" => {
+                self.step(InsertionMode::InHead, token)
+            }
+
+            Token::EOF => self.step(InsertionMode::InBody, token),
+
+            token => {
+                if self.current_name() != "colgroup" {
+                    return self.unexpected(&token);
+                }
+
+                self.open_elements.pop();
+                self.mode = InsertionMode::InTable;
+                self.process(token);
+            }
+        }
+    }
+
+    fn in_table_body(&mut self, token: Token) {
+        let context = ["tbody", "tfoot", "thead", "template", "html"];
+
+        match token {
+            Token::Tag(ref tag) if is_start_tag(tag, &["tr"]) => {
+                self.clear_stack_back_to(&context);
+                self.insert_element(tag);
+                self.mode = InsertionMode::InRow;
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["th", "td"]) => {
+                self.unexpected(&token);
+                self.clear_stack_back_to(&context);
+                self.insert_implied_element("tr");
+                self.mode = InsertionMode::InRow;
+                self.process(token);
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["tbody", "tfoot", "thead"]) => {
+                if !self.in_scope_named(Scope::Table, &tag.name) {
+                    return self.unexpected(&token);
+                }
+
+                self.clear_stack_back_to(&context);
+                self.open_elements.pop();
+                self.mode = InsertionMode::InTable;
+            }
+
+            Token::Tag(ref tag)
+                if is_start_tag(
+                    tag,
+                    &["caption", "col", "colgroup", "tbody", "tfoot", "thead"],
+                ) || is_end_tag(tag, &["table"]) =>
+            {
+                if !self.in_scope(Scope::Table, |n| matches!(n, "tbody" | "thead" | "tfoot")) {
+                    return self.unexpected(&token);
+                }
+
+                self.clear_stack_back_to(&context);
+                self.open_elements.pop();
+                self.mode = InsertionMode::InTable;
+                self.process(token);
+            }
+
+            Token::Tag(ref tag)
+                if is_end_tag(
+                    tag,
+                    &[
+                        "body", "caption", "col", "colgroup", "html", "td", "th", "tr",
+                    ],
+                ) =>
+            {
+                self.unexpected(&token)
+            }
+
+            token => self.step(InsertionMode::InTable, token),
+        }
+    }
+
+    fn in_row(&mut self, token: Token) {
+        let context = ["tr", "template", "html"];
+
+        match token {
+            Token::Tag(ref tag) if is_start_tag(tag, &["th", "td"]) => {
+                self.clear_stack_back_to(&context);
+                self.insert_element(tag);
+                self.mode = InsertionMode::InCell;
+                self.push_marker();
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["tr"]) => {
+                if !self.in_scope_named(Scope::Table, "tr") {
+                    return self.unexpected(&token);
+                }
+
+                self.clear_stack_back_to(&context);
+                self.open_elements.pop();
+                self.mode = InsertionMode::InTableBody;
+            }
+
+            Token::Tag(ref tag)
+                if is_start_tag(
+                    tag,
+                    &[
+                        "caption", "col", "colgroup", "tbody", "tfoot", "thead", "tr",
+                    ],
+                ) || is_end_tag(tag, &["table"]) =>
+            {
+                if !self.in_scope_named(Scope::Table, "tr") {
+                    return self.unexpected(&token);
+                }
+
+                self.clear_stack_back_to(&context);
+                self.open_elements.pop();
+                self.mode = InsertionMode::InTableBody;
+                self.process(token);
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["tbody", "tfoot", "thead"]) => {
+                if !self.in_scope_named(Scope::Table, &tag.name) {
+                    return self.unexpected(&token);
+                }
+
+                if !self.in_scope_named(Scope::Table, "tr") {
+                    return;
+                }
+
+                self.clear_stack_back_to(&context);
+                self.open_elements.pop();
+                self.mode = InsertionMode::InTableBody;
+                self.process(token);
+            }
+
+            Token::Tag(ref tag)
+                if is_end_tag(
+                    tag,
+                    &["body", "caption", "col", "colgroup", "html", "td", "th"],
+                ) =>
+            {
+                self.unexpected(&token)
+            }
+
+            token => self.step(InsertionMode::InTable, token),
+        }
+    }
+
+    fn in_cell(&mut self, token: Token) {
+        match token {
+            Token::Tag(ref tag) if is_end_tag(tag, &["td", "th"]) => {
+                if !self.in_scope_named(Scope::Table, &tag.name) {
+                    return self.unexpected(&token);
+                }
+
+                self.close_element(&tag.name);
+                self.clear_formatting_to_last_marker();
+                self.mode = InsertionMode::InRow;
+            }
+
+            Token::Tag(ref tag)
+                if is_start_tag(
+                    tag,
+                    &[
+                        "caption", "col", "colgroup", "tbody", "td", "tfoot", "th", "thead", "tr",
+                    ],
+                ) =>
+            {
+                if !self.in_scope(Scope::Table, |n| n == "td" || n == "th") {
+                    return self.unexpected(&token);
+                }
+
+                self.close_cell();
+                self.process(token);
+            }
+
+            Token::Tag(ref tag)
+                if is_end_tag(tag, &["body", "caption", "col", "colgroup", "html"]) =>
+            {
+                self.unexpected(&token)
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["table", "tbody", "tfoot", "thead", "tr"]) => {
+                if !self.in_scope_named(Scope::Table, &tag.name) {
+                    return self.unexpected(&token);
+                }
+
+                self.close_cell();
+                self.process(token);
+            }
+
+            token => self.step(InsertionMode::InBody, token),
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#close-the-cell
+    fn close_cell(&mut self) {
+        self.generate_implied_end_tags("");
+
+        if !matches!(self.current_name(), "td" | "th") {
+            let name = String::from(self.current_name());
+            self.unclosed_elements_error(&name);
+        }
+
+        self.pop_until(|n| n == "td" || n == "th");
+        self.clear_formatting_to_last_marker();
+        self.mode = InsertionMode::InRow;
+    }
+
+    fn in_select(&mut self, token: Token) {
+        match token {
+            Token::Characters(text) => self.insert_characters(&text),
+
+            Token::Comment(text) => self.insert_comment(text),
+
+            Token::Doctype(_) => self.unexpected(&token),
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["html"]) => {
+                self.step(InsertionMode::InBody, token)
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["option"]) => {
+                if self.current_name() == "option" {
+                    self.open_elements.pop();
+                }
+
+                self.insert_element(tag);
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["optgroup", "hr"]) => {
+                if self.current_name() == "option" {
+                    self.open_elements.pop();
+                }
+
+                if self.current_name() == "optgroup" {
+                    self.open_elements.pop();
+                }
+
+                if tag.name == "hr" {
+                    self.insert_void_element(tag);
+                } else {
+                    self.insert_element(tag);
+                }
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["optgroup"]) => {
+                let length = self.open_elements.len();
+
+                if self.current_name() == "option"
+                    && length > 1
+                    && self.name(self.open_elements[length - 2]) == "optgroup"
+                {
+                    self.open_elements.pop();
+                }
+
+                if self.current_name() != "optgroup" {
+                    return self.unexpected(&token);
+                }
+
+                self.open_elements.pop();
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["option"]) => {
+                if self.current_name() != "option" {
+                    return self.unexpected(&token);
+                }
+
+                self.open_elements.pop();
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["select"]) => {
+                if !self.in_scope_named(Scope::Select, "select") {
+                    return self.unexpected(&token);
+                }
+
+                self.pop_until_named("select");
+                self.reset_insertion_mode();
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["select"]) => {
+                self.unexpected(&token);
+
+                if self.in_scope_named(Scope::Select, "select") {
+                    self.pop_until_named("select");
+                    self.reset_insertion_mode();
+                }
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["input", "keygen", "textarea"]) => {
+                self.unexpected(&token);
+
+                if self.in_scope_named(Scope::Select, "select") {
+                    self.pop_until_named("select");
+                    self.reset_insertion_mode();
+                    self.process(token);
+                }
+            }
+
+            Token::Tag(ref tag)
+                if is_start_tag(tag, &["script", "template"]) || is_end_tag(tag, &["template"]) =>
+            {
+                self.step(InsertionMode::InHead, token)
+            }
+
+            Token::EOF => self.step(InsertionMode::InBody, token),
+
+            token => self.unexpected(&token),
+        }
+    }
+
+    fn in_select_in_table(&mut self, token: Token) {
+        let table_elements = [
+            "caption", "table", "tbody", "tfoot", "thead", "tr", "td", "th",
+        ];
+
+        match token {
+            Token::Tag(ref tag) if is_start_tag(tag, &table_elements) => {
+                self.unexpected(&token);
+                self.pop_until_named("select");
+                self.reset_insertion_mode();
+                self.process(token);
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &table_elements) => {
+                self.unexpected(&token);
+
+                if self.in_scope_named(Scope::Table, &tag.name) {
+                    self.pop_until_named("select");
+                    self.reset_insertion_mode();
+                    self.process(token);
+                }
+            }
+
+            token => self.step(InsertionMode::InSelect, token),
+        }
+    }
+
+    fn in_template(&mut self, token: Token) {
+        match token {
+            Token::Characters(_) | Token::Comment(_) | Token::Doctype(_) => {
+                self.step(InsertionMode::InBody, token)
+            }
+
+            Token::Tag(ref tag)
+                if is_start_tag(tag, &HEAD_ELEMENTS) || is_end_tag(tag, &["template"]) =>
+            {
+                self.step(InsertionMode::InHead, token)
+            }
+
+            Token::Tag(ref tag)
+                if is_start_tag(tag, &["caption", "colgroup", "tbody", "tfoot", "thead"]) =>
+            {
+                self.switch_template_mode(InsertionMode::InTable, token)
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["col"]) => {
+                self.switch_template_mode(InsertionMode::InColumnGroup, token)
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["tr"]) => {
+                self.switch_template_mode(InsertionMode::InTableBody, token)
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["td", "th"]) => {
+                self.switch_template_mode(InsertionMode::InRow, token)
+            }
+
+            Token::Tag(ref tag) if is_end(tag) => self.unexpected(&token),
+
+            Token::Tag(_) => self.switch_template_mode(InsertionMode::InBody, token),
+
+            Token::EOF => {
+                if !self.has_open("template") {
+                    return;
+                }
+
+                self.unexpected(&token);
+                self.pop_until_named("template");
+                self.clear_formatting_to_last_marker();
+                self.template_modes.pop();
+                self.reset_insertion_mode();
+                self.process(token);
+            }
+
+            Token::ParseError(error) => self.errors.push(error),
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inforeign
+    pub(super) fn in_foreign_content(&mut self, token: Token) {
+        match token {
+            Token::Characters(text) => {
+                if !is_whitespace(&text) {
+                    self.frameset_ok = false;
+                }
+
+                self.insert_characters(&text);
+            }
+
+            Token::Comment(text) => self.insert_comment(text),
+
+            Token::Doctype(_) => self.unexpected(&token),
+
+            // HTML elements end the SVG or MathML content they appear in
+            Token::Tag(ref tag) if is_html_breakout(tag) => {
+                self.unexpected(&token);
+
+                while let Some(current) = self.current().and_then(|h| self.element(h)) {
+                    if current.namespace == Namespace::Html
+                        || is_html_integration_point(current)
+                        || (current.namespace == Namespace::MathMl
+                            && is_mathml_text_integration_point(&current.name))
+                    {
+                        break;
+                    }
+
+                    self.open_elements.pop();
+                }
+
+                // The insertion mode rules, the current node may still be foreign
+                self.step(self.mode, token);
+            }
+
+            Token::Tag(ref tag) if !is_end(tag) => {
+                let namespace = self
+                    .current()
+                    .and_then(|h| self.element(h))
+                    .map_or(Namespace::Html, |element| element.namespace);
+
+                self.insert_foreign_element(tag, namespace);
+            }
+
+            Token::Tag(tag) => self.foreign_end_tag(tag),
+
+            Token::EOF => self.step(self.mode, token),
+
+            Token::ParseError(error) => self.errors.push(error),
+        }
+    }
+
+    fn foreign_end_tag(&mut self, tag: TagToken) {
+        let matches_tag = |builder: &TreeBuilder, index: usize| {
+            builder
+                .element(builder.open_elements[index])
+                .is_some_and(|element| element.name.eq_ignore_ascii_case(&tag.name))
+        };
+
+        let mut index = self.open_elements.len() - 1;
+
+        if !matches_tag(self, index) {
+            self.unexpected(&Token::Tag(tag.clone()));
+        }
+
+        while index > 0 {
+            if matches_tag(self, index) {
+                self.open_elements.truncate(index);
+                return;
+            }
+
+            index -= 1;
+
+            let is_html = self
+                .element(self.open_elements[index])
+                .is_some_and(|element| element.namespace == Namespace::Html);
+
+            if is_html {
+                return self.step(self.mode, Token::Tag(tag));
+            }
+        }
+    }
+
+    fn switch_template_mode(&mut self, mode: InsertionMode, token: Token) {
+        self.template_modes.pop();
+        self.template_modes.push(mode);
+        self.mode = mode;
+        self.process(token);
+    }
+
+    fn after_body(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_whitespace(text) => {
+                self.step(InsertionMode::InBody, token)
+            }
+
+            Token::Comment(text) => self.append_comment(self.open_elements[0], text),
+
+            Token::Doctype(_) => self.unexpected(&token),
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["html"]) => {
+                self.step(InsertionMode::InBody, token)
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["html"]) => {
+                self.mode = InsertionMode::AfterAfterBody
+            }
+
+            Token::EOF => (),
+
+            token => {
+                self.unexpected(&token);
+                self.mode = InsertionMode::InBody;
+                self.process(token);
+            }
+        }
+    }
+
+    fn in_frameset(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_whitespace(text) => self.insert_characters(text),
+
+            Token::Comment(text) => self.insert_comment(text),
+
+            Token::Doctype(_) => self.unexpected(&token),
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["html"]) => {
+                self.step(InsertionMode::InBody, token)
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["frameset"]) => {
+                self.insert_element(tag);
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["frameset"]) => {
+                if self.current_name() == "html" {
+                    return self.unexpected(&token);
+                }
+
+                self.open_elements.pop();
+
+                if self.current_name() != "frameset" {
+                    self.mode = InsertionMode::AfterFrameset;
+                }
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["frame"]) => self.insert_void_element(tag),
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["noframes"]) => {
+                self.step(InsertionMode::InHead, token)
+            }
+
+            Token::EOF => {
+                if self.current_name() != "html" {
+                    self.unexpected(&token);
+                }
+            }
+
+            token => self.unexpected(&token),
+        }
+    }
+
+    fn after_frameset(&mut self, token: Token) {
+        match token {
+            Token::Characters(ref text) if is_whitespace(text) => self.insert_characters(text),
+
+            Token::Comment(text) => self.insert_comment(text),
+
+            Token::Doctype(_) => self.unexpected(&token),
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["html"]) => {
+                self.step(InsertionMode::InBody, token)
+            }
+
+            Token::Tag(ref tag) if is_end_tag(tag, &["html"]) => {
+                self.mode = InsertionMode::AfterAfterFrameset
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["noframes"]) => {
+                self.step(InsertionMode::InHead, token)
+            }
+
+            Token::EOF => (),
+
+            token => self.unexpected(&token),
+        }
+    }
+
+    fn after_after_body(&mut self, token: Token) {
+        match token {
+            Token::Comment(text) => self.append_comment(self.document.root(), text),
+
+            Token::Characters(ref text) if is_whitespace(text) => {
+                self.step(InsertionMode::InBody, token)
+            }
+
+            Token::Doctype(_) => self.step(InsertionMode::InBody, token),
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["html"]) => {
+                self.step(InsertionMode::InBody, token)
+            }
+
+            Token::EOF => (),
+
+            token => {
+                self.unexpected(&token);
+                self.mode = InsertionMode::InBody;
+                self.process(token);
+            }
+        }
+    }
+
+    fn after_after_frameset(&mut self, token: Token) {
+        match token {
+            Token::Comment(text) => self.append_comment(self.document.root(), text),
+
+            Token::Characters(ref text) if is_whitespace(text) => {
+                self.step(InsertionMode::InBody, token)
+            }
+
+            Token::Doctype(_) => self.step(InsertionMode::InBody, token),
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["html"]) => {
+                self.step(InsertionMode::InBody, token)
+            }
+
+            Token::Tag(ref tag) if is_start_tag(tag, &["noframes"]) => {
+                self.step(InsertionMode::InHead, token)
+            }
+
+            Token::EOF => (),
+
+            token => self.unexpected(&token),
+        }
+    }
+}
+
+fn is_end(tag: &TagToken) -> bool {
+    matches!(tag.kind, TagKind::EndTag)
+}
+
+fn is_hidden_input(tag: &TagToken) -> bool {
+    tag.attrs
+        .iter()
+        .any(|attr| attr.name == "type" && attr.value.eq_ignore_ascii_case("hidden"))
+}
+
+fn is_html_breakout(tag: &TagToken) -> bool {
+    match tag.kind {
+        TagKind::StartTag => {
+            is_foreign_breakout(&tag.name)
+                || (tag.name == "font"
+                    && tag
+                        .attrs
+                        .iter()
+                        .any(|attr| matches!(attr.name.as_str(), "color" | "face" | "size")))
+        }
+        TagKind::EndTag => tag.name == "br" || tag.name == "p",
+    }
+}
@@ -0,0 +1,389 @@
+//! Element name sets of https://html.spec.whatwg.org/multipage/parsing.html#tree-construction
+
+use crate::html_parser::interface::Namespace;
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#special
+pub fn is_special(namespace: Namespace, name: &str) -> bool {
+    if namespace != Namespace::Html {
+        return is_foreign_scope_boundary(namespace, name);
+    }
+
+    matches!(
+        name,
+        "address"
+            | "applet"
+            | "area"
+            | "article"
+            | "aside"
+            | "base"
+            | "basefont"
+            | "bgsound"
+            | "blockquote"
+            | "body"
+            | "br"
+            | "button"
+            | "caption"
+            | "center"
+            | "col"
+            | "colgroup"
+            | "dd"
+            | "details"
+            | "dir"
+            | "div"
+            | "dl"
+            | "dt"
+            | "embed"
+            | "fieldset"
+            | "figcaption"
+            | "figure"
+            | "footer"
+            | "form"
+            | "frame"
+            | "frameset"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "head"
+            | "header"
+            | "hgroup"
+            | "hr"
+            | "html"
+            | "iframe"
+            | "img"
+            | "input"
+            | "keygen"
+            | "li"
+            | "link"
+            | "listing"
+            | "main"
+            | "marquee"
+            | "menu"
+            | "meta"
+            | "nav"
+            | "noembed"
+            | "noframes"
+            | "noscript"
+            | "object"
+            | "ol"
+            | "p"
+            | "param"
+            | "plaintext"
+            | "pre"
+            | "script"
+            | "search"
+            | "section"
+            | "select"
+            | "source"
+            | "style"
+            | "summary"
+            | "table"
+            | "tbody"
+            | "td"
+            | "template"
+            | "textarea"
+            | "tfoot"
+            | "th"
+            | "thead"
+            | "title"
+            | "tr"
+            | "track"
+            | "ul"
+            | "wbr"
+            | "xmp"
+    )
+}
+
+pub fn is_formatting(name: &str) -> bool {
+    matches!(
+        name,
+        "a" | "b"
+            | "big"
+            | "code"
+            | "em"
+            | "font"
+            | "i"
+            | "nobr"
+            | "s"
+            | "small"
+            | "strike"
+            | "strong"
+            | "tt"
+            | "u"
+    )
+}
+
+pub fn is_heading(name: &str) -> bool {
+    matches!(name, "h1" | "h2" | "h3" | "h4" | "h5" | "h6")
+}
+
+/// Elements closed by https://html.spec.whatwg.org/multipage/parsing.html#generate-implied-end-tags
+pub fn has_implied_end_tag(name: &str) -> bool {
+    matches!(
+        name,
+        "dd" | "dt" | "li" | "optgroup" | "option" | "p" | "rb" | "rp" | "rt" | "rtc"
+    )
+}
+
+/// Elements closed when generating all implied end tags thoroughly
+pub fn has_implied_end_tag_thoroughly(name: &str) -> bool {
+    has_implied_end_tag(name)
+        || matches!(
+            name,
+            "caption" | "colgroup" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr"
+        )
+}
+
+/// Elements allowed to be left open at the end of the body
+pub fn may_be_left_open(name: &str) -> bool {
+    matches!(
+        name,
+        "dd" | "dt"
+            | "li"
+            | "optgroup"
+            | "option"
+            | "p"
+            | "rb"
+            | "rp"
+            | "rt"
+            | "rtc"
+            | "tbody"
+            | "td"
+            | "tfoot"
+            | "th"
+            | "thead"
+            | "tr"
+            | "body"
+            | "html"
+    )
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-scope
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Scope {
+    Default,
+    ListItem,
+    Button,
+    Table,
+    Select,
+}
+
+impl Scope {
+    /// Whether the element ends the search for an element in this scope
+    pub fn is_boundary(self, namespace: Namespace, name: &str) -> bool {
+        if namespace != Namespace::Html {
+            return match self {
+                Scope::Table => false,
+                Scope::Select => true,
+                _ => is_foreign_scope_boundary(namespace, name),
+            };
+        }
+
+        let default = || {
+            matches!(
+                name,
+                "applet"
+                    | "caption"
+                    | "html"
+                    | "table"
+                    | "td"
+                    | "th"
+                    | "marquee"
+                    | "object"
+                    | "template"
+            )
+        };
+
+        match self {
+            Scope::Default => default(),
+            Scope::ListItem => default() || name == "ol" || name == "ul",
+            Scope::Button => default() || name == "button",
+            Scope::Table => matches!(name, "html" | "table" | "template"),
+            Scope::Select => !matches!(name, "optgroup" | "option"),
+        }
+    }
+}
+
+/// The MathML and SVG elements which are part of the default scope
+fn is_foreign_scope_boundary(namespace: Namespace, name: &str) -> bool {
+    match namespace {
+        Namespace::MathMl => is_mathml_text_integration_point(name) || name == "annotation-xml",
+        Namespace::Svg => matches!(name, "foreignObject" | "desc" | "title"),
+        Namespace::Html => false,
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#mathml-text-integration-point
+pub fn is_mathml_text_integration_point(name: &str) -> bool {
+    matches!(name, "mi" | "mo" | "mn" | "ms" | "mtext")
+}
+
+/// HTML start tags which end foreign content, see
+/// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inforeign
+pub fn is_foreign_breakout(name: &str) -> bool {
+    matches!(
+        name,
+        "b" | "big"
+            | "blockquote"
+            | "body"
+            | "br"
+            | "center"
+            | "code"
+            | "dd"
+            | "div"
+            | "dl"
+            | "dt"
+            | "em"
+            | "embed"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "head"
+            | "hr"
+            | "i"
+            | "img"
+            | "li"
+            | "listing"
+            | "menu"
+            | "meta"
+            | "nobr"
+            | "ol"
+            | "p"
+            | "pre"
+            | "ruby"
+            | "s"
+            | "small"
+            | "span"
+            | "strong"
+            | "strike"
+            | "sub"
+            | "sup"
+            | "table"
+            | "tt"
+            | "u"
+            | "ul"
+            | "var"
+    )
+}
+
+/// Restores the case of SVG element names, which the tokenizer lowercases
+pub fn adjust_svg_tag_name(name: &str) -> Option<&'static str> {
+    const SVG_TAG_NAMES: [&str; 37] = [
+        "altGlyph",
+        "altGlyphDef",
+        "altGlyphItem",
+        "animateColor",
+        "animateMotion",
+        "animateTransform",
+        "clipPath",
+        "feBlend",
+        "feColorMatrix",
+        "feComponentTransfer",
+        "feComposite",
+        "feConvolveMatrix",
+        "feDiffuseLighting",
+        "feDisplacementMap",
+        "feDistantLight",
+        "feDropShadow",
+        "feFlood",
+        "feFuncA",
+        "feFuncB",
+        "feFuncG",
+        "feFuncR",
+        "feGaussianBlur",
+        "feImage",
+        "feMerge",
+        "feMergeNode",
+        "feMorphology",
+        "feOffset",
+        "fePointLight",
+        "feSpecularLighting",
+        "feSpotLight",
+        "feTile",
+        "feTurbulence",
+        "foreignObject",
+        "glyphRef",
+        "linearGradient",
+        "radialGradient",
+        "textPath",
+    ];
+
+    SVG_TAG_NAMES
+        .iter()
+        .find(|adjusted| adjusted.eq_ignore_ascii_case(name))
+        .copied()
+}
+
+/// Restores the case of SVG attribute names, which the tokenizer lowercases
+pub fn adjust_svg_attribute_name(name: &str) -> Option<&'static str> {
+    const SVG_ATTRIBUTE_NAMES: [&str; 58] = [
+        "attributeName",
+        "attributeType",
+        "baseFrequency",
+        "baseProfile",
+        "calcMode",
+        "clipPathUnits",
+        "diffuseConstant",
+        "edgeMode",
+        "filterUnits",
+        "glyphRef",
+        "gradientTransform",
+        "gradientUnits",
+        "kernelMatrix",
+        "kernelUnitLength",
+        "keyPoints",
+        "keySplines",
+        "keyTimes",
+        "lengthAdjust",
+        "limitingConeAngle",
+        "markerHeight",
+        "markerUnits",
+        "markerWidth",
+        "maskContentUnits",
+        "maskUnits",
+        "numOctaves",
+        "pathLength",
+        "patternContentUnits",
+        "patternTransform",
+        "patternUnits",
+        "pointsAtX",
+        "pointsAtY",
+        "pointsAtZ",
+        "preserveAlpha",
+        "preserveAspectRatio",
+        "primitiveUnits",
+        "refX",
+        "refY",
+        "repeatCount",
+        "repeatDur",
+        "requiredExtensions",
+        "requiredFeatures",
+        "specularConstant",
+        "specularExponent",
+        "spreadMethod",
+        "startOffset",
+        "stdDeviation",
+        "stitchTiles",
+        "surfaceScale",
+        "systemLanguage",
+        "tableValues",
+        "targetX",
+        "targetY",
+        "textLength",
+        "viewBox",
+        "viewTarget",
+        "xChannelSelector",
+        "yChannelSelector",
+        "zoomAndPan",
+    ];
+
+    SVG_ATTRIBUTE_NAMES
+        .iter()
+        .find(|adjusted| adjusted.eq_ignore_ascii_case(name))
+        .copied()
+}
@@ -0,0 +1,4 @@
+pub mod css_parser;
+pub mod html_parser;
+pub mod span;
+pub mod style;
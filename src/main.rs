@@ -1,24 +1,75 @@
-mod css_parser;
-mod html_parser;
+use web_renderer::html_parser;
+use web_renderer::html_parser::dom::Document;
+use web_renderer::html_parser::interface::NodeData;
+use web_renderer::style::author::AuthorStyles;
+use web_renderer::style::cascade::Origin;
+use web_renderer::style::computed::{self, ComputedStyle, StyledNode};
+use web_renderer::style::matching::DefaultStates;
+use web_renderer::style::media::Device;
+use web_renderer::style::user_agent::UserAgentStylesheet;
 
 fn main() {
     let html = "
-        <h1>Title</h1>
+        <style>
+            h1,
+            div#answer.note,
+            .note > p em {
+                margin: 0 auto;
+                font-family: \"SF Pro Display\", sans-serif;
+                color: #333 !important
+            }
+        </style>
+        <style media=\"print\">h1 { display: none }</style>
+        <h1 style=\"font-size: 3em\">Title</h1>
         <div id='answer' class=\"note\">
             <p>Hello <em>world</em>!</p>
         </div>
     ";
 
-    let nodes = html_parser::parse(html);
+    let (document, errors) = html_parser::parse(html);
 
-    dbg!(nodes);
+    dbg!(errors);
 
-    let css = "
-        h1,
-        div#answer.note,
-        note > p em";
+    let author = AuthorStyles::collect(&document, document.root(), &Device::default());
 
-    let stylesheet = css_parser::parse(css);
+    dbg!(&author.errors);
 
-    dbg!(stylesheet);
+    let mut stylesheets = UserAgentStylesheet::builtin().stylesheets(&document);
+    stylesheets.extend(
+        author
+            .stylesheets
+            .iter()
+            .map(|stylesheet| (Origin::Author, stylesheet)),
+    );
+
+    let styled = computed::style_tree(
+        &document,
+        &DefaultStates,
+        &stylesheets,
+        &author.inline_styles,
+    );
+
+    print_styled_node(&document, &styled, &ComputedStyle::initial(), 0);
+}
+
+/// The elements with the properties which don't have their initial value
+fn print_styled_node(
+    document: &Document,
+    node: &StyledNode,
+    initial: &ComputedStyle,
+    depth: usize,
+) {
+    if let NodeData::Element(element) = &document[node.node].data {
+        println!("{}<{}>", "  ".repeat(depth), element.name);
+
+        for (property, value) in node.style.iter() {
+            if initial.get(property) != Some(value) {
+                println!("{}  {}: {}", "  ".repeat(depth), property, value);
+            }
+        }
+    }
+
+    for child in &node.children {
+        print_styled_node(document, child, initial, depth + 1);
+    }
 }
@@ -0,0 +1,62 @@
+//! Locations in the source text, shared by the HTML and CSS parsers
+
+/// A point in the source, lines and columns start at 1 and columns count characters
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    /// Byte offset from the start of the source
+    pub offset: usize,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position {
+            line: 1,
+            column: 1,
+            offset: 0,
+        }
+    }
+}
+
+impl Position {
+    /// The position right after the character
+    pub fn advance(self, c: char) -> Position {
+        if c == '\n' {
+            Position {
+                line: self.line + 1,
+                column: 1,
+                offset: self.offset + 1,
+            }
+        } else {
+            Position {
+                line: self.line,
+                column: self.column + 1,
+                offset: self.offset + c.len_utf8(),
+            }
+        }
+    }
+}
+
+/// The source range of a token or node, the end is exclusive
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Span {
+        Span { start, end }
+    }
+
+    /// An empty span, e.g for the end of file
+    pub fn at(position: Position) -> Span {
+        Span::new(position, position)
+    }
+
+    /// The span from the start of this one to the end of the other
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start, other.end)
+    }
+}
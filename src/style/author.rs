@@ -0,0 +1,97 @@
+//! The CSS carried by the document itself, in <style> elements and `style` attributes
+
+use super::cascade::InlineStyles;
+use super::media::{self, Device};
+use crate::css_parser;
+use crate::css_parser::interface::{ParseError, Stylesheet};
+use crate::html_parser::dom::{Document, NodeId};
+use crate::html_parser::interface::{Element, Namespace, NodeData};
+
+/// The author origin of the cascade, without the external stylesheets
+#[derive(Debug, Default)]
+pub struct AuthorStyles {
+    /// In document order, only the ones whose media match the device
+    pub stylesheets: Vec<Stylesheet>,
+    pub inline_styles: InlineStyles,
+    pub errors: Vec<ParseError>,
+}
+
+impl AuthorStyles {
+    /// Parses the <style> elements and the `style` attributes of the elements below `root`
+    pub fn collect(document: &Document, root: NodeId, device: &Device) -> AuthorStyles {
+        let mut styles = AuthorStyles::default();
+
+        for id in document.descendants(root) {
+            let element = match document[id].as_element() {
+                Some(element) => element,
+                None => continue,
+            };
+
+            // The content of <template> isn't rendered
+            if is_in_template(document, id) {
+                continue;
+            }
+
+            if is_style_sheet(element, device) {
+                let (stylesheet, errors) = css_parser::parse(&text_content(document, id));
+
+                styles.stylesheets.push(stylesheet);
+                styles.errors.extend(errors);
+            }
+
+            // https://html.spec.whatwg.org/multipage/dom.html#the-style-attribute
+            if let Some(style) = element.get_attribute("style") {
+                let (declarations, errors) = css_parser::parse_declarations(style);
+
+                styles.inline_styles.insert(id, declarations);
+                styles.errors.extend(errors);
+            }
+        }
+
+        styles
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/semantics.html#update-a-style-block,
+/// a <style> whose type is CSS and whose media match
+fn is_style_sheet(element: &Element, device: &Device) -> bool {
+    if element.namespace != Namespace::Html || element.name != "style" {
+        return false;
+    }
+
+    let is_css = element
+        .get_attribute("type")
+        .is_none_or(|kind| kind.is_empty() || kind.eq_ignore_ascii_case("text/css"));
+
+    is_css
+        && element
+            .get_attribute("media")
+            .is_none_or(|media| media::matches(media, device))
+}
+
+fn is_in_template(document: &Document, id: NodeId) -> bool {
+    let mut ancestor = document[id].parent();
+
+    while let Some(id) = ancestor {
+        if let Some(element) = document[id].as_element() {
+            if element.namespace == Namespace::Html && element.name == "template" {
+                return true;
+            }
+        }
+
+        ancestor = document[id].parent();
+    }
+
+    false
+}
+
+/// https://dom.spec.whatwg.org/#concept-child-text-content
+fn text_content(document: &Document, id: NodeId) -> String {
+    document
+        .children(id)
+        .filter_map(|child| match &document[child].data {
+            NodeData::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
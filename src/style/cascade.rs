@@ -0,0 +1,128 @@
+//! https://drafts.csswg.org/css-cascade/#cascade-sort
+
+use std::collections::HashMap;
+
+use super::matching::{self, StateProvider};
+use crate::css_parser::interface::{Declaration, PseudoElement, Specificity, Stylesheet};
+use crate::html_parser::dom::{Document, NodeId};
+
+/// https://drafts.csswg.org/css-cascade/#cascading-origins, ordered from the
+/// lowest to the highest precedence of normal declarations
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Origin {
+    UserAgent,
+    User,
+    Author,
+}
+
+/// The declarations of the `style` attributes, by element
+pub type InlineStyles = HashMap<NodeId, Vec<Declaration>>;
+
+/// A declaration from a rule whose selectors match an element
+#[derive(Debug, Copy, Clone)]
+pub struct MatchedDeclaration<'a> {
+    pub declaration: &'a Declaration,
+    pub origin: Origin,
+    /// The highest specificity among the selectors of the rule matching the element
+    pub specificity: Specificity,
+    /// From the `style` attribute of the element, which wins over any selector
+    /// of the author origin
+    pub inline: bool,
+    /// Position among all the declarations given to the cascade
+    pub order: usize,
+}
+
+impl MatchedDeclaration<'_> {
+    /// Normal declarations go from the user agent to the author origin, important
+    /// ones the other way round and win over all of them
+    fn precedence(&self) -> (u8, bool, Specificity, usize) {
+        let rank = match (self.declaration.important, self.origin) {
+            (false, Origin::UserAgent) => 0,
+            (false, Origin::User) => 1,
+            (false, Origin::Author) => 2,
+            (true, Origin::Author) => 3,
+            (true, Origin::User) => 4,
+            (true, Origin::UserAgent) => 5,
+        };
+
+        (rank, self.inline, self.specificity, self.order)
+    }
+}
+
+/// Sorts from the lowest to the highest precedence, the last declaration of a property wins
+pub fn sort(declarations: &mut [MatchedDeclaration]) {
+    declarations.sort_by_key(MatchedDeclaration::precedence);
+}
+
+/// The declarations of every rule matching the element, or its pseudo-element
+/// when given, sorted by `sort`. The stylesheets are in source order, the
+/// inline styles belong to the author origin
+pub fn matched_declarations<'a>(
+    document: &Document,
+    states: &dyn StateProvider,
+    id: NodeId,
+    pseudo_element: Option<PseudoElement>,
+    stylesheets: &[(Origin, &'a Stylesheet)],
+    inline_styles: &'a InlineStyles,
+) -> Vec<MatchedDeclaration<'a>> {
+    let mut declarations = vec![];
+    let mut order = 0;
+
+    for &(origin, stylesheet) in stylesheets {
+        for rule in &stylesheet.rules {
+            let specificity = rule
+                .selectors
+                .iter()
+                .filter(|selector| match pseudo_element {
+                    Some(pseudo_element) => matching::matches_pseudo_element(
+                        document,
+                        states,
+                        id,
+                        pseudo_element,
+                        selector,
+                    ),
+                    None => matching::matches_with(document, states, id, selector),
+                })
+                .map(|selector| selector.specificity())
+                .max();
+
+            let specificity = match specificity {
+                Some(specificity) => specificity,
+                None => continue,
+            };
+
+            for declaration in &rule.declarations {
+                declarations.push(MatchedDeclaration {
+                    declaration,
+                    origin,
+                    specificity,
+                    inline: false,
+                    order,
+                });
+
+                order += 1;
+            }
+        }
+    }
+
+    let inline_style = match pseudo_element {
+        Some(_) => None,
+        None => inline_styles.get(&id),
+    };
+
+    for declaration in inline_style.into_iter().flatten() {
+        declarations.push(MatchedDeclaration {
+            declaration,
+            origin: Origin::Author,
+            specificity: Specificity::default(),
+            inline: true,
+            order,
+        });
+
+        order += 1;
+    }
+
+    sort(&mut declarations);
+
+    declarations
+}
@@ -0,0 +1,690 @@
+//! https://drafts.csswg.org/css-cascade/#value-stages, from the declarations
+//! matching an element to the computed value of every property
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use super::cascade::{self, InlineStyles, MatchedDeclaration, Origin};
+use super::generated::{self, GeneratedBox, Generator};
+use super::matching::StateProvider;
+use super::properties::{self, Kind, Property, PROPERTIES};
+use super::values::{Color, Value};
+use crate::css_parser::interface::{CSSValue, PseudoElement, Stylesheet};
+use crate::html_parser::dom::{Document, NodeId};
+use crate::html_parser::interface::NodeData;
+
+/// The computed value of every property of `properties::PROPERTIES`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComputedStyle {
+    values: BTreeMap<&'static str, Value>,
+}
+
+impl ComputedStyle {
+    /// Every property at its initial value, which is what the root element inherits
+    pub fn initial() -> ComputedStyle {
+        let mut style = ComputedStyle {
+            values: PROPERTIES
+                .iter()
+                .map(|property| (property.name, property.initial()))
+                .collect(),
+        };
+
+        fixup(&mut style, false);
+
+        style
+    }
+
+    /// Resolves the cascade of the declarations, sorted by `cascade::sort`, then
+    /// computes the values. `root_font_size` is what `rem` is relative to
+    pub fn compute(
+        declarations: &[MatchedDeclaration],
+        parent: &ComputedStyle,
+        root_font_size: f32,
+        is_root: bool,
+    ) -> ComputedStyle {
+        let candidates: Vec<Candidate> = declarations
+            .iter()
+            .flat_map(|matched| {
+                properties::expand(&matched.declaration.name, &matched.declaration.value)
+                    .into_iter()
+                    .map(move |(property, value)| Candidate {
+                        property,
+                        value,
+                        origin: matched.origin,
+                    })
+            })
+            .collect();
+
+        let mut context = Context {
+            parent,
+            root_font_size,
+            font_size: parent.font_size(),
+            color: parent.color(),
+        };
+
+        let mut style = ComputedStyle {
+            values: BTreeMap::new(),
+        };
+
+        // Lengths in em depend on the font-size, currentcolor on the color
+        let first = ["font-size", "color"];
+        let properties = first
+            .iter()
+            .filter_map(|&name| properties::lookup(name))
+            .chain(
+                PROPERTIES
+                    .iter()
+                    .filter(|property| !first.contains(&property.name)),
+            );
+
+        for property in properties {
+            let value = cascaded_value(&candidates, property, &context);
+
+            match (property.name, &value) {
+                ("font-size", Value::Length(font_size)) => context.font_size = *font_size,
+                ("color", Value::Color(color)) => context.color = *color,
+                _ => (),
+            }
+
+            style.values.insert(property.name, value);
+        }
+
+        fixup(&mut style, is_root);
+
+        style
+    }
+
+    pub fn get(&self, property: &str) -> Option<&Value> {
+        self.values.get(property)
+    }
+
+    /// The properties in alphabetical order
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &Value)> {
+        self.values
+            .iter()
+            .map(|(&property, value)| (property, value))
+    }
+
+    pub fn font_size(&self) -> f32 {
+        match self.get("font-size") {
+            Some(Value::Length(font_size)) => *font_size,
+            _ => 16.0,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self.get("color") {
+            Some(Value::Color(color)) => *color,
+            _ => Color::BLACK,
+        }
+    }
+}
+
+/// A node with the computed style of its element. Text nodes share the style
+/// of their parent
+#[derive(Debug)]
+pub struct StyledNode {
+    pub node: NodeId,
+    pub style: Rc<ComputedStyle>,
+    /// The ::marker, ::before and ::after boxes of the element
+    pub generated: Vec<GeneratedBox>,
+    pub children: Vec<StyledNode>,
+}
+
+/// Styles the elements and text of the document, the stylesheets are in source
+/// order. Comments and doctypes are left out
+pub fn style_tree(
+    document: &Document,
+    states: &dyn StateProvider,
+    stylesheets: &[(Origin, &Stylesheet)],
+    inline_styles: &InlineStyles,
+) -> StyledNode {
+    let mut styler = Styler {
+        document,
+        states,
+        stylesheets,
+        inline_styles,
+        generator: Generator::new(),
+    };
+
+    styler.visit(
+        document.root(),
+        Rc::new(ComputedStyle::initial()),
+        None,
+        0,
+        true,
+    )
+}
+
+struct Styler<'a> {
+    document: &'a Document,
+    states: &'a dyn StateProvider,
+    stylesheets: &'a [(Origin, &'a Stylesheet)],
+    inline_styles: &'a InlineStyles,
+    generator: Generator,
+}
+
+impl Styler<'_> {
+    /// `root_font_size` is None until the root element is styled. The content
+    /// of elements which aren't `rendered` isn't generated
+    fn visit(
+        &mut self,
+        id: NodeId,
+        style: Rc<ComputedStyle>,
+        root_font_size: Option<f32>,
+        depth: usize,
+        rendered: bool,
+    ) -> StyledNode {
+        let document = self.document;
+        let is_element = document[id].as_element().is_some();
+
+        // Elements which aren't rendered don't touch the counters either
+        let rendered = rendered
+            && !(is_element
+                && style
+                    .get("display")
+                    .is_some_and(|display| display.is_keyword("none")));
+
+        let generate = is_element && rendered;
+        let mut generated = vec![];
+
+        if generate {
+            self.generator.enter(document, id, &style, depth);
+
+            let mut pseudo_elements = vec![PseudoElement::Before];
+
+            if generated::is_list_item(&style) {
+                pseudo_elements.insert(0, PseudoElement::Marker);
+            }
+
+            for pseudo_element in pseudo_elements {
+                generated.extend(self.generate(id, pseudo_element, &style, root_font_size, depth));
+            }
+        }
+
+        let mut children = vec![];
+
+        for child in document.children(id) {
+            match &document[child].data {
+                NodeData::Element(_) => {
+                    let declarations = self.declarations(child, None);
+
+                    // The root element inherits the initial style of the document
+                    let child_style = ComputedStyle::compute(
+                        &declarations,
+                        &style,
+                        root_font_size.unwrap_or(style.font_size()),
+                        root_font_size.is_none(),
+                    );
+
+                    let root_font_size = root_font_size.unwrap_or(child_style.font_size());
+
+                    children.push(self.visit(
+                        child,
+                        Rc::new(child_style),
+                        Some(root_font_size),
+                        depth + 1,
+                        rendered,
+                    ));
+                }
+
+                NodeData::Text(_) => children.push(StyledNode {
+                    node: child,
+                    style: Rc::clone(&style),
+                    generated: vec![],
+                    children: vec![],
+                }),
+
+                _ => (),
+            }
+        }
+
+        if generate {
+            generated.extend(self.generate(
+                id,
+                PseudoElement::After,
+                &style,
+                root_font_size,
+                depth,
+            ));
+
+            self.generator.leave(depth);
+        }
+
+        StyledNode {
+            node: id,
+            style,
+            generated,
+            children,
+        }
+    }
+
+    fn declarations(
+        &self,
+        id: NodeId,
+        pseudo_element: Option<PseudoElement>,
+    ) -> Vec<MatchedDeclaration<'_>> {
+        cascade::matched_declarations(
+            self.document,
+            self.states,
+            id,
+            pseudo_element,
+            self.stylesheets,
+            self.inline_styles,
+        )
+    }
+
+    /// The pseudo-element inherits from the element
+    fn generate(
+        &mut self,
+        id: NodeId,
+        pseudo_element: PseudoElement,
+        style: &ComputedStyle,
+        root_font_size: Option<f32>,
+        depth: usize,
+    ) -> Option<GeneratedBox> {
+        let declarations = self.declarations(id, Some(pseudo_element));
+        let pseudo_style = ComputedStyle::compute(
+            &declarations,
+            style,
+            root_font_size.unwrap_or(style.font_size()),
+            false,
+        );
+
+        self.generator.generate(
+            self.document,
+            id,
+            pseudo_element,
+            Rc::new(pseudo_style),
+            depth,
+        )
+    }
+}
+
+/// A longhand declaration
+struct Candidate {
+    property: &'static str,
+    value: CSSValue,
+    origin: Origin,
+}
+
+/// What computed values depend on besides the specified value
+struct Context<'a> {
+    parent: &'a ComputedStyle,
+    root_font_size: f32,
+    /// The font-size of the parent until the one of the element is computed
+    font_size: f32,
+    /// Same for the color
+    color: Color,
+}
+
+/// https://drafts.csswg.org/css-cascade/#defaulting, the winning declaration
+/// of the property, computed. Declarations whose value can't be computed are
+/// skipped like invalid ones
+fn cascaded_value(candidates: &[Candidate], property: &Property, context: &Context) -> Value {
+    let inherit = || {
+        context
+            .parent
+            .get(property.name)
+            .cloned()
+            .unwrap_or_else(|| property.initial())
+    };
+
+    let unset = || {
+        if property.inherited {
+            inherit()
+        } else {
+            property.initial()
+        }
+    };
+
+    // The origins at or above it are ignored after a revert
+    let mut reverted: Option<Origin> = None;
+
+    for candidate in candidates.iter().rev() {
+        if candidate.property != property.name
+            || reverted.is_some_and(|origin| candidate.origin >= origin)
+        {
+            continue;
+        }
+
+        if let CSSValue::Keyword(keyword) = &candidate.value {
+            match keyword.to_ascii_lowercase().as_str() {
+                "inherit" => return inherit(),
+                "initial" => return property.initial(),
+                "unset" => return unset(),
+                "revert" | "revert-layer" => {
+                    reverted = Some(candidate.origin);
+                    continue;
+                }
+                _ => (),
+            }
+        }
+
+        if let Some(value) = compute(property, &candidate.value, context) {
+            return value;
+        }
+    }
+
+    unset()
+}
+
+/// https://drafts.csswg.org/css-cascade/#computed, None when the value isn't
+/// valid for the property
+fn compute(property: &Property, value: &CSSValue, context: &Context) -> Option<Value> {
+    match property.kind {
+        Kind::Color => Color::parse(value, context.color).map(Value::Color),
+        Kind::FontSize => compute_font_size(value, context),
+        Kind::FontWeight => compute_font_weight(value, context),
+        Kind::LineHeight => match value {
+            CSSValue::Percentage(number) => {
+                Some(Value::Length(context.font_size * parse(&number.0)? / 100.0))
+            }
+            CSSValue::Number(number) => Some(Value::Number(parse(&number.0)?)),
+            _ => compute_generic(value, Kind::Length, context),
+        },
+        Kind::BorderWidth => match value {
+            CSSValue::Keyword(keyword) => match keyword.to_ascii_lowercase().as_str() {
+                "thin" => Some(Value::Length(1.0)),
+                "medium" => Some(Value::Length(3.0)),
+                "thick" => Some(Value::Length(5.0)),
+                _ => None,
+            },
+            _ => compute_generic(value, Kind::Length, context),
+        },
+        Kind::Length => match value {
+            CSSValue::Keyword(keyword)
+                if !LENGTH_KEYWORDS.contains(&keyword.to_ascii_lowercase().as_str()) =>
+            {
+                None
+            }
+            CSSValue::StringLiteral(_) | CSSValue::Color(_) => None,
+            _ => compute_generic(value, Kind::Length, context),
+        },
+        Kind::Keyword(keywords) => keyword_in(value, keywords),
+        Kind::Keywords(keywords) => match value {
+            CSSValue::List(items) => Some(Value::List(
+                items
+                    .iter()
+                    .map(|item| keyword_in(item, keywords))
+                    .collect::<Option<_>>()?,
+            )),
+            _ => keyword_in(value, keywords),
+        },
+        Kind::Image => match value {
+            CSSValue::Function(name, _) if name.eq_ignore_ascii_case("url") => {
+                compute_generic(value, Kind::Image, context)
+            }
+            _ => keyword_in(value, &["none"]),
+        },
+        Kind::Number => match value {
+            CSSValue::Number(number) => Some(Value::Number(parse(&number.0)?)),
+            CSSValue::Percentage(number) => Some(Value::Number(parse(&number.0)? / 100.0)),
+            _ => None,
+        },
+        Kind::Integer => match value {
+            CSSValue::Number(number) => Some(Value::Number(number.0.parse::<i32>().ok()? as f32)),
+            _ => keyword_in(value, &["auto"]),
+        },
+        // https://drafts.csswg.org/css-content/#quotes-property
+        Kind::Quotes => match value {
+            CSSValue::List(items)
+                if items.len() % 2 == 0
+                    && items
+                        .iter()
+                        .all(|item| matches!(item, CSSValue::StringLiteral(_))) =>
+            {
+                compute_generic(value, Kind::Quotes, context)
+            }
+            _ => keyword_in(value, &["auto", "none"]),
+        },
+        Kind::CustomIdent => compute_generic(value, Kind::CustomIdent, context),
+    }
+}
+
+/// The keyword lowercased, None when it isn't one of `keywords`
+fn keyword_in(value: &CSSValue, keywords: &[&str]) -> Option<Value> {
+    match value {
+        CSSValue::Keyword(keyword) => {
+            let keyword = keyword.to_ascii_lowercase();
+
+            if keywords.contains(&keyword.as_str()) {
+                Some(Value::Keyword(keyword))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The keywords the properties taking a length accept instead
+const LENGTH_KEYWORDS: &[&str] = &[
+    "auto",
+    "none",
+    "normal",
+    "min-content",
+    "max-content",
+    "fit-content",
+    // vertical-align
+    "baseline",
+    "sub",
+    "super",
+    "text-top",
+    "text-bottom",
+    "middle",
+    "top",
+    "bottom",
+];
+
+/// https://drafts.csswg.org/css-fonts/#font-size-prop, relative sizes are
+/// relative to the parent
+fn compute_font_size(value: &CSSValue, context: &Context) -> Option<Value> {
+    let parent = context.parent.font_size();
+
+    let font_size = match value {
+        CSSValue::Keyword(keyword) => match keyword.to_ascii_lowercase().as_str() {
+            "xx-small" => 9.0,
+            "x-small" => 10.0,
+            "small" => 13.0,
+            "medium" => 16.0,
+            "large" => 18.0,
+            "x-large" => 24.0,
+            "xx-large" => 32.0,
+            "xxx-large" => 48.0,
+            "larger" => parent * 1.2,
+            "smaller" => parent / 1.2,
+            _ => return None,
+        },
+        CSSValue::Percentage(number) => parent * parse(&number.0)? / 100.0,
+        _ => match compute_generic(value, Kind::Length, context)? {
+            Value::Length(length) => length,
+            _ => return None,
+        },
+    };
+
+    if font_size < 0.0 {
+        return None;
+    }
+
+    Some(Value::Length(font_size))
+}
+
+/// https://drafts.csswg.org/css-fonts/#font-weight-prop, a number from 1 to 1000
+fn compute_font_weight(value: &CSSValue, context: &Context) -> Option<Value> {
+    let parent = match context.parent.get("font-weight") {
+        Some(Value::Number(weight)) => *weight,
+        _ => 400.0,
+    };
+
+    let weight = match value {
+        CSSValue::Number(number) => {
+            parse(&number.0).filter(|weight| (1.0..=1000.0).contains(weight))?
+        }
+        CSSValue::Keyword(keyword) => match keyword.to_ascii_lowercase().as_str() {
+            "normal" => 400.0,
+            "bold" => 700.0,
+            // https://drafts.csswg.org/css-fonts/#relative-weights
+            "bolder" if parent < 350.0 => 400.0,
+            "bolder" if parent < 550.0 => 700.0,
+            "bolder" => parent.max(900.0),
+            "lighter" if parent < 100.0 => parent,
+            "lighter" if parent < 550.0 => 100.0,
+            "lighter" if parent < 750.0 => 400.0,
+            "lighter" => 700.0,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some(Value::Number(weight))
+}
+
+/// Keywords are lowercased unless they are names, lengths are made absolute
+fn compute_generic(value: &CSSValue, kind: Kind, context: &Context) -> Option<Value> {
+    let value = match value {
+        CSSValue::Keyword(keyword) if kind == Kind::CustomIdent => Value::Keyword(keyword.clone()),
+        CSSValue::Keyword(keyword) => Value::Keyword(keyword.to_ascii_lowercase()),
+
+        CSSValue::Number(number) => {
+            let number = parse(&number.0)?;
+
+            if kind == Kind::Length && number == 0.0 {
+                Value::Length(0.0)
+            } else {
+                Value::Number(number)
+            }
+        }
+
+        CSSValue::NumberWithUnit(number, unit) => {
+            let number = parse(&number.0)?;
+
+            match absolute_length(number, unit, context) {
+                Some(length) => Value::Length(length),
+                None => Value::Dimension(number, unit.clone()),
+            }
+        }
+
+        CSSValue::Percentage(number) => Value::Percentage(parse(&number.0)?),
+        CSSValue::StringLiteral(string) => Value::String(string.clone()),
+        CSSValue::Color(_) => Value::Color(Color::parse(value, context.color)?),
+
+        CSSValue::Function(name, arguments) => {
+            if let Some(color) = Color::parse(value, context.color) {
+                return Some(Value::Color(color));
+            }
+
+            let arguments = arguments
+                .iter()
+                .map(|argument| compute_generic(argument, kind, context))
+                .collect::<Option<_>>()?;
+
+            Value::Function(name.to_ascii_lowercase(), arguments)
+        }
+
+        CSSValue::List(values) => Value::List(
+            values
+                .iter()
+                .map(|value| compute_generic(value, kind, context))
+                .collect::<Option<_>>()?,
+        ),
+
+        CSSValue::CommaSeparatedList(values) => Value::CommaSeparatedList(
+            values
+                .iter()
+                .map(|value| compute_generic(value, kind, context))
+                .collect::<Option<_>>()?,
+        ),
+    };
+
+    Some(value)
+}
+
+/// https://drafts.csswg.org/css-values/#lengths in CSS pixels. The viewport
+/// units are left as they are, there is no viewport yet
+fn absolute_length(number: f32, unit: &str, context: &Context) -> Option<f32> {
+    let pixels = match unit {
+        "px" => 1.0,
+        "em" => context.font_size,
+        "rem" => context.root_font_size,
+        // Fonts aren't available, so the fallback of half an em is used
+        "ex" | "ch" => context.font_size / 2.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        "q" => 96.0 / 101.6,
+        "pt" => 96.0 / 72.0,
+        "pc" => 16.0,
+        _ => return None,
+    };
+
+    Some(number * pixels)
+}
+
+fn parse(number: &str) -> Option<f32> {
+    number
+        .parse()
+        .ok()
+        .filter(|number: &f32| number.is_finite())
+}
+
+/// The computed values which depend on other properties
+fn fixup(style: &mut ComputedStyle, is_root: bool) {
+    let color = style.color();
+
+    for property in PROPERTIES {
+        if property.kind != Kind::Color {
+            continue;
+        }
+
+        if let Some(value) = style.values.get_mut(property.name) {
+            if value.is_keyword("currentcolor") {
+                *value = Value::Color(color);
+            }
+        }
+    }
+
+    // https://drafts.csswg.org/css-backgrounds/#border-width
+    for side in &["top", "right", "bottom", "left"] {
+        let border_style = style.get(&format!("border-{}-style", side));
+
+        if border_style.is_some_and(|value| value.is_keyword("none") || value.is_keyword("hidden"))
+        {
+            if let Some(property) = properties::lookup(&format!("border-{}-width", side)) {
+                style.values.insert(property.name, Value::Length(0.0));
+            }
+        }
+    }
+
+    // https://drafts.csswg.org/css2/#dis-pos-flo
+    let positioned = style
+        .get("position")
+        .is_some_and(|value| value.is_keyword("absolute") || value.is_keyword("fixed"));
+
+    if positioned {
+        style.values.insert("float", Value::keyword("none"));
+    }
+
+    let floated = style
+        .get("float")
+        .is_some_and(|value| !value.is_keyword("none"));
+
+    if is_root || positioned || floated {
+        if let Some(Value::Keyword(display)) = style.values.get_mut("display") {
+            *display = String::from(blockify(display));
+        }
+    }
+}
+
+/// https://drafts.csswg.org/css-display/#blockify
+fn blockify(display: &str) -> &str {
+    match display {
+        "inline-table" => "table",
+        "inline-flex" => "flex",
+        "inline-grid" => "grid",
+        "inline" | "inline-block" | "run-in" | "table-row-group" | "table-header-group"
+        | "table-footer-group" | "table-row" | "table-cell" | "table-column-group"
+        | "table-column" | "table-caption" => "block",
+        display => display,
+    }
+}
@@ -0,0 +1,539 @@
+//! https://drafts.csswg.org/css-content/ and https://drafts.csswg.org/css-lists/#auto-numbering
+
+use std::rc::Rc;
+
+use super::computed::ComputedStyle;
+use super::values::Value;
+use crate::css_parser::interface::PseudoElement;
+use crate::html_parser::dom::{Document, NodeId};
+use crate::html_parser::interface::{Element, Namespace};
+
+/// A box generated by a pseudo-element, with its `content` resolved to text
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedBox {
+    pub pseudo_element: PseudoElement,
+    /// Inherits from the style of the element
+    pub style: Rc<ComputedStyle>,
+    pub text: String,
+}
+
+/// https://drafts.csswg.org/css-lists/#counter
+struct Counter {
+    name: String,
+    value: i32,
+    /// Depth of the element which created the counter, its scope covers the
+    /// element, its following siblings and their descendants
+    depth: usize,
+    /// https://drafts.csswg.org/css-lists/#reversed, the list items count down
+    reversed: bool,
+}
+
+/// https://html.spec.whatwg.org/multipage/rendering.html#lists, the presentational
+/// hints of the `start` and `reversed` attributes of <ol> and the `value` one of <li>.
+/// The first replaces the value of the `list-item` reset the lists get from the user
+/// agent styles, so it is ignored when the author styles don't reset `list-item`. The
+/// second is left out when counter-set already has a `list-item`
+#[derive(Default)]
+struct ListHints {
+    /// The value of `counter-reset: list-item`, and whether the counter is reversed
+    reset: Option<(i32, bool)>,
+    /// The value of `counter-set: list-item`
+    set: Option<i32>,
+}
+
+impl ListHints {
+    fn new(document: &Document, id: NodeId) -> ListHints {
+        let mut hints = ListHints::default();
+
+        let element = match document[id].as_element() {
+            Some(element) if element.namespace == Namespace::Html => element,
+            _ => return hints,
+        };
+
+        match element.name.as_str() {
+            "ol" => {
+                let reversed = element.get_attribute("reversed").is_some();
+                let start = element.get_attribute("start").and_then(parse_integer);
+
+                // The first item gets the start, by default 1 or the number of
+                // items when reversed
+                let value = match (start, reversed) {
+                    (Some(start), false) => start.saturating_sub(1),
+                    (Some(start), true) => start.saturating_add(1),
+                    (None, false) => 0,
+                    (None, true) => {
+                        let items = document
+                            .children(id)
+                            .filter(|&child| document[child].as_element().is_some_and(is_li))
+                            .count();
+
+                        (items as i32).saturating_add(1)
+                    }
+                };
+
+                hints.reset = Some((value, reversed));
+            }
+            "li" => hints.set = element.get_attribute("value").and_then(parse_integer),
+            _ => (),
+        }
+
+        hints
+    }
+}
+
+/// Counters and quotes depend on everything before the element, so the
+/// elements are given in document order, each one with `enter`, then its
+/// pseudo-elements and descendants, then `leave`. The elements which aren't
+/// rendered are left out
+#[derive(Default)]
+pub struct Generator {
+    /// Every counter in scope, the last one of a name is the innermost
+    counters: Vec<Counter>,
+    quote_depth: usize,
+}
+
+impl Generator {
+    pub fn new() -> Generator {
+        Generator::default()
+    }
+
+    /// Updates the counters with the ones of the element
+    pub fn enter(&mut self, document: &Document, id: NodeId, style: &ComputedStyle, depth: usize) {
+        let hints = ListHints::new(document, id);
+
+        self.update_counters(style, depth, is_list_item(style), &hints);
+    }
+
+    /// The counters created by the descendants go out of scope with them
+    pub fn leave(&mut self, depth: usize) {
+        self.counters.retain(|counter| counter.depth <= depth);
+    }
+
+    /// https://drafts.csswg.org/css-content/#content-property, ::before and
+    /// ::after only exist when their `content` isn't `none` or `normal`, and
+    /// ::marker only for list items. `style` is the one of the pseudo-element
+    pub fn generate(
+        &mut self,
+        document: &Document,
+        id: NodeId,
+        pseudo_element: PseudoElement,
+        style: Rc<ComputedStyle>,
+        depth: usize,
+    ) -> Option<GeneratedBox> {
+        if style
+            .get("display")
+            .is_some_and(|display| display.is_keyword("none"))
+        {
+            return None;
+        }
+
+        let content = style.get("content")?;
+
+        if pseudo_element != PseudoElement::Marker
+            && (is_keyword(content, "none") || is_keyword(content, "normal"))
+        {
+            return None;
+        }
+
+        // The pseudo-element is a child of the element
+        self.update_counters(&style, depth + 1, false, &ListHints::default());
+
+        let text = if is_keyword(content, "normal") {
+            // https://drafts.csswg.org/css-lists/#content-property, the list-style-type
+            marker_text(
+                self.counter_value("list-item"),
+                style.get("list-style-type")?,
+            )?
+        } else {
+            self.content(document, id, content, &style)?
+        };
+
+        Some(GeneratedBox {
+            pseudo_element,
+            style,
+            text,
+        })
+    }
+
+    /// The text of `content`, None for `none`
+    fn content(
+        &mut self,
+        document: &Document,
+        id: NodeId,
+        content: &Value,
+        style: &ComputedStyle,
+    ) -> Option<String> {
+        let items = items(content);
+
+        if let [item] = items {
+            if is_keyword(item, "none") {
+                return None;
+            }
+        }
+
+        let quotes = style.get("quotes").map_or_else(default_quotes, quotes);
+        let mut text = String::new();
+
+        for item in items {
+            match item {
+                Value::String(string) => text.push_str(string),
+
+                // The alternative text for speech follows the '/'
+                Value::Keyword(keyword) if keyword == "/" => break,
+
+                Value::Keyword(keyword) => match keyword.to_ascii_lowercase().as_str() {
+                    "open-quote" => {
+                        if let Some((open, _)) = quote(&quotes, self.quote_depth) {
+                            text.push_str(open);
+                        }
+
+                        self.quote_depth += 1;
+                    }
+                    // Nothing closes a quote which wasn't opened
+                    "close-quote" if self.quote_depth > 0 => {
+                        self.quote_depth -= 1;
+
+                        if let Some((_, close)) = quote(&quotes, self.quote_depth) {
+                            text.push_str(close);
+                        }
+                    }
+                    "no-open-quote" => self.quote_depth += 1,
+                    "no-close-quote" => self.quote_depth = self.quote_depth.saturating_sub(1),
+                    _ => (),
+                },
+
+                Value::Function(name, arguments) => match name.as_str() {
+                    "attr" => {
+                        if let Some(Value::Keyword(name)) = arguments.first() {
+                            let value = document[id]
+                                .as_element()
+                                .and_then(|element| element.get_attribute(name));
+
+                            text.push_str(value.unwrap_or(""));
+                        }
+                    }
+                    "counter" => {
+                        if let Some(Value::Keyword(name)) = arguments.first() {
+                            let style = counter_style(arguments.get(1));
+                            text.push_str(&format_counter(self.counter_value(name), style));
+                        }
+                    }
+                    "counters" => {
+                        if let [Value::Keyword(name), Value::String(separator), ..] =
+                            arguments.as_slice()
+                        {
+                            let style = counter_style(arguments.get(2));
+                            let values: Vec<String> = self
+                                .counters
+                                .iter()
+                                .filter(|counter| &counter.name == name)
+                                .map(|counter| format_counter(counter.value, style))
+                                .collect();
+
+                            if values.is_empty() {
+                                text.push_str(&format_counter(0, style));
+                            } else {
+                                text.push_str(&values.join(separator));
+                            }
+                        }
+                    }
+                    _ => (),
+                },
+
+                _ => (),
+            }
+        }
+
+        Some(text)
+    }
+
+    /// The innermost counter of the name, 0 when there is none
+    fn counter_value(&self, name: &str) -> i32 {
+        self.counters
+            .iter()
+            .rev()
+            .find(|counter| counter.name == name)
+            .map_or(0, |counter| counter.value)
+    }
+
+    /// https://drafts.csswg.org/css-lists/#creating-a-counter, counter-reset
+    /// applies first, then counter-increment and counter-set
+    fn update_counters(
+        &mut self,
+        style: &ComputedStyle,
+        depth: usize,
+        is_list_item: bool,
+        hints: &ListHints,
+    ) {
+        let value = |property| style.get(property);
+
+        for (name, value) in parse_counters(value("counter-reset"), 0) {
+            match hints.reset {
+                Some((value, reversed)) if name == "list-item" => {
+                    self.create_counter(name, value, depth);
+                    self.counters.last_mut().unwrap().reversed = reversed;
+                }
+                _ => self.create_counter(name, value, depth),
+            }
+        }
+
+        let mut increments = parse_counters(value("counter-increment"), 1);
+
+        // https://drafts.csswg.org/css-lists/#list-item-counter, by -1 in a reversed list
+        if is_list_item && !increments.iter().any(|(name, _)| name == "list-item") {
+            let step = match self.counter(String::from("list-item"), depth).reversed {
+                true => -1,
+                false => 1,
+            };
+
+            increments.push((String::from("list-item"), step));
+        }
+
+        for (name, value) in increments {
+            let counter = self.counter(name, depth);
+            counter.value = counter.value.saturating_add(value);
+        }
+
+        let mut sets = parse_counters(value("counter-set"), 0);
+
+        if let Some(value) = hints.set {
+            if !sets.iter().any(|(name, _)| name == "list-item") {
+                sets.push((String::from("list-item"), value));
+            }
+        }
+
+        for (name, value) in sets {
+            self.counter(name, depth).value = value;
+        }
+    }
+
+    /// A counter created by a previous sibling is replaced
+    fn create_counter(&mut self, name: String, value: i32, depth: usize) {
+        self.counters
+            .retain(|counter| counter.name != name || counter.depth != depth);
+        self.counters.push(Counter {
+            name,
+            value,
+            depth,
+            reversed: false,
+        });
+    }
+
+    /// The innermost counter of the name, created on the element when there is none
+    fn counter(&mut self, name: String, depth: usize) -> &mut Counter {
+        let index = match self
+            .counters
+            .iter()
+            .rposition(|counter| counter.name == name)
+        {
+            Some(index) => index,
+            None => {
+                self.create_counter(name, 0, depth);
+                self.counters.len() - 1
+            }
+        };
+
+        &mut self.counters[index]
+    }
+}
+
+/// https://drafts.csswg.org/css-display/#list-items
+pub fn is_list_item(style: &ComputedStyle) -> bool {
+    style.get("display").is_some_and(|display| {
+        items(display)
+            .iter()
+            .any(|value| value.is_keyword("list-item"))
+    })
+}
+
+fn is_li(element: &Element) -> bool {
+    element.namespace == Namespace::Html && element.name == "li"
+}
+
+/// https://html.spec.whatwg.org/multipage/common-microsyntaxes.html#rules-for-parsing-integers,
+/// e.g " -12px" is -12
+fn parse_integer(input: &str) -> Option<i32> {
+    let input = input.trim_start_matches(|c: char| c.is_ascii_whitespace());
+
+    let (sign, digits) = match input.strip_prefix('-') {
+        Some(digits) => (-1, digits),
+        None => (1, input.strip_prefix('+').unwrap_or(input)),
+    };
+
+    let length = digits.bytes().take_while(u8::is_ascii_digit).count();
+
+    digits[..length]
+        .parse::<i32>()
+        .ok()
+        .map(|value| sign * value)
+}
+
+/// `content` and the counter properties keep the case of names, so their
+/// keywords are compared case-insensitively
+fn is_keyword(value: &Value, keyword: &str) -> bool {
+    matches!(value, Value::Keyword(value) if value.eq_ignore_ascii_case(keyword))
+}
+
+/// The items of a space separated value
+fn items(value: &Value) -> &[Value] {
+    match value {
+        Value::List(items) => items,
+        item => std::slice::from_ref(item),
+    }
+}
+
+/// e.g `chapter section 2`, names followed by an optional integer
+fn parse_counters(value: Option<&Value>, default: i32) -> Vec<(String, i32)> {
+    let items = match value {
+        Some(value) => items(value),
+        None => return vec![],
+    };
+
+    let mut counters: Vec<(String, i32)> = vec![];
+
+    for item in items {
+        match item {
+            Value::Keyword(name) if name.eq_ignore_ascii_case("none") => return vec![],
+            Value::Keyword(name) => counters.push((name.clone(), default)),
+            Value::Number(number) if number.fract() == 0.0 => {
+                if let Some(counter) = counters.last_mut() {
+                    counter.1 = *number as i32;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    counters
+}
+
+/// https://drafts.csswg.org/css-content/#quotes-property, the open and close
+/// quotes by nesting level
+fn quotes(value: &Value) -> Vec<(String, String)> {
+    match value {
+        Value::List(items) => items
+            .chunks(2)
+            .filter_map(|pair| match pair {
+                [Value::String(open), Value::String(close)] => Some((open.clone(), close.clone())),
+                _ => None,
+            })
+            .collect(),
+        value if value.is_keyword("none") => vec![],
+        _ => default_quotes(),
+    }
+}
+
+/// The quotes of the deepest level are repeated below it
+fn quote(quotes: &[(String, String)], depth: usize) -> Option<&(String, String)> {
+    quotes.get(depth).or_else(|| quotes.last())
+}
+
+/// The `auto` quotes of English text
+fn default_quotes() -> Vec<(String, String)> {
+    vec![
+        (String::from("\u{201C}"), String::from("\u{201D}")),
+        (String::from("\u{2018}"), String::from("\u{2019}")),
+    ]
+}
+
+/// https://drafts.csswg.org/css-lists/#content-property, the marker of a list
+/// item from its list-style-type, a counter style or a string
+fn marker_text(value: i32, list_style_type: &Value) -> Option<String> {
+    let list_style_type = match list_style_type {
+        Value::String(string) => return Some(string.clone()),
+        Value::Keyword(keyword) => keyword.to_ascii_lowercase(),
+        _ => return None,
+    };
+
+    match list_style_type.as_str() {
+        "none" => None,
+        "disc" | "circle" | "square" => {
+            Some(format!("{} ", format_counter(value, &list_style_type)))
+        }
+        _ => Some(format!("{}. ", format_counter(value, &list_style_type))),
+    }
+}
+
+/// The style argument of counter() and counters(), decimal by default
+fn counter_style(value: Option<&Value>) -> &str {
+    match value {
+        Some(Value::Keyword(style)) => style,
+        _ => "decimal",
+    }
+}
+
+/// https://drafts.csswg.org/css-counter-styles/#simple-numeric, the styles
+/// which are unknown or out of their range fall back to decimal
+fn format_counter(value: i32, style: &str) -> String {
+    let style = style.to_ascii_lowercase();
+
+    let formatted = match style.as_str() {
+        "none" => Some(String::new()),
+        "disc" => Some(String::from("\u{2022}")),
+        "circle" => Some(String::from("\u{25E6}")),
+        "square" => Some(String::from("\u{25AA}")),
+        "decimal-leading-zero" if (0..10).contains(&value) => Some(format!("0{}", value)),
+        "lower-roman" => roman(value).map(|roman| roman.to_ascii_lowercase()),
+        "upper-roman" => roman(value),
+        "lower-alpha" | "lower-latin" => alphabetic(value, "abcdefghijklmnopqrstuvwxyz"),
+        "upper-alpha" | "upper-latin" => alphabetic(value, "ABCDEFGHIJKLMNOPQRSTUVWXYZ"),
+        "lower-greek" => alphabetic(value, "αβγδεζηθικλμνξοπρστυφχψω"),
+        _ => None,
+    };
+
+    formatted.unwrap_or_else(|| value.to_string())
+}
+
+/// https://drafts.csswg.org/css-counter-styles/#additive-system, from 1 to 3999
+fn roman(mut value: i32) -> Option<String> {
+    const SYMBOLS: &[(i32, &str)] = &[
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+
+    if !(1..4000).contains(&value) {
+        return None;
+    }
+
+    let mut roman = String::new();
+
+    for &(weight, symbol) in SYMBOLS {
+        while value >= weight {
+            roman.push_str(symbol);
+            value -= weight;
+        }
+    }
+
+    Some(roman)
+}
+
+/// https://drafts.csswg.org/css-counter-styles/#alphabetic-system, e.g a, b, …, z, aa, ab
+/// from 1
+fn alphabetic(value: i32, letters: &str) -> Option<String> {
+    if value < 1 {
+        return None;
+    }
+
+    let letters: Vec<char> = letters.chars().collect();
+    let mut value = value as usize;
+    let mut symbols = vec![];
+
+    while value > 0 {
+        value -= 1;
+        symbols.push(letters[value % letters.len()]);
+        value /= letters.len();
+    }
+
+    Some(symbols.into_iter().rev().collect())
+}
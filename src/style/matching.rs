@@ -0,0 +1,548 @@
+//! https://drafts.csswg.org/selectors/#matching
+
+use std::iter;
+
+use crate::css_parser;
+use crate::css_parser::interface::{
+    AttributeOperator, AttributeSelector, CaseSensitivity, Combinator, CompoundSelector,
+    ElementState, NamespacePrefix, ParseError, PseudoClass, PseudoElement, RelativeSelector,
+    Selector,
+};
+use crate::html_parser::dom::{Document, NodeId};
+use crate::html_parser::interface::{Element, Namespace, NodeData, QuirksMode};
+
+/// Decides the pseudo-classes which depend on the user or on the state of form
+/// controls, e.g which element is under the pointer for :hover
+pub trait StateProvider {
+    fn has_state(&self, document: &Document, id: NodeId, state: ElementState) -> bool;
+}
+
+/// Nothing is hovered, focused or visited. Form controls are checked and
+/// disabled by their attributes
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DefaultStates;
+
+impl StateProvider for DefaultStates {
+    fn has_state(&self, document: &Document, id: NodeId, state: ElementState) -> bool {
+        match state {
+            ElementState::Checked => is_checked(document, id),
+            ElementState::Disabled => is_disabled(document, id),
+            _ => false,
+        }
+    }
+}
+
+/// Whether the element is the subject of the selector, its ancestors and
+/// previous siblings are looked at for the combinators
+pub fn matches(document: &Document, id: NodeId, selector: &Selector) -> bool {
+    matches_with(document, &DefaultStates, id, selector)
+}
+
+/// Like `matches`, with the user and form control states decided by `states`
+pub fn matches_with(
+    document: &Document,
+    states: &dyn StateProvider,
+    id: NodeId,
+    selector: &Selector,
+) -> bool {
+    Matcher { document, states }.matches(id, selector)
+}
+
+/// Whether the selector applies to the pseudo-element of the element, e.g
+/// `p::before` to the ::before of every <p>
+pub fn matches_pseudo_element(
+    document: &Document,
+    states: &dyn StateProvider,
+    id: NodeId,
+    pseudo_element: PseudoElement,
+    selector: &Selector,
+) -> bool {
+    selector.pseudo_element == Some(pseudo_element)
+        && Matcher { document, states }.matches_originating_element(id, selector)
+}
+
+/// Whether the element matches any selector of the list
+pub fn matches_any(document: &Document, id: NodeId, selectors: &[Selector]) -> bool {
+    selectors
+        .iter()
+        .any(|selector| matches(document, id, selector))
+}
+
+/// https://dom.spec.whatwg.org/#dom-parentnode-queryselector, the first element
+/// below `root` matching the selectors
+pub fn query_selector(
+    document: &Document,
+    root: NodeId,
+    selectors: &str,
+) -> Result<Option<NodeId>, ParseError> {
+    let selectors = css_parser::parse_selectors(selectors)?;
+
+    Ok(document
+        .descendants(root)
+        .find(|&id| matches_any(document, id, &selectors)))
+}
+
+/// https://dom.spec.whatwg.org/#dom-parentnode-queryselectorall, in document order
+pub fn query_selector_all(
+    document: &Document,
+    root: NodeId,
+    selectors: &str,
+) -> Result<Vec<NodeId>, ParseError> {
+    let selectors = css_parser::parse_selectors(selectors)?;
+
+    Ok(document
+        .descendants(root)
+        .filter(|&id| matches_any(document, id, &selectors))
+        .collect())
+}
+
+struct Matcher<'a> {
+    document: &'a Document,
+    states: &'a dyn StateProvider,
+}
+
+impl Matcher<'_> {
+    /// The selectors with a pseudo-element never match the element itself
+    fn matches(&self, id: NodeId, selector: &Selector) -> bool {
+        selector.pseudo_element.is_none() && self.matches_originating_element(id, selector)
+    }
+
+    fn matches_originating_element(&self, id: NodeId, selector: &Selector) -> bool {
+        self.matches_from(id, &selector.compounds, &selector.combinators, None)
+    }
+
+    /// Matches from right to left, the last compound against the element and the
+    /// others against its ancestors or previous siblings. The leftmost one must
+    /// relate to the `anchor` of a :has() argument
+    fn matches_from(
+        &self,
+        id: NodeId,
+        compounds: &[CompoundSelector],
+        combinators: &[Combinator],
+        anchor: Option<(NodeId, Combinator)>,
+    ) -> bool {
+        let document = self.document;
+
+        let (compound, rest) = match compounds.split_last() {
+            Some(split) => split,
+            None => return true,
+        };
+
+        let element = match document[id].as_element() {
+            Some(element) => element,
+            None => return false,
+        };
+
+        if !self.matches_compound(id, element, compound) {
+            return false;
+        }
+
+        let (combinator, combinators) = match combinators.split_last() {
+            Some((&combinator, combinators)) => (combinator, combinators),
+            None => {
+                return anchor.is_none_or(|(anchor, combinator)| {
+                    is_related(document, anchor, combinator, id)
+                })
+            }
+        };
+
+        let matches_rest = |other: NodeId| self.matches_from(other, rest, combinators, anchor);
+
+        match combinator {
+            Combinator::Child => document[id].parent().is_some_and(matches_rest),
+            Combinator::Descendant => document.ancestors(id).any(matches_rest),
+            Combinator::NextSibling => previous_elements(document, id)
+                .next()
+                .is_some_and(matches_rest),
+            Combinator::SubsequentSibling => previous_elements(document, id).any(matches_rest),
+        }
+    }
+
+    /// The simple selectors of a single compound, ignoring its combinators
+    fn matches_compound(&self, id: NodeId, element: &Element, selector: &CompoundSelector) -> bool {
+        // Ids and classes are case-insensitive in quirks mode
+        let quirks = self.document.quirks_mode == QuirksMode::Quirks;
+        let same = |a: &str, b: &str| {
+            if quirks {
+                a.eq_ignore_ascii_case(b)
+            } else {
+                a == b
+            }
+        };
+
+        if let Some(tag_name) = &selector.tag_name {
+            let matched = match element.namespace {
+                _ if tag_name == "*" => true,
+                Namespace::Html => tag_name.eq_ignore_ascii_case(&element.name),
+                _ => *tag_name == element.name,
+            };
+
+            if !matched {
+                return false;
+            }
+        }
+
+        if let Some(identifier) = &selector.identifier {
+            if !element
+                .get_attribute("id")
+                .is_some_and(|id| same(id, identifier))
+            {
+                return false;
+            }
+        }
+
+        if !selector.class_names.is_empty() {
+            let classes = element.get_attribute("class").unwrap_or("");
+
+            if !selector
+                .class_names
+                .iter()
+                .all(|class_name| split_whitespace(classes).any(|class| same(class, class_name)))
+            {
+                return false;
+            }
+        }
+
+        selector
+            .attributes
+            .iter()
+            .all(|attribute| matches_attribute(element, attribute))
+            && selector
+                .pseudo_classes
+                .iter()
+                .all(|pseudo_class| self.matches_pseudo_class(id, element, pseudo_class))
+    }
+
+    fn matches_pseudo_class(
+        &self,
+        id: NodeId,
+        element: &Element,
+        pseudo_class: &PseudoClass,
+    ) -> bool {
+        let document = self.document;
+        let same_type = |other: &NodeId| {
+            document[*other].as_element().is_some_and(|other| {
+                other.name == element.name && other.namespace == element.namespace
+            })
+        };
+
+        // 1-based indexes among the element siblings
+        let index = || previous_elements(document, id).count() as i32 + 1;
+        let last_index = || next_elements(document, id).count() as i32 + 1;
+        let type_index = || previous_elements(document, id).filter(same_type).count() as i32 + 1;
+        let last_type_index = || next_elements(document, id).filter(same_type).count() as i32 + 1;
+
+        match pseudo_class {
+            PseudoClass::Root => document[id]
+                .parent()
+                .is_some_and(|parent| matches!(document[parent].data, NodeData::Document)),
+            PseudoClass::Empty => document
+                .children(id)
+                .all(|child| match &document[child].data {
+                    NodeData::Text(text) => text.is_empty(),
+                    NodeData::Comment(_) => true,
+                    _ => false,
+                }),
+            PseudoClass::FirstChild => index() == 1,
+            PseudoClass::LastChild => last_index() == 1,
+            PseudoClass::OnlyChild => index() == 1 && last_index() == 1,
+            PseudoClass::FirstOfType => type_index() == 1,
+            PseudoClass::LastOfType => last_type_index() == 1,
+            PseudoClass::OnlyOfType => type_index() == 1 && last_type_index() == 1,
+            PseudoClass::NthChild(nth) => nth.matches(index()),
+            PseudoClass::NthLastChild(nth) => nth.matches(last_index()),
+            PseudoClass::NthOfType(nth) => nth.matches(type_index()),
+            PseudoClass::NthLastOfType(nth) => nth.matches(last_type_index()),
+            PseudoClass::Not(selectors) => {
+                !selectors.iter().any(|selector| self.matches(id, selector))
+            }
+            PseudoClass::Is(selectors) | PseudoClass::Where(selectors) => {
+                selectors.iter().any(|selector| self.matches(id, selector))
+            }
+            PseudoClass::Has(selectors) => selectors.iter().any(|relative| self.has(id, relative)),
+            PseudoClass::AnyLink => is_link(element),
+            PseudoClass::Link => {
+                is_link(element) && !self.states.has_state(document, id, ElementState::Visited)
+            }
+            PseudoClass::Enabled => {
+                is_form_control(element)
+                    && !self.states.has_state(document, id, ElementState::Disabled)
+            }
+            PseudoClass::State(state) => self.states.has_state(document, id, *state),
+        }
+    }
+
+    /// Whether an element relates to the anchor as the :has() argument describes
+    fn has(&self, anchor: NodeId, relative: &RelativeSelector) -> bool {
+        let scope = match relative.combinator {
+            Combinator::Descendant | Combinator::Child => Some(anchor),
+            // The later siblings, and their descendants for the combinators after them
+            Combinator::NextSibling | Combinator::SubsequentSibling => {
+                self.document[anchor].parent()
+            }
+        };
+
+        let selector = &relative.selector;
+
+        scope.is_some_and(|scope| {
+            self.document.descendants(scope).any(|candidate| {
+                self.matches_from(
+                    candidate,
+                    &selector.compounds,
+                    &selector.combinators,
+                    Some((anchor, relative.combinator)),
+                )
+            })
+        })
+    }
+}
+
+/// Whether `other` is on the left of the combinator when `id` is on its right
+fn is_related(document: &Document, other: NodeId, combinator: Combinator, id: NodeId) -> bool {
+    match combinator {
+        Combinator::Child => document[id].parent() == Some(other),
+        Combinator::Descendant => document.ancestors(id).any(|ancestor| ancestor == other),
+        Combinator::NextSibling => previous_elements(document, id).next() == Some(other),
+        Combinator::SubsequentSibling => {
+            previous_elements(document, id).any(|sibling| sibling == other)
+        }
+    }
+}
+
+/// The element siblings before the node, from the closest one
+fn previous_elements(document: &Document, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+    iter::successors(document[id].previous_sibling(), move |&sibling| {
+        document[sibling].previous_sibling()
+    })
+    .filter(move |&sibling| document[sibling].as_element().is_some())
+}
+
+/// The element siblings after the node, from the closest one
+fn next_elements(document: &Document, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+    iter::successors(document[id].next_sibling(), move |&sibling| {
+        document[sibling].next_sibling()
+    })
+    .filter(move |&sibling| document[sibling].as_element().is_some())
+}
+
+fn is_html(element: &Element, names: &[&str]) -> bool {
+    element.namespace == Namespace::Html && names.contains(&element.name.as_str())
+}
+
+/// https://html.spec.whatwg.org/multipage/semantics-other.html#selector-any-link
+fn is_link(element: &Element) -> bool {
+    is_html(element, &["a", "area"]) && element.get_attribute("href").is_some()
+}
+
+/// The elements :enabled and :disabled apply to
+fn is_form_control(element: &Element) -> bool {
+    is_html(
+        element,
+        &[
+            "button", "input", "select", "textarea", "optgroup", "option", "fieldset",
+        ],
+    )
+}
+
+/// https://html.spec.whatwg.org/multipage/semantics-other.html#selector-checked
+fn is_checked(document: &Document, id: NodeId) -> bool {
+    let element = match document[id].as_element() {
+        Some(element) => element,
+        None => return false,
+    };
+
+    let is_checkable = element.get_attribute("type").is_some_and(|kind| {
+        kind.eq_ignore_ascii_case("checkbox") || kind.eq_ignore_ascii_case("radio")
+    });
+
+    (is_html(element, &["input"]) && is_checkable && element.get_attribute("checked").is_some())
+        || (is_html(element, &["option"]) && element.get_attribute("selected").is_some())
+}
+
+/// https://html.spec.whatwg.org/multipage/semantics-other.html#selector-disabled
+fn is_disabled(document: &Document, id: NodeId) -> bool {
+    let element = match document[id].as_element() {
+        Some(element) => element,
+        None => return false,
+    };
+
+    if !is_form_control(element) {
+        return false;
+    }
+
+    if element.get_attribute("disabled").is_some() {
+        return true;
+    }
+
+    let is_disabled_parent = |names: &[&str]| {
+        document[id]
+            .parent()
+            .and_then(|parent| document[parent].as_element())
+            .is_some_and(|parent| {
+                is_html(parent, names) && parent.get_attribute("disabled").is_some()
+            })
+    };
+
+    if is_html(element, &["option"]) {
+        return is_disabled_parent(&["optgroup"]);
+    }
+
+    if !is_html(element, &["button", "input", "select", "textarea"]) {
+        return false;
+    }
+
+    // A disabled fieldset disables its descendants, except the ones in its first <legend>
+    let mut child = id;
+
+    for ancestor in document.ancestors(id) {
+        let is_disabled_fieldset = document[ancestor].as_element().is_some_and(|fieldset| {
+            is_html(fieldset, &["fieldset"]) && fieldset.get_attribute("disabled").is_some()
+        });
+
+        if is_disabled_fieldset {
+            let first_legend = document.children(ancestor).find(|&legend| {
+                document[legend]
+                    .as_element()
+                    .is_some_and(|legend| is_html(legend, &["legend"]))
+            });
+
+            if first_legend != Some(child) {
+                return true;
+            }
+        }
+
+        child = ancestor;
+    }
+
+    false
+}
+
+/// Prefixes are matched against the ones written in the attribute names, e.g
+/// [xlink|href] matches xlink:href, as @namespace rules aren't supported
+fn matches_attribute(element: &Element, selector: &AttributeSelector) -> bool {
+    let is_html = element.namespace == Namespace::Html;
+
+    let same_name = |a: &str, b: &str| {
+        if is_html {
+            a.eq_ignore_ascii_case(b)
+        } else {
+            a == b
+        }
+    };
+
+    let has_name = |name: &str| match &selector.namespace {
+        None | Some(NamespacePrefix::None) => same_name(name, &selector.name),
+        Some(NamespacePrefix::Any) => {
+            let local_name = name.rsplit(':').next().unwrap_or(name);
+            same_name(name, &selector.name) || same_name(local_name, &selector.name)
+        }
+        Some(NamespacePrefix::Named(prefix)) => name
+            .split_once(':')
+            .is_some_and(|(p, local_name)| p == prefix && same_name(local_name, &selector.name)),
+    };
+
+    let matcher = match &selector.matcher {
+        Some(matcher) => matcher,
+        None => return element.attrs.iter().any(|attr| has_name(&attr.name)),
+    };
+
+    let insensitive = match matcher.case_sensitivity {
+        Some(case_sensitivity) => case_sensitivity == CaseSensitivity::AsciiCaseInsensitive,
+        None => {
+            is_html
+                && CASE_INSENSITIVE_ATTRIBUTES
+                    .contains(&selector.name.to_ascii_lowercase().as_str())
+        }
+    };
+
+    let lower = |s: &str| {
+        if insensitive {
+            s.to_ascii_lowercase()
+        } else {
+            s.to_string()
+        }
+    };
+
+    let expected = lower(&matcher.value);
+
+    element
+        .attrs
+        .iter()
+        .filter(|attr| has_name(&attr.name))
+        .any(|attr| {
+            let value = lower(&attr.value);
+
+            match matcher.operator {
+                AttributeOperator::Equals => value == expected,
+                AttributeOperator::Includes => {
+                    split_whitespace(&value).any(|word| word == expected)
+                }
+                AttributeOperator::DashMatch => {
+                    value == expected
+                        || value
+                            .strip_prefix(expected.as_str())
+                            .is_some_and(|rest| rest.starts_with('-'))
+                }
+                // An empty value never matches these
+                AttributeOperator::Prefix => !expected.is_empty() && value.starts_with(&expected),
+                AttributeOperator::Suffix => !expected.is_empty() && value.ends_with(&expected),
+                AttributeOperator::Substring => !expected.is_empty() && value.contains(&expected),
+            }
+        })
+}
+
+fn split_whitespace(s: &str) -> impl Iterator<Item = &str> {
+    s.split(['\t', '\n', '\x0C', '\r', ' '])
+        .filter(|word| !word.is_empty())
+}
+
+/// https://html.spec.whatwg.org/multipage/semantics-other.html#case-sensitivity-of-selectors,
+/// the values of these attributes on HTML elements are compared case-insensitively
+const CASE_INSENSITIVE_ATTRIBUTES: &[&str] = &[
+    "accept",
+    "accept-charset",
+    "align",
+    "alink",
+    "axis",
+    "bgcolor",
+    "charset",
+    "checked",
+    "clear",
+    "codetype",
+    "color",
+    "compact",
+    "declare",
+    "defer",
+    "dir",
+    "direction",
+    "disabled",
+    "enctype",
+    "face",
+    "frame",
+    "hreflang",
+    "http-equiv",
+    "lang",
+    "language",
+    "link",
+    "media",
+    "method",
+    "multiple",
+    "nohref",
+    "noresize",
+    "noshade",
+    "nowrap",
+    "readonly",
+    "rel",
+    "rev",
+    "rules",
+    "scope",
+    "scrolling",
+    "selected",
+    "shape",
+    "target",
+    "text",
+    "type",
+    "valign",
+    "valuetype",
+    "vlink",
+];
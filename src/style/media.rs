@@ -0,0 +1,159 @@
+//! https://drafts.csswg.org/mediaqueries/, the subset needed for the `media`
+//! attribute of <style>
+
+/// The screen media queries are evaluated against, sizes are in CSS pixels
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Device {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for Device {
+    fn default() -> Device {
+        Device {
+            width: 800.0,
+            height: 600.0,
+        }
+    }
+}
+
+/// https://drafts.csswg.org/mediaqueries/#mq-list, true when any query of the
+/// comma separated list matches. An empty list matches everything
+pub fn matches(media: &str, device: &Device) -> bool {
+    let media = media.to_ascii_lowercase();
+
+    if media.trim().is_empty() {
+        return true;
+    }
+
+    // The invalid queries become `not all`
+    media
+        .split(',')
+        .any(|query| matches_query(query, device).unwrap_or(false))
+}
+
+/// e.g `screen and (min-width: 600px)`, None when the query is invalid
+fn matches_query(query: &str, device: &Device) -> Option<bool> {
+    let mut rest = query.trim();
+
+    let negated = strip_word(&mut rest, "not");
+
+    if !negated {
+        strip_word(&mut rest, "only");
+    }
+
+    let mut matches = true;
+
+    // A media type, followed by `and` when there are conditions
+    if !rest.starts_with('(') {
+        let end = rest
+            .find(|c: char| c.is_ascii_whitespace() || c == '(')
+            .unwrap_or(rest.len());
+
+        matches = match &rest[..end] {
+            "all" | "screen" => true,
+            "print" | "speech" => false,
+            // https://drafts.csswg.org/mediaqueries/#media-types, the deprecated ones match nothing
+            "tty" | "tv" | "projection" | "handheld" | "braille" | "embossed" | "aural" => false,
+            _ => return None,
+        };
+
+        rest = rest[end..].trim_start();
+
+        if rest.is_empty() {
+            return Some(matches != negated);
+        }
+
+        if !strip_word(&mut rest, "and") {
+            return None;
+        }
+    }
+
+    // Conditions joined by `and`
+    loop {
+        let condition = rest.strip_prefix('(')?;
+        let end = condition.find(')')?;
+
+        matches &= matches_feature(&condition[..end], device)?;
+        rest = condition[end + 1..].trim_start();
+
+        if rest.is_empty() {
+            break;
+        }
+
+        if !strip_word(&mut rest, "and") {
+            return None;
+        }
+    }
+
+    Some(matches != negated)
+}
+
+/// https://drafts.csswg.org/mediaqueries/#mq-features, e.g `min-width: 600px`.
+/// The features which aren't supported never match
+fn matches_feature(feature: &str, device: &Device) -> Option<bool> {
+    let (name, value) = match feature.split_once(':') {
+        Some((name, value)) => (name.trim(), Some(value.trim())),
+        None => (feature.trim(), None),
+    };
+
+    let value = match value {
+        // Boolean context, e.g `(color)`
+        None => {
+            return Some(matches!(
+                name,
+                "width" | "height" | "color" | "orientation" | "hover" | "pointer"
+            ))
+        }
+        Some(value) => value,
+    };
+
+    let matches = match name {
+        "width" => length(value)? == device.width,
+        "min-width" => device.width >= length(value)?,
+        "max-width" => device.width <= length(value)?,
+        "height" => length(value)? == device.height,
+        "min-height" => device.height >= length(value)?,
+        "max-height" => device.height <= length(value)?,
+        "orientation" => match value {
+            "portrait" => device.height >= device.width,
+            "landscape" => device.width > device.height,
+            _ => return None,
+        },
+        "prefers-color-scheme" => value == "light",
+        _ => false,
+    };
+
+    Some(matches)
+}
+
+/// Relative lengths are relative to the initial font-size
+fn length(value: &str) -> Option<f32> {
+    let end = value
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(value.len());
+
+    let number: f32 = value[..end].parse().ok()?;
+
+    let pixels = match &value[end..] {
+        "px" => 1.0,
+        "em" | "rem" => 16.0,
+        "" if number == 0.0 => 0.0,
+        _ => return None,
+    };
+
+    Some(number * pixels)
+}
+
+/// Consumes the keyword when it's the next word
+fn strip_word(rest: &mut &str, word: &str) -> bool {
+    match rest.strip_prefix(word) {
+        Some(after)
+            if after.is_empty() || after.starts_with([' ', '\t', '\n', '\r', '\x0C', '(']) =>
+        {
+            *rest = after.trim_start();
+            true
+        }
+        _ => false,
+    }
+}
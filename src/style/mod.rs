@@ -0,0 +1,12 @@
+//! Connects the parsed HTML and CSS: which rules apply to which elements and
+//! the styles they end up with
+
+pub mod author;
+pub mod cascade;
+pub mod computed;
+pub mod generated;
+pub mod matching;
+pub mod media;
+pub mod properties;
+pub mod user_agent;
+pub mod values;
@@ -0,0 +1,715 @@
+//! https://drafts.csswg.org/indexes/#properties, the properties the style system computes
+
+use super::values::{Color, Value};
+use crate::css_parser::interface::{CSSValue, Number};
+
+/// A longhand property, see https://drafts.csswg.org/css-cascade/#longhand
+pub struct Property {
+    pub name: &'static str,
+    /// https://drafts.csswg.org/css-cascade/#inherited-property
+    pub inherited: bool,
+    pub kind: Kind,
+    initial: fn() -> Value,
+}
+
+impl Property {
+    /// https://drafts.csswg.org/css-cascade/#initial-value
+    pub fn initial(&self) -> Value {
+        (self.initial)()
+    }
+}
+
+/// How a specified value is turned into a computed value
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Kind {
+    /// One of the keywords, which are ASCII case-insensitive so they are lowercased
+    Keyword(&'static [&'static str]),
+    /// Any number of the keywords, space separated
+    Keywords(&'static [&'static str]),
+    /// `none` or a url()
+    Image,
+    Number,
+    /// `auto` or an integer
+    Integer,
+    /// `auto`, `none` or pairs of strings
+    Quotes,
+    /// Names chosen by the author, e.g counter and font family names, keep their case
+    CustomIdent,
+    /// Relative lengths become pixels and a unitless 0 is a length
+    Length,
+    /// thin, medium and thick are lengths too
+    BorderWidth,
+    Color,
+    FontSize,
+    FontWeight,
+    LineHeight,
+}
+
+/// The properties in alphabetical order
+pub const PROPERTIES: &[Property] = &[
+    longhand("background-color", false, Kind::Color, || {
+        Value::Color(Color::TRANSPARENT)
+    }),
+    longhand("background-image", false, Kind::Image, || {
+        Value::keyword("none")
+    }),
+    longhand("border-bottom-color", false, Kind::Color, || {
+        Value::keyword("currentcolor")
+    }),
+    longhand(
+        "border-bottom-style",
+        false,
+        Kind::Keyword(BORDER_STYLES),
+        || Value::keyword("none"),
+    ),
+    longhand("border-bottom-width", false, Kind::BorderWidth, || {
+        Value::Length(3.0)
+    }),
+    longhand(
+        "border-collapse",
+        true,
+        Kind::Keyword(&["separate", "collapse"]),
+        || Value::keyword("separate"),
+    ),
+    longhand("border-left-color", false, Kind::Color, || {
+        Value::keyword("currentcolor")
+    }),
+    longhand(
+        "border-left-style",
+        false,
+        Kind::Keyword(BORDER_STYLES),
+        || Value::keyword("none"),
+    ),
+    longhand("border-left-width", false, Kind::BorderWidth, || {
+        Value::Length(3.0)
+    }),
+    longhand("border-right-color", false, Kind::Color, || {
+        Value::keyword("currentcolor")
+    }),
+    longhand(
+        "border-right-style",
+        false,
+        Kind::Keyword(BORDER_STYLES),
+        || Value::keyword("none"),
+    ),
+    longhand("border-right-width", false, Kind::BorderWidth, || {
+        Value::Length(3.0)
+    }),
+    longhand("border-spacing", true, Kind::Length, || Value::Length(0.0)),
+    longhand("border-top-color", false, Kind::Color, || {
+        Value::keyword("currentcolor")
+    }),
+    longhand(
+        "border-top-style",
+        false,
+        Kind::Keyword(BORDER_STYLES),
+        || Value::keyword("none"),
+    ),
+    longhand("border-top-width", false, Kind::BorderWidth, || {
+        Value::Length(3.0)
+    }),
+    longhand("bottom", false, Kind::Length, || Value::keyword("auto")),
+    longhand(
+        "box-sizing",
+        false,
+        Kind::Keyword(&["content-box", "border-box"]),
+        || Value::keyword("content-box"),
+    ),
+    longhand(
+        "caption-side",
+        true,
+        Kind::Keyword(&["top", "bottom"]),
+        || Value::keyword("top"),
+    ),
+    longhand(
+        "clear",
+        false,
+        Kind::Keyword(&[
+            "none",
+            "left",
+            "right",
+            "both",
+            "inline-start",
+            "inline-end",
+        ]),
+        || Value::keyword("none"),
+    ),
+    longhand("color", true, Kind::Color, || Value::Color(Color::BLACK)),
+    longhand("content", false, Kind::CustomIdent, || {
+        Value::keyword("normal")
+    }),
+    longhand("counter-increment", false, Kind::CustomIdent, || {
+        Value::keyword("none")
+    }),
+    longhand("counter-reset", false, Kind::CustomIdent, || {
+        Value::keyword("none")
+    }),
+    longhand("counter-set", false, Kind::CustomIdent, || {
+        Value::keyword("none")
+    }),
+    longhand("cursor", true, Kind::Keyword(CURSORS), || {
+        Value::keyword("auto")
+    }),
+    longhand("direction", true, Kind::Keyword(&["ltr", "rtl"]), || {
+        Value::keyword("ltr")
+    }),
+    longhand("display", false, Kind::Keyword(DISPLAYS), || {
+        Value::keyword("inline")
+    }),
+    longhand(
+        "empty-cells",
+        true,
+        Kind::Keyword(&["show", "hide"]),
+        || Value::keyword("show"),
+    ),
+    longhand(
+        "float",
+        false,
+        Kind::Keyword(&["none", "left", "right", "inline-start", "inline-end"]),
+        || Value::keyword("none"),
+    ),
+    longhand("font-family", true, Kind::CustomIdent, || {
+        Value::keyword("serif")
+    }),
+    longhand("font-size", true, Kind::FontSize, || Value::Length(16.0)),
+    longhand(
+        "font-style",
+        true,
+        Kind::Keyword(&["normal", "italic", "oblique"]),
+        || Value::keyword("normal"),
+    ),
+    longhand(
+        "font-variant",
+        true,
+        Kind::Keyword(&["normal", "small-caps"]),
+        || Value::keyword("normal"),
+    ),
+    longhand("font-weight", true, Kind::FontWeight, || {
+        Value::Number(400.0)
+    }),
+    longhand("height", false, Kind::Length, || Value::keyword("auto")),
+    longhand("left", false, Kind::Length, || Value::keyword("auto")),
+    longhand("letter-spacing", true, Kind::Length, || {
+        Value::keyword("normal")
+    }),
+    longhand("line-height", true, Kind::LineHeight, || {
+        Value::keyword("normal")
+    }),
+    longhand("list-style-image", true, Kind::Image, || {
+        Value::keyword("none")
+    }),
+    longhand(
+        "list-style-position",
+        true,
+        Kind::Keyword(&["inside", "outside"]),
+        || Value::keyword("outside"),
+    ),
+    longhand("list-style-type", true, Kind::CustomIdent, || {
+        Value::keyword("disc")
+    }),
+    longhand("margin-bottom", false, Kind::Length, || Value::Length(0.0)),
+    longhand("margin-left", false, Kind::Length, || Value::Length(0.0)),
+    longhand("margin-right", false, Kind::Length, || Value::Length(0.0)),
+    longhand("margin-top", false, Kind::Length, || Value::Length(0.0)),
+    longhand("max-height", false, Kind::Length, || Value::keyword("none")),
+    longhand("max-width", false, Kind::Length, || Value::keyword("none")),
+    longhand("min-height", false, Kind::Length, || Value::keyword("auto")),
+    longhand("min-width", false, Kind::Length, || Value::keyword("auto")),
+    longhand("opacity", false, Kind::Number, || Value::Number(1.0)),
+    longhand(
+        "overflow",
+        false,
+        Kind::Keyword(&["visible", "hidden", "clip", "scroll", "auto"]),
+        || Value::keyword("visible"),
+    ),
+    longhand("padding-bottom", false, Kind::Length, || Value::Length(0.0)),
+    longhand("padding-left", false, Kind::Length, || Value::Length(0.0)),
+    longhand("padding-right", false, Kind::Length, || Value::Length(0.0)),
+    longhand("padding-top", false, Kind::Length, || Value::Length(0.0)),
+    longhand(
+        "position",
+        false,
+        Kind::Keyword(&["static", "relative", "absolute", "fixed", "sticky"]),
+        || Value::keyword("static"),
+    ),
+    longhand("quotes", true, Kind::Quotes, || Value::keyword("auto")),
+    longhand("right", false, Kind::Length, || Value::keyword("auto")),
+    longhand(
+        "table-layout",
+        false,
+        Kind::Keyword(&["auto", "fixed"]),
+        || Value::keyword("auto"),
+    ),
+    longhand(
+        "text-align",
+        true,
+        Kind::Keyword(&[
+            "start",
+            "end",
+            "left",
+            "right",
+            "center",
+            "justify",
+            "match-parent",
+        ]),
+        || Value::keyword("start"),
+    ),
+    longhand("text-decoration-color", false, Kind::Color, || {
+        Value::keyword("currentcolor")
+    }),
+    longhand(
+        "text-decoration-line",
+        false,
+        Kind::Keywords(TEXT_DECORATION_LINES),
+        || Value::keyword("none"),
+    ),
+    longhand(
+        "text-decoration-style",
+        false,
+        Kind::Keyword(TEXT_DECORATION_STYLES),
+        || Value::keyword("solid"),
+    ),
+    longhand("text-indent", true, Kind::Length, || Value::Length(0.0)),
+    longhand(
+        "text-transform",
+        true,
+        Kind::Keyword(&["none", "capitalize", "uppercase", "lowercase", "full-width"]),
+        || Value::keyword("none"),
+    ),
+    longhand("top", false, Kind::Length, || Value::keyword("auto")),
+    longhand("vertical-align", false, Kind::Length, || {
+        Value::keyword("baseline")
+    }),
+    longhand(
+        "visibility",
+        true,
+        Kind::Keyword(&["visible", "hidden", "collapse"]),
+        || Value::keyword("visible"),
+    ),
+    longhand(
+        "white-space",
+        true,
+        Kind::Keyword(&[
+            "normal",
+            "pre",
+            "nowrap",
+            "pre-wrap",
+            "break-spaces",
+            "pre-line",
+        ]),
+        || Value::keyword("normal"),
+    ),
+    longhand("width", false, Kind::Length, || Value::keyword("auto")),
+    longhand("word-spacing", true, Kind::Length, || {
+        Value::keyword("normal")
+    }),
+    longhand("z-index", false, Kind::Integer, || Value::keyword("auto")),
+];
+
+const fn longhand(
+    name: &'static str,
+    inherited: bool,
+    kind: Kind,
+    initial: fn() -> Value,
+) -> Property {
+    Property {
+        name,
+        inherited,
+        kind,
+        initial,
+    }
+}
+
+pub fn lookup(name: &str) -> Option<&'static Property> {
+    PROPERTIES
+        .binary_search_by_key(&name, |property| property.name)
+        .ok()
+        .map(|i| &PROPERTIES[i])
+}
+
+/// https://drafts.csswg.org/css-cascade/#css-wide-keywords
+pub fn is_css_wide_keyword(value: &CSSValue) -> bool {
+    match value {
+        CSSValue::Keyword(keyword) => matches!(
+            keyword.to_ascii_lowercase().as_str(),
+            "initial" | "inherit" | "unset" | "revert" | "revert-layer"
+        ),
+        _ => false,
+    }
+}
+
+/// https://drafts.csswg.org/css-cascade/#shorthand-property, the longhands a
+/// declaration sets. Unknown properties and invalid shorthand values set nothing
+pub fn expand(name: &str, value: &CSSValue) -> Vec<(&'static str, CSSValue)> {
+    let longhands: &[&'static str] = match name {
+        "margin" => &["margin-top", "margin-right", "margin-bottom", "margin-left"],
+        "padding" => &[
+            "padding-top",
+            "padding-right",
+            "padding-bottom",
+            "padding-left",
+        ],
+        "border-width" => &[
+            "border-top-width",
+            "border-right-width",
+            "border-bottom-width",
+            "border-left-width",
+        ],
+        "border-style" => &[
+            "border-top-style",
+            "border-right-style",
+            "border-bottom-style",
+            "border-left-style",
+        ],
+        "border-color" => &[
+            "border-top-color",
+            "border-right-color",
+            "border-bottom-color",
+            "border-left-color",
+        ],
+        "border" => &["border-top", "border-right", "border-bottom", "border-left"],
+        "border-top" | "border-right" | "border-bottom" | "border-left" => {
+            return expand_border_side(name, value)
+        }
+        "list-style" => return expand_list_style(value),
+        "text-decoration" => return expand_text_decoration(value),
+        "background" => return expand_background(value),
+        // Only takes the CSS-wide keywords
+        "all" if is_css_wide_keyword(value) => {
+            return PROPERTIES
+                .iter()
+                .filter(|property| property.name != "direction")
+                .map(|property| (property.name, value.clone()))
+                .collect();
+        }
+        _ => {
+            return match lookup(name) {
+                Some(property) => vec![(property.name, value.clone())],
+                None => vec![],
+            }
+        }
+    };
+
+    if is_css_wide_keyword(value) {
+        return longhands
+            .iter()
+            .flat_map(|&longhand| expand(longhand, value))
+            .collect();
+    }
+
+    // border sets the same value on every side
+    if name == "border" {
+        return longhands
+            .iter()
+            .flat_map(|&longhand| expand(longhand, value))
+            .collect();
+    }
+
+    // https://drafts.csswg.org/css-backgrounds/#border-width, one to four values
+    // for the top, right, bottom and left sides
+    let values = items(value);
+    let sides = match values.len() {
+        1 => [0, 0, 0, 0],
+        2 => [0, 1, 0, 1],
+        3 => [0, 1, 2, 1],
+        4 => [0, 1, 2, 3],
+        _ => return vec![],
+    };
+
+    longhands
+        .iter()
+        .zip(sides.iter())
+        .map(|(&longhand, &i)| (longhand, values[i].clone()))
+        .collect()
+}
+
+/// e.g `border-top: 1px solid red`, in any order
+fn expand_border_side(name: &str, value: &CSSValue) -> Vec<(&'static str, CSSValue)> {
+    let side = &name["border-".len()..];
+    let longhand = |property: &str| {
+        lookup(&format!("border-{}-{}", side, property))
+            .map(|property| property.name)
+            .unwrap()
+    };
+
+    if is_css_wide_keyword(value) {
+        return ["width", "style", "color"]
+            .iter()
+            .map(|property| (longhand(property), value.clone()))
+            .collect();
+    }
+
+    let mut width = None;
+    let mut style = None;
+    let mut color = None;
+
+    for item in items(value) {
+        let slot = if is_border_width(item) {
+            &mut width
+        } else if is_keyword_in(item, BORDER_STYLES) {
+            &mut style
+        } else if Color::parse(item, Color::BLACK).is_some() {
+            &mut color
+        } else {
+            return vec![];
+        };
+
+        if slot.replace(item.clone()).is_some() {
+            return vec![];
+        }
+    }
+
+    // The omitted longhands are reset
+    vec![
+        (
+            longhand("width"),
+            width.unwrap_or_else(|| keyword("medium")),
+        ),
+        (longhand("style"), style.unwrap_or_else(|| keyword("none"))),
+        (
+            longhand("color"),
+            color.unwrap_or_else(|| keyword("currentcolor")),
+        ),
+    ]
+}
+
+/// e.g `list-style: square inside`, `none` sets the type unless an image is given too
+fn expand_list_style(value: &CSSValue) -> Vec<(&'static str, CSSValue)> {
+    if is_css_wide_keyword(value) {
+        return ["list-style-type", "list-style-position", "list-style-image"]
+            .iter()
+            .map(|&longhand| (longhand, value.clone()))
+            .collect();
+    }
+
+    let mut list_style_type = None;
+    let mut position = None;
+    let mut image = None;
+    let mut nones = 0;
+
+    for item in items(value) {
+        match item {
+            CSSValue::Keyword(keyword) if keyword.eq_ignore_ascii_case("none") => nones += 1,
+            _ if is_keyword_in(item, &["inside", "outside"]) => position = Some(item.clone()),
+            CSSValue::Function(name, _) if name.eq_ignore_ascii_case("url") => {
+                image = Some(item.clone())
+            }
+            CSSValue::Keyword(_) | CSSValue::StringLiteral(_) => {
+                list_style_type = Some(item.clone())
+            }
+            _ => return vec![],
+        }
+    }
+
+    // Each none goes to the first of type and image without a value
+    for _ in 0..nones {
+        if list_style_type.is_none() {
+            list_style_type = Some(keyword("none"));
+        } else if image.is_none() {
+            image = Some(keyword("none"));
+        }
+    }
+
+    vec![
+        (
+            "list-style-type",
+            list_style_type.unwrap_or_else(|| keyword("disc")),
+        ),
+        (
+            "list-style-position",
+            position.unwrap_or_else(|| keyword("outside")),
+        ),
+        ("list-style-image", image.unwrap_or_else(|| keyword("none"))),
+    ]
+}
+
+/// e.g `text-decoration: underline dotted red`
+fn expand_text_decoration(value: &CSSValue) -> Vec<(&'static str, CSSValue)> {
+    if is_css_wide_keyword(value) {
+        return [
+            "text-decoration-line",
+            "text-decoration-style",
+            "text-decoration-color",
+        ]
+        .iter()
+        .map(|&longhand| (longhand, value.clone()))
+        .collect();
+    }
+
+    let mut lines = vec![];
+    let mut style = None;
+    let mut color = None;
+
+    for item in items(value) {
+        if is_keyword_in(item, TEXT_DECORATION_LINES) {
+            lines.push(item.clone());
+        } else if is_keyword_in(item, TEXT_DECORATION_STYLES) {
+            style = Some(item.clone());
+        } else if Color::parse(item, Color::BLACK).is_some() {
+            color = Some(item.clone());
+        } else {
+            return vec![];
+        }
+    }
+
+    let line = match lines.len() {
+        0 => keyword("none"),
+        1 => lines.remove(0),
+        _ => CSSValue::List(lines),
+    };
+
+    vec![
+        ("text-decoration-line", line),
+        (
+            "text-decoration-style",
+            style.unwrap_or_else(|| keyword("solid")),
+        ),
+        (
+            "text-decoration-color",
+            color.unwrap_or_else(|| keyword("currentcolor")),
+        ),
+    ]
+}
+
+/// Only the color and image of a single layer are supported, e.g `background: url(a.png) white`
+fn expand_background(value: &CSSValue) -> Vec<(&'static str, CSSValue)> {
+    if is_css_wide_keyword(value) {
+        return vec![
+            ("background-color", value.clone()),
+            ("background-image", value.clone()),
+        ];
+    }
+
+    let mut color = None;
+    let mut image = None;
+
+    for item in items(value) {
+        match item {
+            CSSValue::Function(name, _) if name.eq_ignore_ascii_case("url") => {
+                image = Some(item.clone())
+            }
+            CSSValue::Keyword(keyword) if keyword.eq_ignore_ascii_case("none") => {
+                image = Some(item.clone())
+            }
+            _ if Color::parse(item, Color::BLACK).is_some() => color = Some(item.clone()),
+            // Positions, sizes and repeat styles aren't supported
+            _ => (),
+        }
+    }
+
+    vec![
+        (
+            "background-color",
+            color.unwrap_or_else(|| keyword("transparent")),
+        ),
+        ("background-image", image.unwrap_or_else(|| keyword("none"))),
+    ]
+}
+
+const BORDER_STYLES: &[&str] = &[
+    "none", "hidden", "dotted", "dashed", "solid", "double", "groove", "ridge", "inset", "outset",
+];
+
+/// https://drafts.csswg.org/css-display/#the-display-properties, the single keyword values
+const DISPLAYS: &[&str] = &[
+    "none",
+    "contents",
+    "block",
+    "inline",
+    "inline-block",
+    "flow-root",
+    "run-in",
+    "list-item",
+    "flex",
+    "inline-flex",
+    "grid",
+    "inline-grid",
+    "ruby",
+    "ruby-base",
+    "ruby-text",
+    "ruby-base-container",
+    "ruby-text-container",
+    "table",
+    "inline-table",
+    "table-row-group",
+    "table-header-group",
+    "table-footer-group",
+    "table-row",
+    "table-cell",
+    "table-column-group",
+    "table-column",
+    "table-caption",
+];
+
+/// https://drafts.csswg.org/css-ui/#cursor
+const CURSORS: &[&str] = &[
+    "auto",
+    "default",
+    "none",
+    "context-menu",
+    "help",
+    "pointer",
+    "progress",
+    "wait",
+    "cell",
+    "crosshair",
+    "text",
+    "vertical-text",
+    "alias",
+    "copy",
+    "move",
+    "no-drop",
+    "not-allowed",
+    "grab",
+    "grabbing",
+    "e-resize",
+    "n-resize",
+    "ne-resize",
+    "nw-resize",
+    "s-resize",
+    "se-resize",
+    "sw-resize",
+    "w-resize",
+    "ew-resize",
+    "ns-resize",
+    "nesw-resize",
+    "nwse-resize",
+    "col-resize",
+    "row-resize",
+    "all-scroll",
+    "zoom-in",
+    "zoom-out",
+];
+
+const TEXT_DECORATION_LINES: &[&str] = &["none", "underline", "overline", "line-through", "blink"];
+
+const TEXT_DECORATION_STYLES: &[&str] = &["solid", "double", "dotted", "dashed", "wavy"];
+
+fn is_border_width(value: &CSSValue) -> bool {
+    match value {
+        CSSValue::NumberWithUnit(_, _) => true,
+        CSSValue::Number(Number(number)) => number.parse::<f32>() == Ok(0.0),
+        _ => is_keyword_in(value, &["thin", "medium", "thick"]),
+    }
+}
+
+fn is_keyword_in(value: &CSSValue, keywords: &[&str]) -> bool {
+    match value {
+        CSSValue::Keyword(keyword) => keywords
+            .iter()
+            .any(|candidate| keyword.eq_ignore_ascii_case(candidate)),
+        _ => false,
+    }
+}
+
+fn keyword(keyword: &str) -> CSSValue {
+    CSSValue::Keyword(String::from(keyword))
+}
+
+/// The items of a space separated value
+fn items(value: &CSSValue) -> &[CSSValue] {
+    match value {
+        CSSValue::List(items) => items,
+        value => std::slice::from_ref(value),
+    }
+}
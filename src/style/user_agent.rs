@@ -0,0 +1,51 @@
+//! https://html.spec.whatwg.org/multipage/rendering.html, the default styles of HTML elements
+
+use std::sync::OnceLock;
+
+use super::cascade::Origin;
+use crate::css_parser;
+use crate::css_parser::interface::Stylesheet;
+use crate::html_parser::dom::Document;
+use crate::html_parser::interface::QuirksMode;
+
+/// The built-in stylesheet
+pub const CSS: &str = include_str!("user_agent.css");
+
+/// The built-in rules added for documents in quirks mode
+pub const QUIRKS_CSS: &str = include_str!("quirks.css");
+
+/// The stylesheets of the user agent origin, the lowest one of the cascade
+#[derive(Debug)]
+pub struct UserAgentStylesheet {
+    stylesheet: Stylesheet,
+    quirks: Stylesheet,
+}
+
+impl UserAgentStylesheet {
+    /// Replaces the built-in stylesheets, parse errors are ignored
+    pub fn new(css: &str, quirks_css: &str) -> UserAgentStylesheet {
+        UserAgentStylesheet {
+            stylesheet: css_parser::parse(css).0,
+            quirks: css_parser::parse(quirks_css).0,
+        }
+    }
+
+    /// The built-in stylesheets, parsed on first use
+    pub fn builtin() -> &'static UserAgentStylesheet {
+        static BUILTIN: OnceLock<UserAgentStylesheet> = OnceLock::new();
+
+        BUILTIN.get_or_init(|| UserAgentStylesheet::new(CSS, QUIRKS_CSS))
+    }
+
+    /// What applies to the document, the quirks rules are left out unless it
+    /// is in quirks mode
+    pub fn stylesheets(&self, document: &Document) -> Vec<(Origin, &Stylesheet)> {
+        let mut stylesheets = vec![(Origin::UserAgent, &self.stylesheet)];
+
+        if document.quirks_mode == QuirksMode::Quirks {
+            stylesheets.push((Origin::UserAgent, &self.quirks));
+        }
+
+        stylesheets
+    }
+}
@@ -0,0 +1,407 @@
+//! https://drafts.csswg.org/css-values/, computed values
+
+use std::fmt;
+
+use crate::css_parser::interface::CSSValue;
+
+/// https://drafts.csswg.org/css-cascade/#computed-value, lengths are in CSS pixels
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Keyword(String),
+    Length(f32),
+    Percentage(f32),
+    Number(f32),
+    /// A dimension which can't be made absolute, e.g 90deg or 50vw
+    Dimension(f32, String),
+    Color(Color),
+    String(String),
+    Function(String, Vec<Value>),
+    List(Vec<Value>),
+    CommaSeparatedList(Vec<Value>),
+}
+
+impl Value {
+    pub fn keyword(keyword: &str) -> Value {
+        Value::Keyword(String::from(keyword))
+    }
+
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Value::Keyword(value) if value == keyword)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Keyword(keyword) => f.write_str(keyword),
+            Value::Length(length) => write!(f, "{}px", length),
+            Value::Percentage(percentage) => write!(f, "{}%", percentage),
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Dimension(number, unit) => write!(f, "{}{}", number, unit),
+            Value::Color(color) => write!(f, "{}", color),
+            Value::String(string) => {
+                f.write_str("\"")?;
+
+                for c in string.chars() {
+                    match c {
+                        '"' | '\\' => write!(f, "\\{}", c)?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+
+                f.write_str("\"")
+            }
+            Value::Function(name, arguments) => {
+                write!(f, "{}(", name)?;
+                write_separated(f, arguments, ", ")?;
+                f.write_str(")")
+            }
+            Value::List(values) => write_separated(f, values, " "),
+            Value::CommaSeparatedList(values) => write_separated(f, values, ", "),
+        }
+    }
+}
+
+fn write_separated(f: &mut fmt::Formatter<'_>, values: &[Value], separator: &str) -> fmt::Result {
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            f.write_str(separator)?;
+        }
+
+        write!(f, "{}", value)?;
+    }
+
+    Ok(())
+}
+
+/// https://drafts.csswg.org/css-color/#rgb-functions, the alpha is between 0 and 1
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: f32,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const TRANSPARENT: Color = Color {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 0.0,
+    };
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 1.0 }
+    }
+
+    /// The color of a `<color>` value, `current_color` being the value of
+    /// `currentcolor`. None when the value isn't a color
+    pub fn parse(value: &CSSValue, current_color: Color) -> Option<Color> {
+        match value {
+            CSSValue::Keyword(keyword) => {
+                let keyword = keyword.to_ascii_lowercase();
+
+                match keyword.as_str() {
+                    "currentcolor" => Some(current_color),
+                    "transparent" => Some(Color::TRANSPARENT),
+                    _ => NAMED_COLORS
+                        .binary_search_by_key(&keyword.as_str(), |&(name, _)| name)
+                        .ok()
+                        .map(|i| {
+                            let [r, g, b] = NAMED_COLORS[i].1;
+                            Color::rgb(r, g, b)
+                        }),
+                }
+            }
+
+            CSSValue::Color(hex) => parse_hex(hex),
+
+            CSSValue::Function(name, arguments) => {
+                let arguments = color_arguments(arguments)?;
+
+                match name.to_ascii_lowercase().as_str() {
+                    "rgb" | "rgba" => parse_rgb(&arguments),
+                    "hsl" | "hsla" => parse_hsl(&arguments),
+                    _ => None,
+                }
+            }
+
+            _ => None,
+        }
+    }
+}
+
+/// https://drafts.csswg.org/cssom/#serializing-css-values
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.a == 1.0 {
+            write!(f, "rgb({}, {}, {})", self.r, self.g, self.b)
+        } else {
+            write!(f, "rgba({}, {}, {}, {})", self.r, self.g, self.b, self.a)
+        }
+    }
+}
+
+/// https://drafts.csswg.org/css-color/#hex-notation, 3, 4, 6 or 8 digits
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok();
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+
+    let [r, g, b, a] = match hex.len() {
+        3 | 4 => {
+            let alpha = if hex.len() == 4 { digit(3)? } else { 0xF };
+            [digit(0)?, digit(1)?, digit(2)?, alpha].map(|digit| digit * 0x11)
+        }
+        6 | 8 => {
+            let alpha = if hex.len() == 8 { byte(6)? } else { 0xFF };
+            [byte(0)?, byte(2)?, byte(4)?, alpha]
+        }
+        _ => return None,
+    };
+
+    Some(Color {
+        r,
+        g,
+        b,
+        a: a as f32 / 255.0,
+    })
+}
+
+/// Both rgb(0, 0, 0, 0.5) and rgb(0 0 0 / 50%) give [0, 0, 0, 0.5]
+fn color_arguments(arguments: &[CSSValue]) -> Option<Vec<&CSSValue>> {
+    let arguments: Vec<&CSSValue> = match arguments {
+        [CSSValue::List(values)] => values
+            .iter()
+            .filter(|value| !matches!(value, CSSValue::Keyword(slash) if slash == "/"))
+            .collect(),
+        arguments => arguments.iter().collect(),
+    };
+
+    match arguments.len() {
+        3 | 4 => Some(arguments),
+        _ => None,
+    }
+}
+
+fn parse_rgb(arguments: &[&CSSValue]) -> Option<Color> {
+    let channel = |value: &CSSValue| {
+        let channel = match value {
+            CSSValue::Number(number) => number.0.parse::<f32>().ok()?,
+            CSSValue::Percentage(number) => number.0.parse::<f32>().ok()? * 2.55,
+            _ => return None,
+        };
+
+        Some(channel.round().clamp(0.0, 255.0) as u8)
+    };
+
+    Some(Color {
+        r: channel(arguments[0])?,
+        g: channel(arguments[1])?,
+        b: channel(arguments[2])?,
+        a: alpha(arguments.get(3).copied())?,
+    })
+}
+
+/// https://drafts.csswg.org/css-color/#hsl-to-rgb
+fn parse_hsl(arguments: &[&CSSValue]) -> Option<Color> {
+    let hue = match arguments[0] {
+        CSSValue::Number(number) => number.0.parse::<f32>().ok()?,
+        CSSValue::NumberWithUnit(number, unit) if unit == "deg" => number.0.parse::<f32>().ok()?,
+        _ => return None,
+    };
+
+    let percentage = |value: &CSSValue| match value {
+        CSSValue::Percentage(number) => {
+            Some(number.0.parse::<f32>().ok()?.clamp(0.0, 100.0) / 100.0)
+        }
+        _ => None,
+    };
+
+    let saturation = percentage(arguments[1])?;
+    let lightness = percentage(arguments[2])?;
+
+    let channel = |n: f32| {
+        let k = (n + hue.rem_euclid(360.0) / 30.0) % 12.0;
+        let a = saturation * lightness.min(1.0 - lightness);
+        let value = lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0);
+
+        (value * 255.0).round() as u8
+    };
+
+    Some(Color {
+        r: channel(0.0),
+        g: channel(8.0),
+        b: channel(4.0),
+        a: alpha(arguments.get(3).copied())?,
+    })
+}
+
+/// Opaque when missing
+fn alpha(value: Option<&CSSValue>) -> Option<f32> {
+    let alpha = match value {
+        None => 1.0,
+        Some(CSSValue::Number(number)) => number.0.parse::<f32>().ok()?,
+        Some(CSSValue::Percentage(number)) => number.0.parse::<f32>().ok()? / 100.0,
+        _ => return None,
+    };
+
+    Some(alpha.clamp(0.0, 1.0))
+}
+
+/// https://drafts.csswg.org/css-color/#named-colors, sorted by name
+const NAMED_COLORS: &[(&str, [u8; 3])] = &[
+    ("aliceblue", [240, 248, 255]),
+    ("antiquewhite", [250, 235, 215]),
+    ("aqua", [0, 255, 255]),
+    ("aquamarine", [127, 255, 212]),
+    ("azure", [240, 255, 255]),
+    ("beige", [245, 245, 220]),
+    ("bisque", [255, 228, 196]),
+    ("black", [0, 0, 0]),
+    ("blanchedalmond", [255, 235, 205]),
+    ("blue", [0, 0, 255]),
+    ("blueviolet", [138, 43, 226]),
+    ("brown", [165, 42, 42]),
+    ("burlywood", [222, 184, 135]),
+    ("cadetblue", [95, 158, 160]),
+    ("chartreuse", [127, 255, 0]),
+    ("chocolate", [210, 105, 30]),
+    ("coral", [255, 127, 80]),
+    ("cornflowerblue", [100, 149, 237]),
+    ("cornsilk", [255, 248, 220]),
+    ("crimson", [220, 20, 60]),
+    ("cyan", [0, 255, 255]),
+    ("darkblue", [0, 0, 139]),
+    ("darkcyan", [0, 139, 139]),
+    ("darkgoldenrod", [184, 134, 11]),
+    ("darkgray", [169, 169, 169]),
+    ("darkgreen", [0, 100, 0]),
+    ("darkgrey", [169, 169, 169]),
+    ("darkkhaki", [189, 183, 107]),
+    ("darkmagenta", [139, 0, 139]),
+    ("darkolivegreen", [85, 107, 47]),
+    ("darkorange", [255, 140, 0]),
+    ("darkorchid", [153, 50, 204]),
+    ("darkred", [139, 0, 0]),
+    ("darksalmon", [233, 150, 122]),
+    ("darkseagreen", [143, 188, 143]),
+    ("darkslateblue", [72, 61, 139]),
+    ("darkslategray", [47, 79, 79]),
+    ("darkslategrey", [47, 79, 79]),
+    ("darkturquoise", [0, 206, 209]),
+    ("darkviolet", [148, 0, 211]),
+    ("deeppink", [255, 20, 147]),
+    ("deepskyblue", [0, 191, 255]),
+    ("dimgray", [105, 105, 105]),
+    ("dimgrey", [105, 105, 105]),
+    ("dodgerblue", [30, 144, 255]),
+    ("firebrick", [178, 34, 34]),
+    ("floralwhite", [255, 250, 240]),
+    ("forestgreen", [34, 139, 34]),
+    ("fuchsia", [255, 0, 255]),
+    ("gainsboro", [220, 220, 220]),
+    ("ghostwhite", [248, 248, 255]),
+    ("gold", [255, 215, 0]),
+    ("goldenrod", [218, 165, 32]),
+    ("gray", [128, 128, 128]),
+    ("green", [0, 128, 0]),
+    ("greenyellow", [173, 255, 47]),
+    ("grey", [128, 128, 128]),
+    ("honeydew", [240, 255, 240]),
+    ("hotpink", [255, 105, 180]),
+    ("indianred", [205, 92, 92]),
+    ("indigo", [75, 0, 130]),
+    ("ivory", [255, 255, 240]),
+    ("khaki", [240, 230, 140]),
+    ("lavender", [230, 230, 250]),
+    ("lavenderblush", [255, 240, 245]),
+    ("lawngreen", [124, 252, 0]),
+    ("lemonchiffon", [255, 250, 205]),
+    ("lightblue", [173, 216, 230]),
+    ("lightcoral", [240, 128, 128]),
+    ("lightcyan", [224, 255, 255]),
+    ("lightgoldenrodyellow", [250, 250, 210]),
+    ("lightgray", [211, 211, 211]),
+    ("lightgreen", [144, 238, 144]),
+    ("lightgrey", [211, 211, 211]),
+    ("lightpink", [255, 182, 193]),
+    ("lightsalmon", [255, 160, 122]),
+    ("lightseagreen", [32, 178, 170]),
+    ("lightskyblue", [135, 206, 250]),
+    ("lightslategray", [119, 136, 153]),
+    ("lightslategrey", [119, 136, 153]),
+    ("lightsteelblue", [176, 196, 222]),
+    ("lightyellow", [255, 255, 224]),
+    ("lime", [0, 255, 0]),
+    ("limegreen", [50, 205, 50]),
+    ("linen", [250, 240, 230]),
+    ("magenta", [255, 0, 255]),
+    ("maroon", [128, 0, 0]),
+    ("mediumaquamarine", [102, 205, 170]),
+    ("mediumblue", [0, 0, 205]),
+    ("mediumorchid", [186, 85, 211]),
+    ("mediumpurple", [147, 112, 219]),
+    ("mediumseagreen", [60, 179, 113]),
+    ("mediumslateblue", [123, 104, 238]),
+    ("mediumspringgreen", [0, 250, 154]),
+    ("mediumturquoise", [72, 209, 204]),
+    ("mediumvioletred", [199, 21, 133]),
+    ("midnightblue", [25, 25, 112]),
+    ("mintcream", [245, 255, 250]),
+    ("mistyrose", [255, 228, 225]),
+    ("moccasin", [255, 228, 181]),
+    ("navajowhite", [255, 222, 173]),
+    ("navy", [0, 0, 128]),
+    ("oldlace", [253, 245, 230]),
+    ("olive", [128, 128, 0]),
+    ("olivedrab", [107, 142, 35]),
+    ("orange", [255, 165, 0]),
+    ("orangered", [255, 69, 0]),
+    ("orchid", [218, 112, 214]),
+    ("palegoldenrod", [238, 232, 170]),
+    ("palegreen", [152, 251, 152]),
+    ("paleturquoise", [175, 238, 238]),
+    ("palevioletred", [219, 112, 147]),
+    ("papayawhip", [255, 239, 213]),
+    ("peachpuff", [255, 218, 185]),
+    ("peru", [205, 133, 63]),
+    ("pink", [255, 192, 203]),
+    ("plum", [221, 160, 221]),
+    ("powderblue", [176, 224, 230]),
+    ("purple", [128, 0, 128]),
+    ("rebeccapurple", [102, 51, 153]),
+    ("red", [255, 0, 0]),
+    ("rosybrown", [188, 143, 143]),
+    ("royalblue", [65, 105, 225]),
+    ("saddlebrown", [139, 69, 19]),
+    ("salmon", [250, 128, 114]),
+    ("sandybrown", [244, 164, 96]),
+    ("seagreen", [46, 139, 87]),
+    ("seashell", [255, 245, 238]),
+    ("sienna", [160, 82, 45]),
+    ("silver", [192, 192, 192]),
+    ("skyblue", [135, 206, 235]),
+    ("slateblue", [106, 90, 205]),
+    ("slategray", [112, 128, 144]),
+    ("slategrey", [112, 128, 144]),
+    ("snow", [255, 250, 250]),
+    ("springgreen", [0, 255, 127]),
+    ("steelblue", [70, 130, 180]),
+    ("tan", [210, 180, 140]),
+    ("teal", [0, 128, 128]),
+    ("thistle", [216, 191, 216]),
+    ("tomato", [255, 99, 71]),
+    ("turquoise", [64, 224, 208]),
+    ("violet", [238, 130, 238]),
+    ("wheat", [245, 222, 179]),
+    ("white", [255, 255, 255]),
+    ("whitesmoke", [245, 245, 245]),
+    ("yellow", [255, 255, 0]),
+    ("yellowgreen", [154, 205, 50]),
+];
@@ -0,0 +1,263 @@
+use web_renderer::css_parser;
+use web_renderer::css_parser::interface::Specificity;
+use web_renderer::span::Span;
+
+/// The selectors of every rule, with the names of their declarations
+fn rules(css: &str) -> Vec<(String, Vec<String>)> {
+    let (stylesheet, _) = css_parser::parse(css);
+
+    stylesheet
+        .rules
+        .iter()
+        .map(|rule| {
+            let selectors: Vec<String> = rule.selectors.iter().map(|s| s.to_string()).collect();
+            let declarations = rule.declarations.iter().map(|d| d.name.clone()).collect();
+
+            (selectors.join(", "), declarations)
+        })
+        .collect()
+}
+
+fn error_codes(css: &str) -> Vec<&'static str> {
+    let (_, errors) = css_parser::parse(css);
+
+    errors.iter().map(|error| error.code).collect()
+}
+
+fn rule(selectors: &str, declarations: &[&str]) -> (String, Vec<String>) {
+    (
+        String::from(selectors),
+        declarations
+            .iter()
+            .map(|&name| String::from(name))
+            .collect(),
+    )
+}
+
+#[test]
+fn invalid_declarations_are_dropped_up_to_the_end_of_the_block() {
+    let cases = [
+        "a { color } b { color: red } c { x: y }",
+        "a { color: } b { color: red } c { x: y }",
+        "a { color: red ! } b { color: red } c { x: y }",
+        "a { color: red !foo } b { color: red } c { x: y }",
+        "a { 12: red } b { color: red } c { x: y }",
+    ];
+
+    for css in cases {
+        assert_eq!(
+            rules(css),
+            [rule("a", &[]), rule("b", &["color"]), rule("c", &["x"])],
+            "{}",
+            css
+        );
+        assert_eq!(error_codes(css), ["invalid-declaration"], "{}", css);
+    }
+}
+
+#[test]
+fn invalid_declarations_are_dropped_up_to_the_next_semicolon() {
+    let css = "a { color; margin: 0; width: ; height: 1px !x; top: 0 }";
+
+    assert_eq!(rules(css), [rule("a", &["margin", "top"])]);
+    assert_eq!(error_codes(css).len(), 3);
+}
+
+#[test]
+fn unclosed_blocks_end_with_the_file() {
+    assert_eq!(rules("a { color: red"), [rule("a", &["color"])]);
+    assert_eq!(error_codes("a { color: red"), ["eof-in-block"]);
+    assert_eq!(error_codes("a"), ["eof-in-rule"]);
+}
+
+#[test]
+fn stray_closing_braces_are_errors() {
+    assert_eq!(rules("} a { x: y }"), [rule("a", &["x"])]);
+    assert_eq!(error_codes("} a { x: y }"), ["unexpected-token"]);
+}
+
+#[test]
+fn declaration_lists_recover_like_blocks() {
+    let (declarations, errors) = css_parser::parse_declarations("color; margin: 0; width:");
+
+    let names: Vec<&str> = declarations.iter().map(|d| d.name.as_str()).collect();
+
+    assert_eq!(names, ["margin"]);
+    assert_eq!(errors.len(), 2);
+}
+
+/// The source of the span, with the line and column it starts at
+fn source(css: &str, span: Span) -> (usize, usize, &str) {
+    (
+        span.start.line,
+        span.start.column,
+        &css[span.start.offset..span.end.offset],
+    )
+}
+
+#[test]
+fn rules_declarations_and_errors_have_source_spans() {
+    let css = "a,\nb > c { color: red !important; x: }\n\u{e9} { top: 0 } }";
+    let (stylesheet, errors) = css_parser::parse(css);
+
+    let spans: Vec<_> = stylesheet
+        .rules
+        .iter()
+        .map(|rule| {
+            let declarations: Vec<_> = rule
+                .declarations
+                .iter()
+                .map(|declaration| source(css, declaration.span))
+                .collect();
+
+            (source(css, rule.span), declarations)
+        })
+        .collect();
+
+    assert_eq!(
+        spans,
+        [
+            (
+                (1, 1, "a,\nb > c { color: red !important; x: }"),
+                vec![(2, 9, "color: red !important")]
+            ),
+            ((3, 1, "\u{e9} { top: 0 }"), vec![(3, 5, "top: 0")]),
+        ]
+    );
+
+    let errors: Vec<_> = errors
+        .iter()
+        .map(|error| (error.code, source(css, error.span)))
+        .collect();
+
+    assert_eq!(
+        errors,
+        [
+            ("invalid-declaration", (2, 35, "}")),
+            ("unexpected-token", (3, 14, "}")),
+        ]
+    );
+}
+
+/// The value of the declaration, as debug output
+fn value(declaration: &str) -> String {
+    let (declarations, errors) = css_parser::parse_declarations(declaration);
+
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    format!("{:?}", declarations[0].value)
+}
+
+#[test]
+fn numbers_and_dimensions() {
+    assert_eq!(
+        value("x: 1.5e1px"),
+        r#"NumberWithUnit(Number("1.5e1"), "px")"#
+    );
+    assert_eq!(
+        value("x: -.5em +2 10%"),
+        r#"List([NumberWithUnit(Number("-.5"), "em"), Number(Number("+2")), Percentage(Number("10"))])"#
+    );
+    // An exponent needs digits, and a sign starts a new number
+    assert_eq!(value("x: 1e"), r#"NumberWithUnit(Number("1"), "e")"#);
+    assert_eq!(
+        value("x: 3-4"),
+        r#"List([Number(Number("3")), Number(Number("-4"))])"#
+    );
+}
+
+#[test]
+fn strings_and_escapes() {
+    assert_eq!(
+        value("x: \"a\\\"b\" 'c\\\nd' \"\\41 x\""),
+        r#"List([StringLiteral("a\"b"), StringLiteral("cd"), StringLiteral("Ax")])"#
+    );
+
+    // A newline ends the string, which makes the declaration invalid
+    let (declarations, errors) = css_parser::parse_declarations("x: \"a\n; y: 1");
+    let names: Vec<&str> = declarations.iter().map(|d| d.name.as_str()).collect();
+
+    assert_eq!(names, ["y"]);
+    assert_eq!(errors[0].code, "bad-string");
+}
+
+#[test]
+fn urls_and_comments() {
+    assert_eq!(
+        value("x: url( a.png ) url(\"b c.png\")"),
+        r#"List([Function("url", [StringLiteral("a.png")]), Function("url", [StringLiteral("b c.png")])])"#
+    );
+    assert_eq!(value("x: /* c */ 0 /**/"), r#"Number(Number("0"))"#);
+
+    let (_, errors) = css_parser::parse_declarations("x: url(a b)");
+
+    assert_eq!(errors[0].code, "bad-url");
+}
+
+fn specificity(selector: &str) -> (u32, u32, u32) {
+    let selectors = css_parser::parse_selectors(selector).unwrap();
+    let Specificity(ids, classes, types) = selectors[0].specificity();
+
+    (ids, classes, types)
+}
+
+#[test]
+fn selector_specificity() {
+    let cases = [
+        ("*", (0, 0, 0)),
+        ("li", (0, 0, 1)),
+        ("ul li", (0, 0, 2)),
+        ("ul ol + li", (0, 0, 3)),
+        ("h1 + *[rel=up]", (0, 1, 1)),
+        ("ul ol li.red", (0, 1, 3)),
+        ("li.red.level", (0, 2, 1)),
+        ("#x34y", (1, 0, 0)),
+        ("#s12:not(foo)", (1, 0, 1)),
+        (".foo :is(.bar, #baz)", (1, 1, 0)),
+        ("a:where(#x, .y) b", (0, 0, 2)),
+        ("li:nth-child(2n+1)", (0, 1, 1)),
+        ("a:has(> #x, img)", (1, 0, 1)),
+        ("p::before", (0, 0, 2)),
+        ("a:hover::after", (0, 1, 2)),
+    ];
+
+    for (selector, expected) in cases {
+        assert_eq!(specificity(selector), expected, "{}", selector);
+    }
+
+    assert!(Specificity(1, 0, 0) > Specificity(0, 10, 10));
+    assert!(Specificity(0, 1, 0) > Specificity(0, 0, 10));
+}
+
+#[test]
+fn selectors_serialize_to_canonical_css() {
+    let cases = [
+        ("a>b", "a > b"),
+        ("a  +  b~c d", "a + b ~ c d"),
+        // The simple selectors go in a fixed order
+        (
+            "*.x#y[href^='h' i]:hover::before",
+            "*#y.x[href^=\"h\" i]:hover::before",
+        ),
+        ("[a=\"b\\\"c\"]", "[a=\"b\\\"c\"]"),
+        ("[lang|=EN s]", "[lang|=\"EN\" s]"),
+        ("li:nth-child( 2n + 1 )", "li:nth-child(2n+1)"),
+        (
+            ":nth-child(odd):nth-child(even)",
+            ":nth-child(2n+1):nth-child(2n)",
+        ),
+        (":not(.a,.b)", ":not(.a, .b)"),
+        (":has(>img, + p)", ":has(> img, + p)"),
+    ];
+
+    for (selector, expected) in cases {
+        let selectors = css_parser::parse_selectors(selector).unwrap();
+
+        assert_eq!(selectors[0].to_string(), expected);
+
+        // Parsing the serialization gives it back
+        let reparsed = css_parser::parse_selectors(expected).unwrap();
+
+        assert_eq!(reparsed[0].to_string(), expected);
+    }
+}
@@ -0,0 +1,232 @@
+use web_renderer::html_parser;
+use web_renderer::html_parser::dom::{Document, DomError, NodeId};
+use web_renderer::html_parser::interface::NodeData;
+
+/// The name of elements, the data of text and comments
+fn name(document: &Document, id: NodeId) -> String {
+    match &document[id].data {
+        NodeData::Element(element) => element.name.clone(),
+        NodeData::Text(text) | NodeData::Comment(text) => text.clone(),
+        NodeData::Doctype(_) => String::from("!doctype"),
+        NodeData::Document => String::from("#document"),
+        NodeData::DocumentFragment => String::from("#fragment"),
+    }
+}
+
+fn names(document: &Document, ids: impl Iterator<Item = NodeId>) -> Vec<String> {
+    ids.map(|id| name(document, id)).collect()
+}
+
+/// The first element of the name
+fn find(document: &Document, element: &str) -> NodeId {
+    document
+        .descendants(document.root())
+        .find(|&id| document[id].as_element().is_some_and(|e| e.name == element))
+        .unwrap()
+}
+
+#[test]
+fn nodes_link_to_their_parent_and_siblings() {
+    let (document, _) = html_parser::parse("<!DOCTYPE html><ul><li>a<li>b<li>c</ul>");
+    let ul = find(&document, "ul");
+    let items: Vec<NodeId> = document.children(ul).collect();
+
+    assert_eq!(names(&document, items.iter().copied()), ["li", "li", "li"]);
+    assert_eq!(document[ul].first_child(), Some(items[0]));
+    assert_eq!(document[ul].last_child(), Some(items[2]));
+    assert_eq!(document[items[1]].previous_sibling(), Some(items[0]));
+    assert_eq!(document[items[1]].next_sibling(), Some(items[2]));
+    assert_eq!(document[items[0]].previous_sibling(), None);
+    assert_eq!(document[items[2]].next_sibling(), None);
+
+    for &item in &items {
+        assert_eq!(document[item].parent(), Some(ul));
+    }
+
+    assert_eq!(document[document.root()].parent(), None);
+}
+
+#[test]
+fn traversals_follow_document_order() {
+    let (document, _) = html_parser::parse("<!DOCTYPE html><p>a<b>b</b></p><!--c-->");
+    let b = find(&document, "b");
+
+    assert_eq!(
+        names(&document, document.descendants(document.root())),
+        ["!doctype", "html", "head", "body", "p", "a", "b", "b", "c"]
+    );
+    assert_eq!(
+        names(&document, document.ancestors(b)),
+        ["p", "body", "html", "#document"]
+    );
+    assert_eq!(document.document_element(), Some(find(&document, "html")));
+
+    // Stops at the end of the subtree
+    let p = find(&document, "p");
+
+    assert_eq!(names(&document, document.descendants(p)), ["a", "b", "b"]);
+}
+
+#[test]
+fn nodes_are_inserted_moved_and_removed() {
+    let (mut document, _) = html_parser::parse("<ul><li>a<li>b</ul>");
+    let ul = find(&document, "ul");
+    let items: Vec<NodeId> = document.children(ul).collect();
+
+    let c = document.create_element("LI").unwrap();
+    document.append_child(ul, c).unwrap();
+    document.set_text_content(c, "c");
+
+    // Inserting a node which is in the tree moves it
+    document.insert_before(ul, c, Some(items[0])).unwrap();
+    assert_eq!(document.inner_html(ul), "<li>c</li><li>a</li><li>b</li>");
+
+    document.insert_before(ul, c, Some(c)).unwrap();
+    assert_eq!(document.inner_html(ul), "<li>c</li><li>a</li><li>b</li>");
+
+    let replaced = document.replace_child(ul, c, items[1]).unwrap();
+    assert_eq!(replaced, items[1]);
+    assert_eq!(document[replaced].parent(), None);
+    assert_eq!(document.inner_html(ul), "<li>a</li><li>c</li>");
+
+    document.remove_child(ul, items[0]).unwrap();
+    assert_eq!(document.inner_html(ul), "<li>c</li>");
+}
+
+#[test]
+fn fragments_insert_their_children() {
+    let (mut document, _) = html_parser::parse("<p>x</p>");
+    let p = find(&document, "p");
+
+    let fragment = document.create_document_fragment();
+    let a = document.create_text_node("a");
+    let b = document.create_comment("b");
+    document.append_child(fragment, a).unwrap();
+    document.append_child(fragment, b).unwrap();
+
+    document
+        .insert_before(p, fragment, document[p].first_child())
+        .unwrap();
+
+    assert_eq!(document.inner_html(p), "a<!--b-->x");
+    assert_eq!(document[fragment].first_child(), None);
+}
+
+#[test]
+fn clones_and_attributes() {
+    let (mut document, _) = html_parser::parse("<p class=a><b>x</b></p>");
+    let p = find(&document, "p");
+
+    let shallow = document.clone_node(p, false);
+    let deep = document.clone_node(p, true);
+
+    assert_eq!(document.outer_html(shallow), "<p class=\"a\"></p>");
+    assert_eq!(document.outer_html(deep), "<p class=\"a\"><b>x</b></p>");
+    assert_eq!(document[deep].parent(), None);
+
+    document.set_attribute(p, "ID", "y").unwrap();
+    document.set_attribute(p, "class", "b").unwrap();
+    assert_eq!(
+        document.outer_html(p),
+        "<p class=\"b\" id=\"y\"><b>x</b></p>"
+    );
+
+    assert_eq!(document.remove_attribute(p, "Class").unwrap().value, "b");
+    assert!(document.remove_attribute(p, "class").is_none());
+}
+
+#[test]
+fn invalid_insertions_are_errors() {
+    let (mut document, _) = html_parser::parse("<!DOCTYPE html><p><b>x</b></p>");
+    let root = document.root();
+    let doctype = document.children(root).next().unwrap();
+    let html = find(&document, "html");
+    let p = find(&document, "p");
+    let b = find(&document, "b");
+    let text = document[b].first_child().unwrap();
+
+    // Inside itself, under a text node or a doctype
+    assert_eq!(document.append_child(b, p), Err(DomError::HierarchyRequest));
+    assert_eq!(document.append_child(p, p), Err(DomError::HierarchyRequest));
+    assert_eq!(
+        document.append_child(text, b),
+        Err(DomError::HierarchyRequest)
+    );
+    assert_eq!(
+        document.append_child(doctype, b),
+        Err(DomError::HierarchyRequest)
+    );
+    assert_eq!(
+        document.append_child(p, root),
+        Err(DomError::HierarchyRequest)
+    );
+
+    // The reference child must be a child of the parent
+    assert_eq!(
+        document.insert_before(p, text, Some(p)),
+        Err(DomError::NotFound)
+    );
+    assert_eq!(document.remove_child(p, text), Err(DomError::NotFound));
+
+    // A document has no text, one doctype before one element
+    let element = document.create_element("div").unwrap();
+    let other = document.create_text_node("t");
+    let second_doctype = document.clone_node(doctype, false);
+
+    assert_eq!(
+        document.append_child(root, other),
+        Err(DomError::HierarchyRequest)
+    );
+    assert_eq!(
+        document.append_child(root, element),
+        Err(DomError::HierarchyRequest)
+    );
+    assert_eq!(
+        document.append_child(root, second_doctype),
+        Err(DomError::HierarchyRequest)
+    );
+    assert_eq!(
+        document.append_child(p, second_doctype),
+        Err(DomError::HierarchyRequest)
+    );
+
+    // The root element and the doctype can be replaced, an element can't go
+    // before the doctype
+    assert_eq!(document.replace_child(root, element, html), Ok(html));
+    assert_eq!(
+        document.replace_child(root, second_doctype, doctype),
+        Ok(doctype)
+    );
+
+    document.remove_child(root, element).unwrap();
+
+    assert_eq!(
+        document.insert_before(root, html, Some(second_doctype)),
+        Err(DomError::HierarchyRequest)
+    );
+    assert_eq!(document.append_child(root, html), Ok(html));
+
+    // Only fragments with at most one element and no text
+    let fragment = document.create_document_fragment();
+    let text = document.create_text_node("t");
+    document.append_child(fragment, text).unwrap();
+
+    assert_eq!(
+        document.append_child(root, fragment),
+        Err(DomError::HierarchyRequest)
+    );
+
+    // Invalid names
+    assert_eq!(
+        document.create_element("1p"),
+        Err(DomError::InvalidCharacter)
+    );
+    assert_eq!(
+        document.set_attribute(p, "a=b", ""),
+        Err(DomError::InvalidCharacter)
+    );
+    assert_eq!(
+        document.set_attribute(text, "a", ""),
+        Err(DomError::InvalidNodeType)
+    );
+}
@@ -0,0 +1,287 @@
+use web_renderer::css_parser;
+use web_renderer::css_parser::interface::ElementState;
+use web_renderer::html_parser;
+use web_renderer::html_parser::dom::{Document, NodeId};
+use web_renderer::style::matching::{self, StateProvider};
+
+/// The ids of the elements matching the selectors, in document order
+fn select(document: &Document, selectors: &str) -> Vec<String> {
+    matching::query_selector_all(document, document.root(), selectors)
+        .unwrap()
+        .into_iter()
+        .filter_map(|id| document[id].as_element()?.get_attribute("id"))
+        .map(String::from)
+        .collect()
+}
+
+/// Checks the ids each selector matches in the document
+fn check(html: &str, cases: &[(&str, &[&str])]) {
+    let (document, _) = html_parser::parse(html);
+
+    for (selectors, expected) in cases {
+        assert_eq!(select(&document, selectors), *expected, "{}", selectors);
+    }
+}
+
+#[test]
+fn simple_selectors_and_descendants() {
+    check(
+        "<!DOCTYPE html>
+        <div id=a class='x y'>
+            <p id=b class=x><span id=c></span></p>
+            <section id=d><p id=e class=Y></p></section>
+        </div>",
+        &[
+            ("p", &["b", "e"]),
+            ("P", &["b", "e"]),
+            (".x", &["a", "b"]),
+            (".x.y", &["a"]),
+            (".y", &["a"]),
+            ("#d", &["d"]),
+            ("div p", &["b", "e"]),
+            ("div > p", &["b"]),
+            ("div > * > p", &["e"]),
+            (".x span", &["c"]),
+            ("span, #d", &["c", "d"]),
+            ("*", &["a", "b", "c", "d", "e"]),
+        ],
+    );
+}
+
+#[test]
+fn classes_and_ids_ignore_case_in_quirks_mode() {
+    let html = "<div id=A class=Big></div>";
+
+    check(html, &[(".big", &["A"]), ("#a", &["A"])]);
+    check(
+        &format!("<!DOCTYPE html>{}", html),
+        &[(".big", &[]), ("#a", &[]), (".Big", &["A"])],
+    );
+}
+
+#[test]
+fn query_selector_returns_the_first_match() {
+    let (document, _) = html_parser::parse("<p id=a></p><p id=b></p>");
+    let first = matching::query_selector(&document, document.root(), "p").unwrap();
+
+    assert_eq!(
+        first.and_then(|id| document[id].as_element()?.get_attribute("id")),
+        Some("a")
+    );
+    assert!(matching::query_selector(&document, document.root(), "p >").is_err());
+}
+
+#[test]
+fn attribute_selectors() {
+    check(
+        "<!DOCTYPE html>
+        <a id=a href='https://x.org/a.pdf' lang=en-US data-state=open rel='nofollow noopener' type=TEXT></a>
+        <a id=b href=http://y lang=EN data-state=OPEN rel=nofollowx title=''></a>
+        <svg><a id=c xlink:href=q HREF=z></a></svg>",
+        &[
+            ("[title]", &["b"]),
+            ("a[id][lang]", &["a", "b"]),
+            ("[data-state=open]", &["a"]),
+            ("[rel~=nofollow]", &["a"]),
+            ("[rel~='nofollow noopener']", &[]),
+            ("[lang|=en]", &["a", "b"]),
+            ("[href^=https]", &["a"]),
+            ("[href$='.pdf']", &["a"]),
+            ("[href*='x.o']", &["a"]),
+            ("[title='']", &["b"]),
+            // An empty value never matches the substring operators
+            ("[href^=''], [href$=''], [href*='']", &[]),
+        ],
+    );
+}
+
+#[test]
+fn attribute_case_sensitivity() {
+    check(
+        "<!DOCTYPE html>
+        <a id=a data-state=open type=TEXT></a>
+        <a id=b data-state=OPEN></a>
+        <svg><a id=c HREF=z></a></svg>",
+        &[
+            ("[data-state=open i]", &["a", "b"]),
+            ("[data-state='OPEN' s]", &["b"]),
+            // Some values of HTML attributes ignore case, unless told otherwise
+            ("[type=text]", &["a"]),
+            ("[type=text s]", &[]),
+            // Names ignore case on HTML elements only
+            ("[DATA-STATE]", &["a", "b"]),
+            ("[HREF]", &[]),
+            ("[href]", &["c"]),
+        ],
+    );
+}
+
+#[test]
+fn attribute_namespaces() {
+    check(
+        "<!DOCTYPE html><svg><a id=a xlink:href=q></a><a id=b href=z></a></svg>",
+        &[
+            ("[href]", &["b"]),
+            ("[|href]", &["b"]),
+            ("[*|href]", &["a", "b"]),
+            ("[*|href=q]", &["a"]),
+        ],
+    );
+}
+
+#[test]
+fn invalid_attribute_selectors_are_errors() {
+    let (document, _) = html_parser::parse("<p>");
+
+    for selectors in ["[", "[a", "[a=]", "[a b]", "[a=b c]", "[a~~=b]", "[1=b]"] {
+        assert!(
+            matching::query_selector_all(&document, document.root(), selectors).is_err(),
+            "{}",
+            selectors
+        );
+    }
+}
+
+#[test]
+fn sibling_combinators() {
+    check(
+        "<!DOCTYPE html>
+        <h1 id=a></h1> text <!-- comment -->
+        <p id=b></p>
+        <p id=c></p>
+        <div id=d><p id=e></p></div>
+        <p id=f></p>",
+        &[
+            ("h1 + p", &["b"]),
+            ("h1 ~ p", &["b", "c", "f"]),
+            ("p + p", &["c"]),
+            ("p ~ p", &["c", "f"]),
+            ("h1 + p + p", &["c"]),
+            ("h1 ~ div p", &["e"]),
+            ("h1 ~ div > p", &["e"]),
+            ("h1 ~ * + p", &["c", "f"]),
+            ("div ~ p, h1 + p", &["b", "f"]),
+        ],
+    );
+}
+
+const LIST: &str = "<!DOCTYPE html><ul id=u>\
+    <li id=l1></li><li id=l2><!-- c --></li><b id=b1></b><li id=l3> </li>\
+    <li id=l4><i id=i1></i></li><b id=b2></b><li id=l5>t</li>\
+    </ul>";
+
+#[test]
+fn an_plus_b() {
+    check(
+        LIST,
+        &[
+            ("li:nth-child(2n+1)", &["l1", "l4", "l5"]),
+            ("li:nth-child(odd)", &["l1", "l4", "l5"]),
+            ("li:nth-child(even)", &["l2", "l3"]),
+            ("ul > :nth-child(3)", &["b1"]),
+            ("ul > :nth-child(-n+2)", &["l1", "l2"]),
+            ("ul > :nth-child(-2n+5)", &["l1", "b1", "l4"]),
+            ("ul > :nth-child(n+5)", &["l4", "b2", "l5"]),
+            ("ul > :nth-child(0n+0), :nth-child(-n-1)", &[]),
+            ("ul > :nth-last-child(2)", &["b2"]),
+            ("li:nth-of-type(2n)", &["l2", "l4"]),
+            ("li:nth-last-of-type(1)", &["l5"]),
+        ],
+    );
+
+    let (document, _) = html_parser::parse(LIST);
+
+    for selectors in [
+        ":nth-child(+ 2n)",
+        ":nth-child(2 n)",
+        ":nth-child(n-)",
+        ":nth-child()",
+    ] {
+        assert!(
+            matching::query_selector_all(&document, document.root(), selectors).is_err(),
+            "{}",
+            selectors
+        );
+    }
+}
+
+#[test]
+fn structural_pseudo_classes() {
+    check(
+        LIST,
+        &[
+            (":first-child", &["u", "l1", "i1"]),
+            (":last-child", &["u", "i1", "l5"]),
+            (":only-child", &["u", "i1"]),
+            ("b:first-of-type", &["b1"]),
+            ("b:last-of-type", &["b2"]),
+            ("i:only-of-type", &["i1"]),
+            // Comments don't count, whitespace does
+            (":empty", &["l1", "l2", "b1", "i1", "b2"]),
+        ],
+    );
+
+    let (document, _) = html_parser::parse(LIST);
+    let root = matching::query_selector_all(&document, document.root(), ":root").unwrap();
+
+    assert_eq!(root, [document.document_element().unwrap()]);
+}
+
+#[test]
+fn logical_pseudo_classes() {
+    check(
+        LIST,
+        &[
+            ("li:not(:empty)", &["l3", "l4", "l5"]),
+            ("ul > :not(li, b)", &[]),
+            (":is(b, i)", &["b1", "i1", "b2"]),
+            (":where(#b1, #l1)", &["l1", "b1"]),
+            ("li:has(i)", &["l4"]),
+            ("ul:has(> b)", &["u"]),
+            ("ul:has(> i)", &[]),
+            ("li:has(+ b)", &["l2", "l4"]),
+            ("li:has(~ b)", &["l1", "l2", "l3", "l4"]),
+            ("ul > :not(:has(i))", &["l1", "l2", "b1", "l3", "b2", "l5"]),
+        ],
+    );
+}
+
+/// Only the element with the id is hovered
+struct Hovered(&'static str);
+
+impl StateProvider for Hovered {
+    fn has_state(&self, document: &Document, id: NodeId, state: ElementState) -> bool {
+        state == ElementState::Hover
+            && document[id]
+                .as_element()
+                .is_some_and(|element| element.get_attribute("id") == Some(self.0))
+    }
+}
+
+#[test]
+fn element_states_come_from_the_provider() {
+    let (document, _) = html_parser::parse(
+        "<!DOCTYPE html>
+        <input id=a type=checkbox checked><input id=b type=radio><input id=c checked>
+        <fieldset disabled><input id=d><legend><input id=e></legend></fieldset>
+        <select><optgroup disabled><option id=f selected></optgroup></select>
+        <a id=g href=x></a><a id=h></a>",
+    );
+
+    let default = |selectors| select(&document, selectors);
+
+    assert_eq!(default(":checked"), ["a", "f"]);
+    assert_eq!(default(":disabled"), ["d", "f"]);
+    assert_eq!(default("input:enabled"), ["a", "b", "c", "e"]);
+    assert_eq!(default(":any-link, :link"), ["g"]);
+    assert!(default(":hover, :focus, :visited").is_empty());
+
+    let selector = &css_parser::parse_selectors("a:hover").unwrap()[0];
+    let hovered: Vec<&str> = document
+        .descendants(document.root())
+        .filter(|&id| matching::matches_with(&document, &Hovered("g"), id, selector))
+        .filter_map(|id| document[id].as_element()?.get_attribute("id"))
+        .collect();
+
+    assert_eq!(hovered, ["g"]);
+}
@@ -0,0 +1,135 @@
+use web_renderer::html_parser::dom::{Document, NodeId};
+use web_renderer::html_parser::{self, Parser};
+use web_renderer::span::Span;
+
+/// The tree with the spans
+fn dump(document: &Document, id: NodeId, depth: usize, output: &mut String) {
+    let node = &document[id];
+
+    output.push_str(&format!(
+        "{}{:?} {:?}\n",
+        "  ".repeat(depth),
+        node.data,
+        node.span
+    ));
+
+    for child in document.children(id) {
+        dump(document, child, depth + 1, output);
+    }
+}
+
+/// The tree and the errors of the input fed in chunks of `size` chars
+fn parse_in_chunks(input: &str, size: usize) -> String {
+    let chars: Vec<char> = input.chars().collect();
+    let mut parser = Parser::new();
+
+    for chunk in chars.chunks(size) {
+        parser.feed(&chunk.iter().collect::<String>());
+    }
+
+    let (document, errors) = parser.end();
+    let mut output = format!("{:?}\n", document.quirks_mode);
+
+    dump(&document, document.root(), 0, &mut output);
+
+    for error in errors {
+        output.push_str(&format!(
+            "{} {} {:?}\n",
+            error.code, error.message, error.span
+        ));
+    }
+
+    output
+}
+
+#[test]
+fn the_document_does_not_depend_on_the_chunk_boundaries() {
+    let documents = [
+        "<!DOCTYPE html>\r\n<html lang=en>\r\n<p class=\"a\" id='b' hidden>x\r\ny\rz</p>",
+        "<p>&notit; &notin; &not &amp &#x41;&#65;&#x0; &#X1F600; &bogus; caf\u{e9}</p>",
+        "<a title=\"&notit;&amp;\" href=?a=1&b=2>l</a><img alt='&#x41'>",
+        "<!-- comment --><!--> <!---><!-- a -- b --!><p>x</p><!-- unclosed",
+        "<svg><![CDATA[a<b]]></svg><p><![CDATA[c]]></p>",
+        "<script><!--<script>a</script>b--></script><script>if (a<!--b) {}</script>",
+        "<title>&lt;a&gt;</title><textarea>\r\n&amp;</textarea><style></styl</style>",
+        "<table><tr><td>\u{1F600}</td></tr></table><plaintext>a</plaintext>&amp;",
+        // A CR at the end of a chunk can be followed by the LF of the next one
+        "x\r\n",
+        "x\r",
+        "<p>a\r\r\n</p>\r\n\r\n",
+        "<textarea>\r\nq</textarea><p title='\r\n'>\r\n",
+    ];
+
+    for html in documents {
+        let whole = parse_in_chunks(html, usize::MAX);
+
+        for size in 1..8 {
+            assert_eq!(
+                whole,
+                parse_in_chunks(html, size),
+                "{:?} in chunks of {}",
+                html,
+                size
+            );
+        }
+    }
+}
+
+/// The source of the span, with the line and column it starts at
+fn source(html: &str, span: Span) -> (usize, usize, &str) {
+    (
+        span.start.line,
+        span.start.column,
+        &html[span.start.offset..span.end.offset],
+    )
+}
+
+#[test]
+fn nodes_attributes_and_errors_have_source_spans() {
+    let html =
+        "<!DOCTYPE html>\n<p class=a id='\u{e9}'>\n  h\u{e9}llo <b>x</b></p><!-- c -->&bogus;</i>";
+    let (document, errors) = html_parser::parse(html);
+
+    let nodes: Vec<_> = document
+        .descendants(document.root())
+        .map(|id| source(html, document[id].span))
+        .collect();
+
+    // The implied elements get the span of the tag which implied them
+    assert_eq!(
+        nodes,
+        [
+            (1, 1, "<!DOCTYPE html>"),
+            (2, 1, "<p class=a id='\u{e9}'>"),
+            (2, 1, "<p class=a id='\u{e9}'>"),
+            (2, 1, "<p class=a id='\u{e9}'>"),
+            (2, 1, "<p class=a id='\u{e9}'>"),
+            (2, 19, "\n  h\u{e9}llo "),
+            (3, 9, "<b>"),
+            (3, 12, "x"),
+            (3, 21, "<!-- c -->"),
+            (3, 31, "&bogus;"),
+        ]
+    );
+
+    let p = document
+        .descendants(document.root())
+        .find_map(|id| document[id].as_element().filter(|e| e.name == "p"))
+        .unwrap();
+    let attrs: Vec<_> = p.attrs.iter().map(|attr| source(html, attr.span)).collect();
+
+    assert_eq!(attrs, [(2, 4, "class=a"), (2, 12, "id='\u{e9}'")]);
+
+    let errors: Vec<_> = errors
+        .iter()
+        .map(|error| (error.code, source(html, error.span)))
+        .collect();
+
+    assert_eq!(
+        errors,
+        [
+            ("unknown-named-character-reference", (3, 37, ";")),
+            ("unexpected-end-tag", (3, 38, "</i>")),
+        ]
+    );
+}
@@ -0,0 +1,443 @@
+use web_renderer::css_parser;
+use web_renderer::css_parser::interface::{Declaration, PseudoElement, Specificity};
+use web_renderer::html_parser;
+use web_renderer::html_parser::dom::Document;
+use web_renderer::style::author::AuthorStyles;
+use web_renderer::style::cascade::{self, MatchedDeclaration, Origin};
+use web_renderer::style::computed::{self, StyledNode};
+use web_renderer::style::matching::DefaultStates;
+use web_renderer::style::media::Device;
+use web_renderer::style::user_agent::UserAgentStylesheet;
+
+/// The document styled like a browser would, with the user agent and author styles
+fn style(html: &str) -> (Document, StyledNode) {
+    let (document, _) = html_parser::parse(html);
+    let author = AuthorStyles::collect(&document, document.root(), &Device::default());
+
+    let mut stylesheets = UserAgentStylesheet::builtin().stylesheets(&document);
+    stylesheets.extend(
+        author
+            .stylesheets
+            .iter()
+            .map(|stylesheet| (Origin::Author, stylesheet)),
+    );
+
+    let styled = computed::style_tree(
+        &document,
+        &DefaultStates,
+        &stylesheets,
+        &author.inline_styles,
+    );
+
+    (document, styled)
+}
+
+/// The computed value of the property on the element whose id is `x`
+fn computed(html: &str, property: &str) -> String {
+    fn find<'a>(document: &Document, node: &'a StyledNode) -> Option<&'a StyledNode> {
+        let is_x = document[node.node]
+            .as_element()
+            .is_some_and(|element| element.get_attribute("id") == Some("x"));
+
+        if is_x {
+            return Some(node);
+        }
+
+        node.children.iter().find_map(|child| find(document, child))
+    }
+
+    let (document, styled) = style(html);
+    let node = find(&document, &styled).unwrap();
+
+    node.style.get(property).unwrap().to_string()
+}
+
+/// The text of every generated box, in document order
+fn generated(html: &str) -> Vec<String> {
+    fn collect(node: &StyledNode, texts: &mut Vec<String>) {
+        let (after, before): (Vec<_>, Vec<_>) = node
+            .generated
+            .iter()
+            .partition(|generated| generated.pseudo_element == PseudoElement::After);
+
+        texts.extend(before.iter().map(|generated| generated.text.clone()));
+
+        for child in &node.children {
+            collect(child, texts);
+        }
+
+        texts.extend(after.iter().map(|generated| generated.text.clone()));
+    }
+
+    let (_, styled) = style(html);
+    let mut texts = vec![];
+
+    collect(&styled, &mut texts);
+
+    texts
+}
+
+#[test]
+fn list_items_are_numbered() {
+    assert_eq!(generated("<ol><li>a<li>b</ol>"), ["1. ", "2. "]);
+    assert_eq!(
+        generated("<ol><li>a<ol><li>b</ol><li>c</ol>"),
+        ["1. ", "1. ", "2. "]
+    );
+}
+
+#[test]
+fn list_attributes_change_the_numbers() {
+    assert_eq!(generated("<ol start=5><li>a<li>b</ol>"), ["5. ", "6. "]);
+    assert_eq!(
+        generated("<ol start=' -2x'><li>a<li>b</ol>"),
+        ["-2. ", "-1. "]
+    );
+    assert_eq!(generated("<ol start=x><li>a</ol>"), ["1. "]);
+    assert_eq!(
+        generated("<ol reversed><li>a<li>b<li>c</ol>"),
+        ["3. ", "2. ", "1. "]
+    );
+    assert_eq!(
+        generated("<ol reversed start=10><li>a<li>b</ol>"),
+        ["10. ", "9. "]
+    );
+    assert_eq!(
+        generated("<ol><li>a<li value=7>b<li>c</ol>"),
+        ["1. ", "7. ", "8. "]
+    );
+}
+
+#[test]
+fn author_counter_styles_win_over_list_attributes() {
+    assert_eq!(
+        generated("<style>ol { counter-reset: none }</style><ol start=5><li>a</ol><ol><li>b</ol>"),
+        ["1. ", "1. "]
+    );
+    assert_eq!(
+        generated("<style>li { counter-set: list-item 3 }</style><ol><li value=9>a</ol>"),
+        ["3. "]
+    );
+}
+
+#[test]
+fn declarations_sort_by_origin_importance_specificity_and_order() {
+    let normal = Declaration::new();
+    let important = Declaration {
+        important: true,
+        ..Declaration::new()
+    };
+
+    let matched = |declaration, origin, specificity: (u32, u32, u32), inline, order| {
+        let (ids, classes, types) = specificity;
+
+        MatchedDeclaration {
+            declaration,
+            origin,
+            specificity: Specificity(ids, classes, types),
+            inline,
+            order,
+        }
+    };
+
+    let mut declarations = vec![
+        matched(&normal, Origin::Author, (0, 1, 0), false, 0),
+        matched(&important, Origin::UserAgent, (0, 0, 1), false, 1),
+        matched(&normal, Origin::Author, (0, 0, 1), false, 2),
+        matched(&normal, Origin::User, (1, 0, 0), false, 3),
+        matched(&important, Origin::Author, (0, 0, 0), false, 4),
+        matched(&important, Origin::User, (0, 0, 0), false, 5),
+        matched(&normal, Origin::Author, (0, 0, 0), true, 6),
+        matched(&normal, Origin::Author, (0, 1, 0), false, 7),
+        matched(&normal, Origin::UserAgent, (1, 0, 0), false, 8),
+    ];
+
+    cascade::sort(&mut declarations);
+
+    let orders: Vec<usize> = declarations.iter().map(|d| d.order).collect();
+
+    assert_eq!(orders, [8, 3, 2, 0, 7, 6, 4, 5, 1]);
+}
+
+#[test]
+fn before_and_after_content() {
+    assert_eq!(
+        generated(
+            "<style>p::before { content: '[' attr(title) ']' } p::after { content: open-quote 'x' close-quote }</style>\
+            <p title=t>a</p>"
+        ),
+        ["[t]", "\u{201C}x\u{201D}"]
+    );
+    assert_eq!(
+        generated(
+            "<style>q::before { content: open-quote } q::after { content: close-quote }</style>\
+            <q>a<q>b</q></q>"
+        ),
+        ["\u{201C}", "\u{2018}", "\u{2019}", "\u{201D}"]
+    );
+
+    // Not generated for `none`, or when the element or pseudo-element isn't rendered
+    assert!(generated(
+        "<style>p::before { content: 'x'; display: none } p::after { content: none }</style><p>a"
+    )
+    .is_empty());
+    assert!(generated(
+        "<style>div::before { content: 'x' }</style><div style='display: none'><p></div>"
+    )
+    .is_empty());
+}
+
+#[test]
+fn counters_and_markers() {
+    assert_eq!(
+        generated(
+            "<style>h2 { counter-increment: s } h2::before { content: counter(s, upper-roman) '. ' }</style>\
+            <h2>a</h2><h2>b</h2>"
+        ),
+        ["I. ", "II. "]
+    );
+    assert_eq!(
+        generated(
+            "<style>ol { counter-reset: item } li { display: block; counter-increment: item }\
+            li::before { content: counters(item, '.') ' ' }</style>\
+            <ol><li>a<ol><li>b<li>c</ol><li>d</ol>"
+        ),
+        ["1 ", "1.1 ", "1.2 ", "2 "]
+    );
+    assert_eq!(
+        generated("<ul><li>a<ul><li>b</ul></ul>"),
+        ["\u{2022} ", "\u{25E6} "]
+    );
+    assert_eq!(
+        generated("<style>li::marker { content: '- ' }</style><ul><li>a</ul>"),
+        ["- "]
+    );
+}
+
+#[test]
+fn first_line_is_a_pseudo_element() {
+    let selectors = css_parser::parse_selectors("p::first-line, p:first-line").unwrap();
+
+    for selector in &selectors {
+        assert_eq!(selector.pseudo_element, Some(PseudoElement::FirstLine));
+        assert_eq!(selector.to_string(), "p::first-line");
+    }
+}
+
+#[test]
+fn the_cascade_picks_the_winning_declaration() {
+    let red = "rgb(255, 0, 0)";
+    let green = "rgb(0, 128, 0)";
+    let blue = "rgb(0, 0, 255)";
+
+    let cases = [
+        // Specificity, then source order
+        (
+            "<style>#x { color: red } p.a { color: blue } p { color: green }</style><p id=x class=a>",
+            red,
+        ),
+        (
+            "<style>p { color: green } p { color: blue }</style><p id=x>",
+            blue,
+        ),
+        // The style attribute wins over any selector, !important over both
+        (
+            "<style>#x#x { color: green }</style><p id=x style='color: red'>",
+            red,
+        ),
+        (
+            "<style>p { color: green !important } #x { color: blue }</style><p id=x style='color: red'>",
+            green,
+        ),
+        (
+            "<style>p { color: green !important }</style><p id=x style='color: red !important'>",
+            red,
+        ),
+    ];
+
+    for (html, expected) in cases {
+        assert_eq!(computed(html, "color"), expected, "{}", html);
+    }
+
+    // Author styles win over the user agent ones
+    assert_eq!(
+        computed(
+            "<style>b { font-weight: 100 }</style><b id=x>",
+            "font-weight"
+        ),
+        "100"
+    );
+    assert_eq!(computed("<b id=x>", "font-weight"), "700");
+}
+
+#[test]
+fn revert_and_the_css_wide_keywords() {
+    // revert in the author origin goes back to the user agent styles
+    let html = "<style>h1 { margin-top: 0; font-weight: normal } #x { margin-top: revert; font-weight: revert }</style><h1 id=x>";
+
+    assert_eq!(computed(html, "margin-top"), "21.44px");
+    assert_eq!(computed(html, "font-weight"), "700");
+
+    // revert of a property the user agent doesn't set is unset
+    let html = "<style>div { color: red } #x { color: revert; margin-left: 3px; margin-left: revert }</style><div><p id=x></div>";
+
+    assert_eq!(computed(html, "color"), "rgb(255, 0, 0)");
+    assert_eq!(computed(html, "margin-left"), "0px");
+
+    let html = "<style>div { color: red; display: inline } #x { color: initial; display: unset; margin-top: unset; font-weight: inherit }</style><div style='font-weight: bold'><p id=x></div>";
+
+    assert_eq!(computed(html, "color"), "rgb(0, 0, 0)");
+    assert_eq!(computed(html, "display"), "inline");
+    assert_eq!(computed(html, "margin-top"), "0px");
+    assert_eq!(computed(html, "font-weight"), "700");
+}
+
+#[test]
+fn values_are_computed_from_the_parent() {
+    let html = "<style>div { color: red; font-size: 20px } p { font-size: 2em; width: 3em; border-top: 1px solid }</style><div><p id=x></div>";
+
+    // Inherited, and relative to the font-size of the element or the parent
+    assert_eq!(computed(html, "color"), "rgb(255, 0, 0)");
+    assert_eq!(computed(html, "font-size"), "40px");
+    assert_eq!(computed(html, "width"), "120px");
+    assert_eq!(computed(html, "border-top-color"), "rgb(255, 0, 0)");
+
+    let html = "<style>html { font-size: 10px } p { font-size: 2rem; width: 1.5rem }</style><div style='font-size: 30px'><p id=x></div>";
+
+    assert_eq!(computed(html, "font-size"), "20px");
+    assert_eq!(computed(html, "width"), "15px");
+
+    // Floats are blockified
+    let html = "<style>p { float: left; display: inline }</style><p id=x>";
+
+    assert_eq!(computed(html, "display"), "block");
+}
+
+#[test]
+fn user_agent_styles() {
+    let cases = [
+        ("<!DOCTYPE html><body id=x>", "margin-top", "8px"),
+        ("<!DOCTYPE html><script id=x></script>", "display", "none"),
+        ("<!DOCTYPE html><p id=x hidden>", "display", "none"),
+        ("<!DOCTYPE html><span id=x>", "display", "inline"),
+        ("<!DOCTYPE html><li id=x>", "display", "list-item"),
+        ("<!DOCTYPE html><table><td id=x>", "display", "table-cell"),
+        ("<!DOCTYPE html><h1 id=x>", "font-size", "32px"),
+        ("<!DOCTYPE html><h1><b id=x>", "font-weight", "900"),
+        ("<!DOCTYPE html><pre id=x>", "white-space", "pre"),
+        ("<!DOCTYPE html><ul><ul id=x>", "list-style-type", "circle"),
+        ("<!DOCTYPE html><dialog id=x>", "display", "none"),
+        ("<!DOCTYPE html><dialog id=x open>", "display", "block"),
+    ];
+
+    for (html, property, expected) in cases {
+        assert_eq!(computed(html, property), expected, "{}", html);
+    }
+
+    // An important user agent declaration wins over the author ones
+    assert_eq!(
+        computed(
+            "<!DOCTYPE html><style>input { display: block !important }</style><input id=x type=HIDDEN>",
+            "display"
+        ),
+        "none"
+    );
+}
+
+#[test]
+fn quirks_mode_styles() {
+    assert_eq!(computed("<form id=x>", "margin-bottom"), "16px");
+    assert_eq!(
+        computed("<!DOCTYPE html><form id=x>", "margin-bottom"),
+        "0px"
+    );
+    assert_eq!(
+        computed(
+            "<body style='font-size: 20px'><table><td id=x>",
+            "font-size"
+        ),
+        "16px"
+    );
+    assert_eq!(
+        computed(
+            "<!DOCTYPE html><body style='font-size: 20px'><table><td id=x>",
+            "font-size"
+        ),
+        "20px"
+    );
+}
+
+/// The selectors of the rules from the <style> elements which apply to the device
+fn collected_rules(html: &str, device: &Device) -> Vec<String> {
+    let (document, _) = html_parser::parse(html);
+    let author = AuthorStyles::collect(&document, document.root(), device);
+
+    author
+        .stylesheets
+        .iter()
+        .flat_map(|stylesheet| &stylesheet.rules)
+        .map(|rule| rule.selectors[0].to_string())
+        .collect()
+}
+
+#[test]
+fn style_elements_are_collected_when_their_media_match() {
+    let html = "<style>a {}</style>
+        <style media=print>b {}</style>
+        <style media='screen and (min-width: 600px)'>c {}</style>
+        <style media='(max-width: 600px), print'>d {}</style>
+        <style media='not print'>e {}</style>
+        <style media='(orientation: portrait)'>f {}</style>
+        <style media='bogus, all'>g {}</style>
+        <style media=''>h {}</style>
+        <style type=text/less>i {}</style>
+        <template><style>j {}</style></template>
+        <svg><style>k {}</style></svg>";
+
+    assert_eq!(
+        collected_rules(html, &Device::default()),
+        ["a", "c", "e", "g", "h"]
+    );
+
+    let phone = Device {
+        width: 400.0,
+        height: 800.0,
+    };
+
+    assert_eq!(
+        collected_rules(html, &phone),
+        ["a", "d", "e", "f", "g", "h"]
+    );
+}
+
+#[test]
+fn style_attributes_are_collected_with_the_errors() {
+    let (document, _) = html_parser::parse(
+        "<p style='color: red; margin: 0'></p><template><p style='color: blue'></p></template>\
+        <style>a { color: }</style><b style='color'>",
+    );
+    let author = AuthorStyles::collect(&document, document.root(), &Device::default());
+
+    let mut inline: Vec<Vec<&str>> = author
+        .inline_styles
+        .values()
+        .map(|declarations| declarations.iter().map(|d| d.name.as_str()).collect())
+        .collect();
+    inline.sort();
+
+    assert_eq!(inline, [vec![], vec!["color", "margin"]]);
+    assert_eq!(author.errors.len(), 2);
+
+    // The style attribute applies to its element only
+    assert_eq!(
+        computed(
+            "<p style='color: red'><b id=x style='margin-left: 2px'>",
+            "margin-left"
+        ),
+        "2px"
+    );
+    assert_eq!(
+        computed("<div style='margin-left: 2px'><b id=x>", "margin-left"),
+        "0px"
+    );
+}
@@ -0,0 +1,770 @@
+//! Cases in the format of the html5lib tree construction tests,
+//! https://github.com/html5lib/html5lib-tests/tree/master/tree-construction
+
+use web_renderer::html_parser;
+use web_renderer::html_parser::dom::{Document, NodeId};
+use web_renderer::html_parser::interface::{Namespace, NodeData};
+
+fn dump(document: &Document, id: NodeId, depth: usize, output: &mut String) {
+    let indent = format!("| {}", "  ".repeat(depth));
+
+    match &document[id].data {
+        NodeData::Element(element) => {
+            let prefix = match element.namespace {
+                Namespace::Html => "",
+                Namespace::Svg => "svg ",
+                Namespace::MathMl => "math ",
+            };
+
+            output.push_str(&format!("{}<{}{}>\n", indent, prefix, element.name));
+
+            let mut attrs: Vec<_> = element.attrs.iter().collect();
+            attrs.sort_by(|a, b| a.name.cmp(&b.name));
+
+            for attr in attrs {
+                output.push_str(&format!("{}  {}=\"{}\"\n", indent, attr.name, attr.value));
+            }
+        }
+        NodeData::Text(text) => output.push_str(&format!("{}\"{}\"\n", indent, text)),
+        NodeData::Comment(comment) => output.push_str(&format!("{}<!-- {} -->\n", indent, comment)),
+        NodeData::Doctype(doctype) => {
+            let name = doctype.name.as_deref().unwrap_or("");
+
+            match (&doctype.public_id, &doctype.system_id) {
+                (None, None) => output.push_str(&format!("{}<!DOCTYPE {}>\n", indent, name)),
+                (public_id, system_id) => output.push_str(&format!(
+                    "{}<!DOCTYPE {} \"{}\" \"{}\">\n",
+                    indent,
+                    name,
+                    public_id.as_deref().unwrap_or(""),
+                    system_id.as_deref().unwrap_or("")
+                )),
+            }
+        }
+        NodeData::Document | NodeData::DocumentFragment => (),
+    }
+
+    for child in document.children(id) {
+        dump(document, child, depth + 1, output);
+    }
+}
+
+/// The children of the document, one node per line
+fn tree(html: &str) -> String {
+    let (document, _) = html_parser::parse(html);
+    let mut output = String::new();
+
+    for child in document.children(document.root()) {
+        dump(&document, child, 0, &mut output);
+    }
+
+    output
+}
+
+fn error_codes(html: &str) -> Vec<&'static str> {
+    let (_, errors) = html_parser::parse(html);
+
+    errors.iter().map(|error| error.code).collect()
+}
+
+/// Compares the tree of each input with the expected one, given without the
+/// indentation of the source. Text spanning several lines can't start one
+/// with whitespace
+fn check(cases: &[(&str, &str)]) {
+    for (html, expected) in cases {
+        let expected: String = expected
+            .trim()
+            .lines()
+            .map(|line| format!("{}\n", line.trim_start()))
+            .collect();
+
+        assert_eq!(tree(html), expected, "{:?}", html);
+    }
+}
+
+#[test]
+fn missing_html_head_and_body_are_implied() {
+    check(&[
+        (
+            "Test",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     "Test"
+            "#,
+        ),
+        (
+            "<!DOCTYPE html><title>t</title>x<meta charset=utf-8>",
+            r#"
+            | <!DOCTYPE html>
+            | <html>
+            |   <head>
+            |     <title>
+            |       "t"
+            |   <body>
+            |     "x"
+            |     <meta>
+            |       charset="utf-8"
+            "#,
+        ),
+        (
+            "<!-- a --><html><!-- b --><head></head> <body></body></html><!-- c -->",
+            r#"
+            | <!--  a  -->
+            | <html>
+            |   <!--  b  -->
+            |   <head>
+            |   " "
+            |   <body>
+            | <!--  c  -->
+            "#,
+        ),
+        (
+            "<body a=1><body b=2 a=3>",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     a="1"
+            |     b="2"
+            "#,
+        ),
+        (
+            "<frameset><frame></frameset>",
+            r#"
+            | <html>
+            |   <head>
+            |   <frameset>
+            |     <frame>
+            "#,
+        ),
+    ]);
+}
+
+#[test]
+fn start_tags_close_the_elements_they_cannot_be_in() {
+    check(&[
+        (
+            "<p>One<p>Two",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     <p>
+            |       "One"
+            |     <p>
+            |       "Two"
+            "#,
+        ),
+        (
+            "<ul><li>a<li>b<ol><li>c</ol></ul>",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     <ul>
+            |       <li>
+            |         "a"
+            |       <li>
+            |         "b"
+            |         <ol>
+            |           <li>
+            |             "c"
+            "#,
+        ),
+        (
+            "<dl><dt>a<dd>b<dt>c</dl>",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     <dl>
+            |       <dt>
+            |         "a"
+            |       <dd>
+            |         "b"
+            |       <dt>
+            |         "c"
+            "#,
+        ),
+        (
+            "<h1><h2>x</h2></h1><button><button>",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     <h1>
+            |     <h2>
+            |       "x"
+            |     <button>
+            |     <button>
+            "#,
+        ),
+        (
+            "<p><div>x</div></p>",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     <p>
+            |     <div>
+            |       "x"
+            |     <p>
+            "#,
+        ),
+    ]);
+}
+
+#[test]
+fn misnested_formatting_elements_are_adopted() {
+    check(&[
+        (
+            "<b><p>1</b>2</p>",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     <b>
+            |     <p>
+            |       <b>
+            |         "1"
+            |       "2"
+            "#,
+        ),
+        (
+            "<a><p>X<a>Y</a>Z</p></a>",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     <a>
+            |     <p>
+            |       <a>
+            |         "X"
+            |       <a>
+            |         "Y"
+            |       "Z"
+            "#,
+        ),
+        (
+            "<b>1<i>2</b>3</i>",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     <b>
+            |       "1"
+            |       <i>
+            |         "2"
+            |     <i>
+            |       "3"
+            "#,
+        ),
+    ]);
+}
+
+#[test]
+fn tables_imply_their_sections_and_foster_parent_the_rest() {
+    check(&[
+        (
+            "<table>x<tr><td>a</table>",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     "x"
+            |     <table>
+            |       <tbody>
+            |         <tr>
+            |           <td>
+            |             "a"
+            "#,
+        ),
+        (
+            "<table><td>x<tr><th>y</table>",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     <table>
+            |       <tbody>
+            |         <tr>
+            |           <td>
+            |             "x"
+            |         <tr>
+            |           <th>
+            |             "y"
+            "#,
+        ),
+        (
+            "<table><col><caption>c</caption><b>x</b></table>",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     <b>
+            |       "x"
+            |     <table>
+            |       <colgroup>
+            |         <col>
+            |       <caption>
+            |         "c"
+            "#,
+        ),
+    ]);
+}
+
+#[test]
+fn select_template_and_foreign_content() {
+    check(&[
+        (
+            "<select><option>a<option>b<optgroup><option>c</select>d",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     <select>
+            |       <option>
+            |         "a"
+            |       <option>
+            |         "b"
+            |       <optgroup>
+            |         <option>
+            |           "c"
+            |     "d"
+            "#,
+        ),
+        (
+            "<template><td>x</td></template>",
+            r#"
+            | <html>
+            |   <head>
+            |     <template>
+            |       <td>
+            |         "x"
+            |   <body>
+            "#,
+        ),
+        (
+            "<svg><foreignObject><p>x</svg>y",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     <svg svg>
+            |       <svg foreignObject>
+            |         <p>
+            |           "xy"
+            "#,
+        ),
+        (
+            "<math><mi><b>x</b></mi><mtext>y</mtext></math><p>z",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     <math math>
+            |       <math mi>
+            |         <b>
+            |           "x"
+            |       <math mtext>
+            |         "y"
+            |     <p>
+            |       "z"
+            "#,
+        ),
+        (
+            "<svg viewbox='0 0 1 1'><lineargradient/><b>x",
+            r#"
+            | <html>
+            |   <head>
+            |   <body>
+            |     <svg svg>
+            |       viewBox="0 0 1 1"
+            |       <svg linearGradient>
+            |     <b>
+            |       "x"
+            "#,
+        ),
+    ]);
+}
+
+#[test]
+fn void_elements_have_no_content() {
+    check(&[
+        (
+            "<!DOCTYPE html><br/><img src=a>text<input><input/>",
+            r#"
+            | <!DOCTYPE html>
+            | <html>
+            |   <head>
+            |   <body>
+            |     <br>
+            |     <img>
+            |       src="a"
+            |     "text"
+            |     <input>
+            |     <input>
+            "#,
+        ),
+        (
+            "<!DOCTYPE html><p>a<hr>b",
+            r#"
+            | <!DOCTYPE html>
+            | <html>
+            |   <head>
+            |   <body>
+            |     <p>
+            |       "a"
+            |     <hr>
+            |     "b"
+            "#,
+        ),
+        (
+            "<!DOCTYPE html><img></img></br>",
+            r#"
+            | <!DOCTYPE html>
+            | <html>
+            |   <head>
+            |   <body>
+            |     <img>
+            |     <br>
+            "#,
+        ),
+    ]);
+
+    assert!(error_codes("<!DOCTYPE html><br/><img/><input>").is_empty());
+    assert_eq!(
+        error_codes("<!DOCTYPE html><img></img></br>"),
+        ["unexpected-end-tag", "unexpected-end-tag"]
+    );
+}
+
+#[test]
+fn only_foreign_elements_can_close_themselves() {
+    check(&[
+        (
+            "<!DOCTYPE html><div/>x",
+            r#"
+            | <!DOCTYPE html>
+            | <html>
+            |   <head>
+            |   <body>
+            |     <div>
+            |       "x"
+            "#,
+        ),
+        (
+            "<!DOCTYPE html><svg><circle/><path></path></svg><math><mi/>x</math>",
+            r#"
+            | <!DOCTYPE html>
+            | <html>
+            |   <head>
+            |   <body>
+            |     <svg svg>
+            |       <svg circle>
+            |       <svg path>
+            |     <math math>
+            |       <math mi>
+            |       "x"
+            "#,
+        ),
+    ]);
+
+    assert_eq!(
+        error_codes("<!DOCTYPE html><div/>x"),
+        [
+            "non-void-html-element-start-tag-with-trailing-solidus",
+            "unclosed-elements"
+        ]
+    );
+}
+
+#[test]
+fn comments_doctypes_and_cdata_sections() {
+    check(&[
+        (
+            "<!DOCTYPE html><!-- a -- b --><!--><!---><!--x--!><!--y",
+            r#"
+            | <!DOCTYPE html>
+            | <!--  a -- b  -->
+            | <!--  -->
+            | <!--  -->
+            | <!-- x -->
+            | <!-- y -->
+            | <html>
+            |   <head>
+            |   <body>
+            "#,
+        ),
+        (
+            r#"<!doctype HTML PUBLIC "-//W3C//DTD HTML 4.01//EN" "http://www.w3.org/TR/html4/strict.dtd">"#,
+            r#"
+            | <!DOCTYPE html "-//W3C//DTD HTML 4.01//EN" "http://www.w3.org/TR/html4/strict.dtd">
+            | <html>
+            |   <head>
+            |   <body>
+            "#,
+        ),
+        (
+            "<!DOCTYPE>",
+            r#"
+            | <!DOCTYPE >
+            | <html>
+            |   <head>
+            |   <body>
+            "#,
+        ),
+        (
+            "<?xml x?><!bogus>",
+            r#"
+            | <!-- ?xml x? -->
+            | <!-- bogus -->
+            | <html>
+            |   <head>
+            |   <body>
+            "#,
+        ),
+        (
+            "<!DOCTYPE html><svg><![CDATA[a<b]]></svg><p><![CDATA[c]]>",
+            r#"
+            | <!DOCTYPE html>
+            | <html>
+            |   <head>
+            |   <body>
+            |     <svg svg>
+            |       "a<b"
+            |     <p>
+            |       <!-- [CDATA[c]] -->
+            "#,
+        ),
+    ]);
+
+    assert_eq!(
+        error_codes("<!DOCTYPE html><!-- a -- b --><!--><!---><!--x--!><!--y"),
+        [
+            "abrupt-closing-of-empty-comment",
+            "abrupt-closing-of-empty-comment",
+            "incorrectly-closed-comment",
+            "eof-in-comment"
+        ]
+    );
+    assert_eq!(
+        error_codes("<!DOCTYPE>"),
+        ["missing-doctype-name", "non-conforming-doctype"]
+    );
+    assert_eq!(
+        error_codes("<?xml x?><!bogus>"),
+        [
+            "unexpected-question-mark-instead-of-tag-name",
+            "incorrectly-opened-comment",
+            "missing-doctype"
+        ]
+    );
+    assert_eq!(
+        error_codes("<!DOCTYPE html><p><![CDATA[c]]>"),
+        ["cdata-in-html-content"]
+    );
+}
+
+#[test]
+fn character_references_are_decoded_in_text() {
+    check(&[(
+        "<!DOCTYPE html><p>&amp;&lt;&notit;&notin;&not &#x41;&#65;&#0;&#x110000;&#128;&bogus;&amp",
+        r#"
+        | <!DOCTYPE html>
+        | <html>
+        |   <head>
+        |   <body>
+        |     <p>
+        |       "&<¬it;∉¬ AA��€&bogus;&"
+        "#,
+    )]);
+
+    assert_eq!(
+        error_codes("<!DOCTYPE html>&notit;&#0;&#x110000;&#128;&bogus;&amp"),
+        [
+            "missing-semicolon-after-character-reference",
+            "null-character-reference",
+            "character-reference-outside-unicode-range",
+            "control-character-reference",
+            "unknown-named-character-reference",
+            "missing-semicolon-after-character-reference"
+        ]
+    );
+}
+
+#[test]
+fn legacy_references_are_kept_in_attribute_values() {
+    check(&[(
+        r#"<!DOCTYPE html><a href="?a=1&copy=2&amp;b&copy" title="&notin;&notit;x&lt">"#,
+        r#"
+        | <!DOCTYPE html>
+        | <html>
+        |   <head>
+        |   <body>
+        |     <a>
+        |       href="?a=1&copy=2&b©"
+        |       title="∉&notit;x<"
+        "#,
+    )]);
+}
+
+#[test]
+fn attribute_values_keep_their_case() {
+    check(&[(
+        r#"<!DOCTYPE html><DIV ID=Foo CLASS="Bar Baz" data-X=YES title='A&AMP;B'>"#,
+        r#"
+        | <!DOCTYPE html>
+        | <html>
+        |   <head>
+        |   <body>
+        |     <div>
+        |       class="Bar Baz"
+        |       data-x="YES"
+        |       id="Foo"
+        |       title="A&B"
+        "#,
+    )]);
+}
+
+#[test]
+fn raw_text_and_rcdata_elements() {
+    check(&[
+        (
+            "<!DOCTYPE html><script>a<b></script><style><p>&amp;</style><title><b>&amp;</b></title><textarea></textarea>x</textarea>",
+            r#"
+            | <!DOCTYPE html>
+            | <html>
+            |   <head>
+            |     <script>
+            |       "a<b>"
+            |     <style>
+            |       "<p>&amp;"
+            |     <title>
+            |       "<b>&</b>"
+            |   <body>
+            |     <textarea>
+            |     "x"
+            "#,
+        ),
+        (
+            "<!DOCTYPE html><xmp><p></xmp><iframe><b></iframe><noembed><i></noembed><noframes>&amp;</noframes>",
+            r#"
+            | <!DOCTYPE html>
+            | <html>
+            |   <head>
+            |   <body>
+            |     <xmp>
+            |       "<p>"
+            |     <iframe>
+            |       "<b>"
+            |     <noembed>
+            |       "<i>"
+            |     <noframes>
+            |       "&amp;"
+            "#,
+        ),
+        (
+            "<!DOCTYPE html><script><!--<script>x</script>y--></script><style></styl</STYLE>",
+            r#"
+            | <!DOCTYPE html>
+            | <html>
+            |   <head>
+            |     <script>
+            |       "<!--<script>x</script>y-->"
+            |     <style>
+            |       "</styl"
+            |   <body>
+            "#,
+        ),
+    ]);
+
+    assert_eq!(
+        error_codes("<!DOCTYPE html><textarea></textarea>x</textarea>"),
+        ["unexpected-end-tag"]
+    );
+    assert_eq!(
+        error_codes("<!DOCTYPE html><script><!--a"),
+        ["eof-in-script-html-comment-like-text", "unexpected-eof"]
+    );
+}
+
+#[test]
+fn newlines_are_normalized() {
+    check(&[
+        (
+            "<!DOCTYPE html><p title='a\r\nb\rc'>a\r\nb\rc\n\r</p>",
+            "
+            | <!DOCTYPE html>
+            | <html>
+            |   <head>
+            |   <body>
+            |     <p>
+            |       title=\"a\nb\nc\"
+            |       \"a\nb\nc\n\n\"
+            ",
+        ),
+        (
+            "<!DOCTYPE html><pre>\r\nx</pre>",
+            r#"
+            | <!DOCTYPE html>
+            | <html>
+            |   <head>
+            |   <body>
+            |     <pre>
+            |       "x"
+            "#,
+        ),
+    ]);
+}
+
+#[test]
+fn null_and_control_characters() {
+    check(&[
+        (
+            "<!DOCTYPE html><p x\0='\0'>a\0b<svg>c\0</svg><script>\0</script><textarea>\0</textarea>",
+            "
+            | <!DOCTYPE html>
+            | <html>
+            |   <head>
+            |   <body>
+            |     <p>
+            |       x\u{FFFD}=\"\u{FFFD}\"
+            |       \"ab\"
+            |       <svg svg>
+            |         \"c\u{FFFD}\"
+            |       <script>
+            |         \"\u{FFFD}\"
+            |       <textarea>
+            |         \"\u{FFFD}\"
+            ",
+        ),
+        // Reported but kept
+        (
+            "<!DOCTYPE html>a\u{1}b\u{7f}c\u{fdd0}d",
+            "
+            | <!DOCTYPE html>
+            | <html>
+            |   <head>
+            |   <body>
+            |     \"a\u{1}b\u{7f}c\u{fdd0}d\"
+            ",
+        ),
+    ]);
+
+    assert_eq!(
+        error_codes("<!DOCTYPE html><p x\0='\0'>a\0b<svg>c\0</svg>"),
+        ["unexpected-null-character"; 4]
+    );
+    assert_eq!(
+        error_codes("<!DOCTYPE html>a\u{1}b\u{7f}c\u{fdd0}d\u{10ffff}"),
+        [
+            "control-character-in-input-stream",
+            "control-character-in-input-stream",
+            "noncharacter-in-input-stream",
+            "noncharacter-in-input-stream"
+        ]
+    );
+}